@@ -0,0 +1,37 @@
+#!/usr/bin/env python3
+"""Write reference all-MiniLM-L6-v2 embeddings for tests/onnx_golden.rs.
+
+Needs `pip install sentence-transformers`. The sentences are read from the
+fixture itself, so adding one means editing the JSON and rerunning this:
+
+    python3 scripts/golden-embeddings.py tests/fixtures/minilm-golden.json
+"""
+
+import json
+import sys
+
+from sentence_transformers import SentenceTransformer
+
+MODEL = "sentence-transformers/all-MiniLM-L6-v2"
+
+
+def main(path):
+    with open(path) as f:
+        fixture = json.load(f)
+
+    model = SentenceTransformer(MODEL)
+    model.max_seq_length = fixture["max_seq_length"]
+    texts = [case["text"] for case in fixture["cases"]]
+    embeddings = model.encode(texts, normalize_embeddings=True)
+
+    fixture["model"] = MODEL
+    for case, embedding in zip(fixture["cases"], embeddings):
+        case["embedding"] = [round(float(x), 7) for x in embedding]
+
+    with open(path, "w") as f:
+        json.dump(fixture, f, indent=2)
+        f.write("\n")
+
+
+if __name__ == "__main__":
+    main(sys.argv[1] if len(sys.argv) > 1 else "tests/fixtures/minilm-golden.json")
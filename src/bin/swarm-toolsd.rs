@@ -0,0 +1,105 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Resident daemon that keeps swarm-tools' engines loaded between hook events.
+#[derive(Debug, Parser)]
+#[command(name = "swarm-toolsd", version)]
+struct Args {
+    /// Socket to listen on; defaults to SWARM_TOOLS_SOCKET, then
+    /// $XDG_RUNTIME_DIR/swarm-tools.sock.
+    #[arg(long, global = true)]
+    socket: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Listen for hook events (the default).
+    Serve {
+        /// Config profile used for the engines' initial settings.
+        #[arg(long)]
+        profile: Option<String>,
+        /// Skip loading the embedding model; loop detection and routing then
+        /// work as they do in-process.
+        #[arg(long)]
+        no_semantic: bool,
+    },
+    /// Print the running daemon's status as JSON.
+    Status,
+    /// Ask the running daemon to exit.
+    Stop,
+}
+
+#[cfg(unix)]
+fn main() -> ExitCode {
+    use swarm_tools::cli::{EXIT_FAILURE, EXIT_SUCCESS};
+
+    let args = Args::parse();
+    match run(args) {
+        Ok(()) => ExitCode::from(EXIT_SUCCESS as u8),
+        Err(e) => {
+            eprintln!("swarm-toolsd: {}", e);
+            ExitCode::from(EXIT_FAILURE as u8)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn run(args: Args) -> Result<(), swarm_tools::daemon::DaemonError> {
+    use std::sync::Arc;
+    use swarm_tools::config_loader::ConfigLoader;
+    use swarm_tools::daemon::{default_socket_path, Daemon, DaemonClient, DaemonError};
+    use swarm_tools::embedder::select_embedder;
+    use swarm_tools::hook_handlers::SharedEngines;
+    use swarm_tools::model_store::ModelStore;
+
+    let socket_path = args.socket.unwrap_or_else(default_socket_path);
+    let command = args.command.unwrap_or(Command::Serve {
+        profile: None,
+        no_semantic: false,
+    });
+
+    match command {
+        Command::Serve {
+            profile,
+            no_semantic,
+        } => {
+            let config = ConfigLoader::new().with_profile(profile).load_or_default();
+            let store = ModelStore::resolve(&config.model_store);
+            let embedder = if no_semantic {
+                None
+            } else {
+                select_embedder(&config.embedder, &store, &[])
+            };
+            let daemon = Daemon::new(SharedEngines::new(embedder, &config), &socket_path);
+            let listener = daemon.bind()?;
+            eprintln!(
+                "swarm-toolsd: listening on {} (embedder {})",
+                socket_path.display(),
+                daemon.status().embedder.as_deref().unwrap_or("not loaded")
+            );
+            Arc::new(daemon).serve(listener)?;
+        }
+        Command::Status => {
+            let status = DaemonClient::new(&socket_path).status()?;
+            let json = serde_json::to_string_pretty(&status)
+                .map_err(|e| DaemonError::Protocol(e.to_string()))?;
+            println!("{}", json);
+        }
+        Command::Stop => {
+            DaemonClient::new(&socket_path).shutdown()?;
+            println!("Stopped the daemon on {}", socket_path.display());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn main() -> ExitCode {
+    let _ = Args::parse();
+    eprintln!("swarm-toolsd: Unix domain sockets are not available on this platform; hooks run in-process");
+    ExitCode::from(swarm_tools::cli::EXIT_FAILURE as u8)
+}
@@ -0,0 +1,389 @@
+//! The `swarm-tools` command line.
+//!
+//! `src/main.rs` only parses arguments. Every subcommand is implemented here
+//! so hooks and utilities share config loading, state paths, file limits and
+//! exit codes.
+
+use crate::config::SwarmToolsConfig;
+use crate::config_loader::{ConfigError, ConfigLoader, LoadedConfig};
+use crate::embedder::select_embedder;
+use crate::enhanced_monitor::TrajectoryCompression;
+use crate::feature_config::PromptRoutingConfig;
+use crate::hook_handlers::{self, HookContext, HookOptions, PromptRouting};
+use crate::hook_io::{HookInput, HookIoError, HookOutput};
+use crate::model_store::{FileReport, FileState, ModelDirSource, ModelError, ModelStore};
+use crate::role_feedback::{read_labelled, LabelledPrompt, RoleFeedback, RoutingEvaluation};
+use crate::role_router::RoleRouter;
+use crate::savings_ledger::{SavingsLedger, SavingsSummary, LEDGER_FILE, ROTATED_LEDGER_FILE};
+use crate::security::{read_file_with_limit, MAX_FILE_SIZE};
+use crate::session_state::{
+    load_session, session_dir, SessionSummary, ARCHIVE_DIR, LEGACY_DIRS, SESSIONS_DIR,
+};
+use crate::types::{AgentRole, CompressedTrajectory, Plan, TrajectoryLog};
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+pub const EXIT_SUCCESS: i32 = 0;
+/// Failure Claude Code reports to the user without blocking anything.
+pub const EXIT_FAILURE: i32 = 1;
+pub use crate::hook_io::EXIT_BLOCKING_ERROR;
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Hook(#[from] HookIoError),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Model(#[from] ModelError),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// Configuration and state locations for commands run outside a hook.
+pub struct Workspace {
+    pub loaded: LoadedConfig,
+    pub project_dir: PathBuf,
+    pub state_dir: PathBuf,
+}
+
+impl Workspace {
+    /// Loads the configuration for `project_dir`, failing on invalid config
+    /// rather than falling back to defaults.
+    pub fn load(project_dir: &Path, profile: Option<String>) -> Result<Self, CliError> {
+        let loaded = ConfigLoader::new()
+            .with_project_dir(project_dir)
+            .with_profile(profile)
+            .load()?;
+        let state_dir = project_dir.join(&loaded.config.shared_config.config_dir);
+        Ok(Self {
+            loaded,
+            project_dir: project_dir.to_path_buf(),
+            state_dir,
+        })
+    }
+
+    pub fn config(&self) -> &SwarmToolsConfig {
+        &self.loaded.config
+    }
+}
+
+/// Handles one hook event: reads the payload from `input`, checks it is the
+/// `expected_event`, and writes the decision to `output`.
+///
+/// The event goes to the daemon when one is listening on a socket owned by
+/// and private to the current user, and is handled in-process otherwise.
+pub fn run_hook(
+    expected_event: &str,
+    profile: Option<String>,
+    role: Option<AgentRole>,
+    input: impl Read,
+    output: impl Write,
+) -> Result<(), CliError> {
+    let input = HookInput::read_from(input)?;
+    if input.event.name() != expected_event {
+        return Err(CliError::Invalid(format!(
+            "expected a {} event but received {}",
+            expected_event,
+            input.event.name()
+        )));
+    }
+
+    let options = HookOptions::from_env(profile, role);
+    handle_hook(&input, &options).write_to(output)?;
+    Ok(())
+}
+
+fn handle_hook(input: &HookInput, options: &HookOptions) -> HookOutput {
+    #[cfg(unix)]
+    {
+        use crate::daemon::{default_socket_path, DaemonClient};
+
+        let socket_path = default_socket_path();
+        if socket_path.exists() {
+            match DaemonClient::new(socket_path).hook(input, options) {
+                Ok(output) => return output,
+                Err(e) => eprintln!("Warning: {}; handling the event in-process", e),
+            }
+        }
+    }
+    hook_handlers::dispatch(&HookContext::load(input, options), input)
+}
+
+/// Totals across every session and the tool-savings ledger.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatsReport {
+    pub sessions: usize,
+    pub activity: SessionSummary,
+    pub savings: SavingsSummary,
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Sessions: {}", self.sessions)?;
+        writeln!(
+            f,
+            "Tool calls: {} ({} failed, {} repeated)",
+            self.activity.tool_calls,
+            self.activity.failed_tool_calls,
+            self.activity.repeated_tool_calls
+        )?;
+        writeln!(f, "Trajectory tokens: {}", self.activity.trajectory_tokens)?;
+        writeln!(
+            f,
+            "Routing decisions: {} ({} denied, {} trimmed)",
+            self.savings.decisions, self.savings.denied, self.savings.modified
+        )?;
+        write!(f, "Estimated tokens saved: {}", self.savings.tokens_saved)?;
+        for (tool, tokens) in &self.savings.tokens_saved_by_tool {
+            write!(f, "\n  {}: {}", tool, tokens)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn stats(workspace: &Workspace) -> StatsReport {
+    let mut report = StatsReport {
+        savings: SavingsLedger::new(&workspace.state_dir).summary(),
+        ..Default::default()
+    };
+
+    let Ok(entries) = fs::read_dir(workspace.state_dir.join(SESSIONS_DIR)) else {
+        return report;
+    };
+    for entry in entries.flatten() {
+        if !entry.path().is_dir() {
+            continue;
+        }
+        report.sessions += 1;
+        let Some(summary) = load_session(&entry.path()).and_then(|record| record.summary) else {
+            continue;
+        };
+        let total = &mut report.activity;
+        total.tool_calls += summary.tool_calls;
+        total.failed_tool_calls += summary.failed_tool_calls;
+        total.repeated_tool_calls += summary.repeated_tool_calls;
+        total.trajectory_tokens += summary.trajectory_tokens;
+        total.denied_tool_calls += summary.denied_tool_calls;
+        total.tokens_saved += summary.tokens_saved;
+    }
+    report
+}
+
+/// Suggests a role, team and model for a task.
+pub fn route(workspace: &Workspace, task: &str) -> PromptRouting {
+    let config = workspace.config();
+    PromptRouting::analyze(config, &config.role_router(), task)
+}
+
+/// Formats a routing suggestion without its plan.
+pub fn format_route(routing: &PromptRouting) -> String {
+    let settings = PromptRoutingConfig {
+        include_plan: false,
+        ..PromptRoutingConfig::default()
+    };
+    routing.render(&settings).join("\n")
+}
+
+/// Records that `task` should have been routed to `role`; returns the
+/// feedback file.
+pub fn label_role(workspace: &Workspace, task: &str, role: AgentRole) -> Result<PathBuf, CliError> {
+    let role = known_role(workspace, role)?;
+    let feedback = RoleFeedback::new(&workspace.state_dir);
+    feedback.record(&LabelledPrompt {
+        prompt: task.to_string(),
+        role,
+    })?;
+    Ok(feedback.path().to_path_buf())
+}
+
+/// The corrections semantic routing currently learns from.
+pub fn export_roles(workspace: &Workspace) -> Vec<LabelledPrompt> {
+    RoleFeedback::new(&workspace.state_dir)
+        .exemplars(workspace.config().role_prototypes.max_exemplars_per_role)
+}
+
+/// Routes every prompt in the labelled JSONL file at `path` and compares
+/// the roles with the labels.
+///
+/// Routing uses the configured embedder, loading the model if `auto` picks
+/// it, and the recorded corrections unless `with_feedback` is false.
+pub fn eval_roles(
+    workspace: &Workspace,
+    path: &Path,
+    with_feedback: bool,
+) -> Result<RoutingEvaluation, CliError> {
+    let examples = read_labelled(path).map_err(|e| CliError::Invalid(e.to_string()))?;
+    let config = workspace.config();
+    let corpus: Vec<String> = examples.iter().map(|e| e.prompt.clone()).collect();
+    let store = ModelStore::resolve(&config.model_store);
+    let mut router = match select_embedder(&config.embedder, &store, &corpus) {
+        Some(embedder) => RoleRouter::with_embedder(config.role_router_keywords.clone(), embedder)
+            .with_routing(config.role_routing.clone())
+            .with_custom_roles(&config.custom_roles),
+        None => config.role_router(),
+    };
+    if with_feedback && config.role_prototypes.enabled {
+        router = router.with_exemplars(&export_roles(workspace), config.role_prototypes.strategy);
+    }
+    Ok(RoutingEvaluation::run(&router, &examples))
+}
+
+/// Codifies a task into a plan for `role`, or for the role it routes to.
+pub fn plan(workspace: &Workspace, task: &str, role: Option<AgentRole>) -> Result<Plan, CliError> {
+    let role = match role {
+        Some(role) => known_role(workspace, role)?,
+        None => workspace.config().role_router().route_task(task),
+    };
+    Ok(workspace
+        .config()
+        .codified_reasoning()
+        .codify_prompt(task, role.as_str()))
+}
+
+/// `role`, if it is built in or declared under `custom_roles`.
+fn known_role(workspace: &Workspace, role: AgentRole) -> Result<AgentRole, CliError> {
+    workspace
+        .config()
+        .resolve_role(role.as_str())
+        .map_err(CliError::Invalid)
+}
+
+pub fn format_plan(plan: &Plan) -> String {
+    let mut lines = vec![format!(
+        "{} step(s), ~{} tokens",
+        plan.steps.len(),
+        plan.total_expected_tokens
+    )];
+    for step in &plan.steps {
+        lines.push(format!(
+            "  {}. {} {} -> {} (priority {:.2}, ~{} tokens)",
+            step.step_number,
+            step.action,
+            step.target,
+            step.expected_outcome,
+            step.priority,
+            step.expected_tokens
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Compresses a trajectory file, keeping high-impact and successful entries.
+pub fn compress(workspace: &Workspace, path: &Path) -> Result<CompressedTrajectory, CliError> {
+    let content = read_file_with_limit(path, MAX_FILE_SIZE)
+        .map_err(|e| CliError::Invalid(format!("{}: {}", path.display(), e)))?;
+    let trajectory: TrajectoryLog = serde_json::from_str(&content)
+        .map_err(|e| CliError::Invalid(format!("{}: {}", path.display(), e)))?;
+    Ok(workspace
+        .config()
+        .enhanced_monitor()
+        .compress_trajectory(&trajectory))
+}
+
+/// Model directory and the state of each model file in it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelsReport {
+    /// Registry id of the model the files belong to.
+    pub model: String,
+    pub dir: PathBuf,
+    pub source: ModelDirSource,
+    pub offline: bool,
+    pub files: Vec<FileReport>,
+}
+
+impl ModelsReport {
+    /// True when every file is present and none contradicts its digest.
+    pub fn is_complete(&self) -> bool {
+        self.files
+            .iter()
+            .all(|file| matches!(file.state, FileState::Verified | FileState::Unpinned))
+    }
+}
+
+impl fmt::Display for ModelsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Model {} in {} (from {})",
+            self.model,
+            self.dir.display(),
+            self.source
+        )?;
+        if self.offline {
+            write!(f, ", offline")?;
+        }
+        for file in &self.files {
+            let state = match file.state {
+                FileState::Verified => "verified",
+                FileState::Unpinned => "unpinned",
+                FileState::Mismatch => "MISMATCH",
+                FileState::Missing => "missing",
+            };
+            write!(f, "\n  {:<16} {:<9}", file.name, state)?;
+            if let Some(sha256) = &file.sha256 {
+                write!(f, " {}", sha256)?;
+            }
+            if file.state == FileState::Mismatch {
+                if let Some(expected) = &file.expected {
+                    write!(f, " (expected {})", expected)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the model files, downloading missing or mismatched ones first
+/// when `fetch` is set; `force` downloads every file again.
+pub fn models(workspace: &Workspace, fetch: bool, force: bool) -> Result<ModelsReport, CliError> {
+    let store = ModelStore::resolve(&workspace.config().model_store);
+    let files = if fetch {
+        store.fetch(force)?
+    } else {
+        store.verify()?
+    };
+    Ok(ModelsReport {
+        model: store.model().to_string(),
+        dir: store.dir().to_path_buf(),
+        source: store.source(),
+        offline: store.is_offline(),
+        files,
+    })
+}
+
+/// Returns the state that `reset` would remove: one session, or every
+/// session, archive, legacy per-agent directory and the savings ledger.
+///
+/// The project config override in the same directory is never included.
+pub fn reset_targets(workspace: &Workspace, session: Option<&str>) -> Vec<PathBuf> {
+    let candidates = match session {
+        Some(session_id) => vec![session_dir(&workspace.state_dir, session_id)],
+        None => [SESSIONS_DIR, ARCHIVE_DIR, LEDGER_FILE, ROTATED_LEDGER_FILE]
+            .into_iter()
+            .chain(LEGACY_DIRS)
+            .map(|name| workspace.state_dir.join(name))
+            .collect(),
+    };
+    candidates
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
+}
+
+pub fn reset(targets: &[PathBuf]) -> Result<(), CliError> {
+    for path in targets {
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
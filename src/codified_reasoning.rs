@@ -1,17 +1,30 @@
+use crate::feature_config::CustomRoleConfig;
 use crate::types::{Plan, PlanStep, StepStatus};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Weights used when scoring and ordering plan steps.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct CodifiedReasoningConfig {
+    /// How step urgency is derived.
     pub urgency_source: UrgencySource,
+    /// Weight of a step's contribution in its priority.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub contribution_weight: f64,
+    /// Weight of a step's urgency in its priority.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub urgency_weight: f64,
+    /// Weight of role impact in a step's score.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub impact_weight: f64,
+    /// Token estimate for steps whose action gives no better hint.
+    #[schemars(range(min = 1))]
     pub default_step_tokens: u32,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+/// `Position` ranks earlier steps as more urgent; `Deadline` and `Custom` use a fixed urgency.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum UrgencySource {
     Position,
     Deadline,
@@ -67,6 +80,14 @@ impl CodifiedReasoning {
         }
     }
 
+    /// Weighs the plan steps of each custom role by its `impact`.
+    pub fn with_custom_roles(mut self, roles: &BTreeMap<String, CustomRoleConfig>) -> Self {
+        for (name, custom) in roles {
+            self.role_impact_map.insert(name.clone(), custom.impact);
+        }
+        self
+    }
+
     pub fn codify_prompt(&self, free_form_plan: &str, target_role: &str) -> Plan {
         let steps = self.parse_into_steps(free_form_plan);
         let total_steps = steps.len() as u32;
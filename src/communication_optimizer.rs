@@ -1,5 +1,7 @@
 use crate::config::CommunicationPatternsConfig;
+use crate::config_validation::REGEX_COMPILE_TIMEOUT;
 use crate::role_router::{RoleContext, RoleRouter};
+use crate::security::compile_regex_with_timeout;
 use crate::types::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -30,21 +32,29 @@ impl CommunicationAnalyzer {
 
     /// Creates a new CommunicationAnalyzer with custom patterns from config.
     pub fn with_config(config: CommunicationPatternsConfig) -> Result<Self> {
-        let redundancy_patterns: Result<Vec<(Regex, f64)>> = config
+        let redundancy_patterns = config
             .redundancy_patterns
-            .into_iter()
-            .map(|p| Ok((Regex::new(&p.pattern)?, p.weight)))
-            .collect();
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let path = format!("communication_patterns.redundancy_patterns[{}].pattern", i);
+                Ok((compile_pattern(&path, &p.pattern)?, p.weight))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-        let irrelevance_patterns: Result<Vec<(Regex, f64)>> = config
+        let irrelevance_patterns = config
             .irrelevance_patterns
-            .into_iter()
-            .map(|p| Ok((Regex::new(&p.pattern)?, p.weight)))
-            .collect();
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let path = format!("communication_patterns.irrelevance_patterns[{}].pattern", i);
+                Ok((compile_pattern(&path, &p.pattern)?, p.weight))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         Ok(Self {
-            redundancy_patterns: redundancy_patterns?,
-            irrelevance_patterns: irrelevance_patterns?,
+            redundancy_patterns,
+            irrelevance_patterns,
         })
     }
 
@@ -748,7 +758,7 @@ impl CommunicationOptimizer {
 
         let role_context = self
             .role_router
-            .filter_context(&messages_with_impact, target_role);
+            .filter_context(&messages_with_impact, target_role.clone());
 
         let mut messages_to_include = Vec::new();
         let mut messages_to_exclude = Vec::new();
@@ -813,3 +823,9 @@ impl Default for CommunicationOptimizer {
         Self::new().unwrap()
     }
 }
+
+/// Compiles a configured pattern, naming the offending config field on failure.
+fn compile_pattern(path: &str, pattern: &str) -> Result<Regex> {
+    compile_regex_with_timeout(pattern, REGEX_COMPILE_TIMEOUT)
+        .ok_or_else(|| format!("{}: invalid or unsafe regex {:?}", path, pattern).into())
+}
@@ -1,14 +1,49 @@
+use crate::codified_reasoning::{CodifiedReasoning, CodifiedReasoningConfig};
+use crate::communication_optimizer::CommunicationAnalyzer;
+use crate::config_validation::{validate_config, ValidationIssue};
+use crate::cost_benefit::CostBenefitAnalyzer;
+use crate::enhanced_monitor::EnhancedMonitor;
+use crate::feature_config::{
+    CustomRoleConfig, EmbedderConfig, McpRoutingConfig, ModelStoreConfig, ModelTieringConfig,
+    PromptRoutingConfig, RolePrototypesConfig, RoleRoutingConfig, SelfHealingConfig,
+    SessionRetentionConfig, SharedConfigSettings, ToolLoopConfig, VectorIndexConfig,
+};
+use crate::loop_detector::LoopDetector;
+use crate::mcp_router::McpRouter;
+use crate::model_tier::ModelTierer;
+use crate::quality_gate::QualityGate;
+use crate::role_router::RoleRouter;
+use crate::self_healing::SelfHealingManager;
+use crate::team_optimizer::{TaskAnalyzer, TeamOptimizer};
+use crate::trajectory_compressor::{TrajectoryCompressor, TrajectoryCompressorConfig};
+use crate::types::{AgentRole, Result, SwarmConfig};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
 
 /// Quality gate configuration for output scoring and refinement decisions.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct QualityGateConfig {
+    /// Whether outputs are scored at all.
     pub enabled: bool,
+    /// Score (0-100) an output needs to pass the gate.
+    #[schemars(range(min = 0.0, max = 100.0))]
     pub minimum_threshold: f64,
+    /// Weight of the agent's role impact. The five weights should sum to 1.0.
+    #[schemars(range(min = 0.0))]
     pub impact_weight: f64,
+    /// Weight of the agent's measured contribution.
+    #[schemars(range(min = 0.0))]
     pub contribution_weight: f64,
+    /// Weight of output completeness.
+    #[schemars(range(min = 0.0))]
     pub completeness_weight: f64,
+    /// Weight of output coherence.
+    #[schemars(range(min = 0.0))]
     pub coherence_weight: f64,
+    /// Weight of the penalty for leftover TODOs.
+    #[schemars(range(min = 0.0))]
     pub todo_penalty_weight: f64,
 }
 
@@ -27,21 +62,29 @@ impl Default for QualityGateConfig {
 }
 
 /// Communication analyzer patterns configuration.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct CommunicationPatternsConfig {
+    /// Patterns marking a message as repeating earlier content.
     pub redundancy_patterns: Vec<RedundancyPatternConfig>,
+    /// Patterns marking a message as low-value chatter.
     pub irrelevance_patterns: Vec<IrrelevancePatternConfig>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct RedundancyPatternConfig {
+    /// Regex matched against the lowercased message content.
     pub pattern: String,
+    /// Redundancy score contributed by a match.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub weight: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct IrrelevancePatternConfig {
+    /// Regex matched against the lowercased message content.
     pub pattern: String,
+    /// Irrelevance score contributed by a match.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub weight: f64,
 }
 
@@ -109,12 +152,22 @@ impl Default for CommunicationPatternsConfig {
 }
 
 /// Cost-benefit analysis weights configuration.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct CostBenefitWeightsConfig {
+    /// Weight of token spend on the cost side.
+    #[schemars(range(min = 0.0))]
     pub tokens_weight: f64,
+    /// Weight of elapsed time on the cost side.
+    #[schemars(range(min = 0.0))]
     pub time_weight: f64,
+    /// Weight of output quality on the benefit side.
+    #[schemars(range(min = 0.0))]
     pub quality_weight: f64,
+    /// Weight of iteration count on the cost side.
+    #[schemars(range(min = 0.0))]
     pub iteration_weight: f64,
+    /// Weight of agent contribution on the benefit side.
+    #[schemars(range(min = 0.0))]
     pub contribution_weight: f64,
 }
 
@@ -131,14 +184,22 @@ impl Default for CostBenefitWeightsConfig {
 }
 
 /// Role router keywords configuration for role-specific filtering.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct RoleRouterKeywordsConfig {
+    /// Context keywords relevant to extractors.
     pub extractor: Vec<String>,
+    /// Context keywords relevant to analyzers.
     pub analyzer: Vec<String>,
+    /// Context keywords relevant to writers.
     pub writer: Vec<String>,
+    /// Context keywords relevant to reviewers.
     pub reviewer: Vec<String>,
+    /// Context keywords relevant to synthesizers.
     pub synthesizer: Vec<String>,
+    /// Context keywords relevant to every role.
     pub general: Vec<String>,
+    /// Maximum boost given to the most recent messages.
+    #[schemars(range(min = 1.0))]
     pub recency_multiplier_max: f64,
 }
 
@@ -196,10 +257,162 @@ impl Default for RoleRouterKeywordsConfig {
     }
 }
 
+/// A named overlay applied on top of the base configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default, JsonSchema)]
+pub struct ConfigProfile {
+    /// Profile this one builds on; its settings are applied first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Free-form note shown by tooling.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Config sections to override, in the same shape as the top-level file.
+    #[serde(flatten)]
+    pub settings: Map<String, Value>,
+}
+
+/// Pins agents whose ID matches `pattern` to a profile.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct AgentProfileRule {
+    /// Agent ID pattern; `*` matches any run of characters and `?` a single one.
+    #[schemars(length(min = 1))]
+    pub pattern: String,
+    /// Name of the profile to use for matching agents.
+    pub profile: String,
+}
+
+/// Top-level configuration covering every subsystem.
+///
+/// This is the shape of `~/.config/swarm-tools/config.json` and of the project
+/// override file. Every section is optional in JSON; missing sections and fields
+/// keep their built-in defaults. Use [`crate::config_loader::ConfigLoader`] to
+/// build one from all configuration layers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default, JsonSchema)]
+#[serde(default)]
+pub struct SwarmToolsConfig {
+    pub swarm: SwarmConfig,
+    pub mcp_routing: McpRoutingConfig,
+    pub model_tiering: ModelTieringConfig,
+    pub self_healing: SelfHealingConfig,
+    pub shared_config: SharedConfigSettings,
+    pub quality_gate: QualityGateConfig,
+    pub communication_patterns: CommunicationPatternsConfig,
+    pub cost_benefit_weights: CostBenefitWeightsConfig,
+    pub role_router_keywords: RoleRouterKeywordsConfig,
+    pub trajectory_compressor: TrajectoryCompressorConfig,
+    pub codified_reasoning: CodifiedReasoningConfig,
+    pub prompt_routing: PromptRoutingConfig,
+    pub role_routing: RoleRoutingConfig,
+    pub role_prototypes: RolePrototypesConfig,
+    /// User-defined roles by name; see [`SwarmToolsConfig::resolve_role`].
+    pub custom_roles: BTreeMap<String, CustomRoleConfig>,
+    pub session_retention: SessionRetentionConfig,
+    pub model_store: ModelStoreConfig,
+    pub embedder: EmbedderConfig,
+    pub vector_index: VectorIndexConfig,
+    pub tool_loops: ToolLoopConfig,
+    /// Named overlays; see [`crate::config_profiles`].
+    pub profiles: BTreeMap<String, ConfigProfile>,
+    /// Profile used when neither the agent ID nor the caller picks one.
+    pub active_profile: Option<String>,
+    /// Agent ID patterns pinned to profiles; the first match wins.
+    pub agent_profiles: Vec<AgentProfileRule>,
+}
+
+impl SwarmToolsConfig {
+    /// Returns every problem in this configuration; see [`validate_config`].
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        validate_config(self)
+    }
+
+    /// Whether `role` is built in or declared under `custom_roles`.
+    pub fn knows_role(&self, role: &AgentRole) -> bool {
+        match role {
+            AgentRole::Custom(name) => self.custom_roles.contains_key(name),
+            _ => true,
+        }
+    }
+
+    /// Parses a role name, accepting custom roles only when declared.
+    pub fn resolve_role(&self, name: &str) -> std::result::Result<AgentRole, String> {
+        AgentRole::from_name(name)
+            .ok()
+            .filter(|role| self.knows_role(role))
+            .ok_or_else(|| format!("Unknown agent role: {}", name))
+    }
+
+    pub fn loop_detector(&self) -> LoopDetector {
+        LoopDetector::new(&self.swarm)
+            .with_vector_index(self.vector_index.clone())
+            .with_tool_loops(self.tool_loops.clone())
+    }
+
+    pub fn mcp_router(&self) -> McpRouter {
+        McpRouter::with_config(self.mcp_routing.clone()).with_custom_roles(&self.custom_roles)
+    }
+
+    pub fn model_tierer(&self) -> ModelTierer {
+        ModelTierer::with_config(self.model_tiering.clone()).with_custom_roles(&self.custom_roles)
+    }
+
+    pub fn self_healing_manager(&self) -> SelfHealingManager {
+        SelfHealingManager::with_config(self.self_healing.clone())
+    }
+
+    pub fn quality_gate(&self) -> QualityGate {
+        QualityGate::with_config(self.quality_gate.clone())
+    }
+
+    pub fn communication_analyzer(&self) -> Result<CommunicationAnalyzer> {
+        CommunicationAnalyzer::with_config(self.communication_patterns.clone())
+    }
+
+    pub fn cost_benefit_analyzer(&self) -> CostBenefitAnalyzer {
+        CostBenefitAnalyzer::with_config(self.cost_benefit_weights.clone())
+    }
+
+    pub fn role_router(&self) -> RoleRouter {
+        RoleRouter::with_config(self.role_router_keywords.clone())
+            .with_routing(self.role_routing.clone())
+            .with_custom_roles(&self.custom_roles)
+    }
+
+    pub fn trajectory_compressor(&self) -> TrajectoryCompressor {
+        TrajectoryCompressor::with_config(self.trajectory_compressor.clone())
+    }
+
+    pub fn codified_reasoning(&self) -> CodifiedReasoning {
+        CodifiedReasoning::with_config(self.codified_reasoning.clone())
+            .with_custom_roles(&self.custom_roles)
+    }
+
+    pub fn task_analyzer(&self) -> TaskAnalyzer {
+        TaskAnalyzer::new().with_custom_roles(&self.custom_roles)
+    }
+
+    pub fn team_optimizer(&self) -> TeamOptimizer {
+        TeamOptimizer::new().with_custom_roles(&self.custom_roles)
+    }
+
+    pub fn enhanced_monitor(&self) -> EnhancedMonitor {
+        EnhancedMonitor::new(self.swarm.context_budget)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_swarm_tools_config_partial_json() {
+        let config: SwarmToolsConfig =
+            serde_json::from_str(r#"{"quality_gate": {"enabled": false, "minimum_threshold": 50.0, "impact_weight": 0.3, "contribution_weight": 0.25, "completeness_weight": 0.2, "coherence_weight": 0.15, "todo_penalty_weight": 0.1}}"#)
+                .unwrap();
+        assert!(!config.quality_gate.enabled);
+        assert_eq!(config.model_tiering, ModelTieringConfig::default());
+        assert_eq!(config.swarm, SwarmConfig::default());
+    }
+
     #[test]
     fn test_quality_gate_config_default() {
         let config = QualityGateConfig::default();
@@ -0,0 +1,531 @@
+use crate::config::SwarmToolsConfig;
+use crate::config_profiles::{profile_chain, select_profile, PROFILE_ENV_VAR, PROFILE_KEYS};
+use crate::config_schema::{unknown_keys, SCHEMA_KEY};
+use crate::config_validation::{IssueSeverity, ValidationIssue};
+use crate::security::read_file_with_limit;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Maximum size of a configuration file we are willing to read.
+pub const MAX_CONFIG_FILE_SIZE: usize = 1024 * 1024; // 1MB
+
+/// Prefix for environment variable overrides.
+pub const ENV_PREFIX: &str = "SWARM_TOOLS_";
+
+/// Separator between path segments in environment variable overrides.
+///
+/// `SWARM_TOOLS_MODEL_TIERING__SIMPLE_HAIKU_THRESHOLD=2000` sets
+/// `model_tiering.simple_haiku_threshold`. Variables without the separator
+/// are reserved for other settings and never treated as overrides.
+pub const ENV_PATH_SEPARATOR: &str = "__";
+
+/// Environment variable that turns on strict mode when set to `1` or `true`.
+pub const STRICT_ENV_VAR: &str = "SWARM_TOOLS_STRICT";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Could not read config file {path}: {message}")]
+    Read { path: String, message: String },
+    #[error("Invalid JSON in config file {path}: {message}")]
+    Parse { path: String, message: String },
+    #[error("Config file {path} must contain a JSON object at the top level")]
+    NotAnObject { path: String },
+    #[error("Merged configuration does not match the expected shape: {0}")]
+    Shape(String),
+    #[error("Invalid configuration:\n{}", format_issues(.0))]
+    Invalid(Vec<ValidationIssue>),
+    #[error("Could not apply configuration: {0}")]
+    Apply(String),
+    #[error("Invalid profile selection: {0}")]
+    Profile(String),
+}
+
+fn format_issues(issues: &[ValidationIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("  {}", issue))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Where a configuration layer came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfigSourceKind {
+    Defaults,
+    UserFile,
+    ProjectFile,
+    Profile,
+    Environment,
+}
+
+/// A single layer that contributed to the merged configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigSource {
+    pub kind: ConfigSourceKind,
+    /// File path for file layers, profile name for profiles, variable names for
+    /// the environment layer.
+    pub origin: String,
+}
+
+/// The merged configuration plus the layers that produced it.
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: SwarmToolsConfig,
+    pub sources: Vec<ConfigSource>,
+    /// Non-fatal validation findings; errors make [`ConfigLoader::load`] fail instead.
+    pub warnings: Vec<ValidationIssue>,
+    /// The profile that was applied, if any.
+    pub profile: Option<String>,
+}
+
+/// Builds a [`SwarmToolsConfig`] from layered sources.
+///
+/// Layers are deep-merged in this order, later layers winning:
+/// 1. Built-in defaults
+/// 2. The user file (`~/.config/swarm-tools/config.json`)
+/// 3. The project file (`<project>/<shared_config.config_dir>/<shared_config.override_file>`)
+/// 4. The selected profile, see [`crate::config_profiles`]
+/// 5. `SWARM_TOOLS_<SECTION>__<FIELD>` environment variables
+///
+/// Objects merge key by key; arrays and scalars replace the previous value.
+///
+/// Keys that match no config field are reported as warnings, or as errors in
+/// strict mode. A top-level `$schema` key is allowed in every file.
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    user_path: Option<PathBuf>,
+    project_dir: PathBuf,
+    env_vars: Option<Vec<(String, String)>>,
+    strict: Option<bool>,
+    profile: Option<String>,
+    agent_id: Option<String>,
+}
+
+impl ConfigLoader {
+    /// Creates a loader using the standard user path, the current directory as
+    /// project root and the process environment.
+    pub fn new() -> Self {
+        Self {
+            user_path: default_user_config_path(),
+            project_dir: PathBuf::from("."),
+            env_vars: None,
+            strict: None,
+            profile: None,
+            agent_id: None,
+        }
+    }
+
+    /// Selects a profile explicitly, overriding `SWARM_TOOLS_PROFILE` and
+    /// `active_profile` but not agent pins.
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Sets the agent the configuration is for, so `agent_profiles` can apply.
+    pub fn with_agent_id(mut self, agent_id: impl Into<String>) -> Self {
+        self.agent_id = Some(agent_id.into());
+        self
+    }
+
+    /// Rejects unknown keys instead of warning about them.
+    ///
+    /// When not set explicitly, strict mode follows `SWARM_TOOLS_STRICT`.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = Some(strict);
+        self
+    }
+
+    /// Overrides the user config file location (`None` skips the user layer).
+    pub fn with_user_path(mut self, path: Option<PathBuf>) -> Self {
+        self.user_path = path;
+        self
+    }
+
+    /// Sets the project root the project override file is resolved against.
+    pub fn with_project_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.project_dir = dir.into();
+        self
+    }
+
+    /// Uses the given variables instead of the process environment.
+    pub fn with_env_vars<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env_vars = Some(
+            vars.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        );
+        self
+    }
+
+    pub fn user_path(&self) -> Option<&Path> {
+        self.user_path.as_deref()
+    }
+
+    /// Resolves the project override file path from the given shared settings.
+    pub fn project_path(&self, config: &SwarmToolsConfig) -> PathBuf {
+        self.project_dir
+            .join(&config.shared_config.config_dir)
+            .join(&config.shared_config.override_file)
+    }
+
+    /// Loads and merges every layer, then validates the result.
+    ///
+    /// Validation errors are returned as [`ConfigError::Invalid`] with every
+    /// offending path; warnings are kept in [`LoadedConfig::warnings`].
+    pub fn load(&self) -> Result<LoadedConfig, ConfigError> {
+        let mut merged = serde_json::to_value(SwarmToolsConfig::default())
+            .map_err(|e| ConfigError::Shape(e.to_string()))?;
+        let mut sources = vec![ConfigSource {
+            kind: ConfigSourceKind::Defaults,
+            origin: "built-in".to_string(),
+        }];
+        let vars = self.vars();
+        let unknown_severity = if self.is_strict(&vars) {
+            IssueSeverity::Error
+        } else {
+            IssueSeverity::Warning
+        };
+        let mut unknown = Vec::new();
+
+        if let Some(user_path) = &self.user_path {
+            if let Some(layer) = read_layer(user_path)? {
+                let origin = user_path.display().to_string();
+                unknown.extend(unknown_keys(&layer, &origin, unknown_severity));
+                deep_merge(&mut merged, layer);
+                sources.push(ConfigSource {
+                    kind: ConfigSourceKind::UserFile,
+                    origin: user_path.display().to_string(),
+                });
+            }
+        }
+
+        // The user file may relocate the project override, so resolve it
+        // against what has been merged so far.
+        let shared: SwarmToolsConfig = from_merged(&merged)?;
+        if shared.shared_config.enabled {
+            let project_path = self.project_path(&shared);
+            if let Some(layer) = read_layer(&project_path)? {
+                let origin = project_path.display().to_string();
+                unknown.extend(unknown_keys(&layer, &origin, unknown_severity));
+                deep_merge(&mut merged, layer);
+                sources.push(ConfigSource {
+                    kind: ConfigSourceKind::ProjectFile,
+                    origin: project_path.display().to_string(),
+                });
+            }
+        }
+
+        let base: SwarmToolsConfig = from_merged(&merged)?;
+        for (name, profile) in &base.profiles {
+            let overlay = Value::Object(profile.settings.clone());
+            unknown.extend(
+                unknown_keys(&overlay, &format!("profile `{}`", name), unknown_severity)
+                    .into_iter()
+                    .map(|mut issue| {
+                        issue.path = format!("profiles.{}.{}", name, issue.path);
+                        issue
+                    }),
+            );
+        }
+
+        let env_profile = vars
+            .iter()
+            .find(|(k, _)| k == PROFILE_ENV_VAR)
+            .map(|(_, v)| v.as_str());
+        let profile = select_profile(
+            &base,
+            self.agent_id.as_deref(),
+            self.profile.as_deref(),
+            env_profile,
+        );
+        if let Some(name) = &profile {
+            for link in profile_chain(&base, name).map_err(ConfigError::Profile)? {
+                let settings = &base.profiles[&link].settings;
+                if let Some(key) = PROFILE_KEYS.iter().find(|k| settings.contains_key(**k)) {
+                    return Err(ConfigError::Profile(format!(
+                        "profile `{}` cannot set `{}`",
+                        link, key
+                    )));
+                }
+                deep_merge(&mut merged, Value::Object(settings.clone()));
+            }
+            sources.push(ConfigSource {
+                kind: ConfigSourceKind::Profile,
+                origin: name.clone(),
+            });
+        }
+
+        let (env_layer, env_keys) = env_layer(&vars);
+        if !env_keys.is_empty() {
+            unknown.extend(unknown_keys(
+                &env_layer,
+                "environment variables",
+                unknown_severity,
+            ));
+            deep_merge(&mut merged, env_layer);
+            sources.push(ConfigSource {
+                kind: ConfigSourceKind::Environment,
+                origin: env_keys.join(","),
+            });
+        }
+
+        let config = from_merged(&merged)?;
+        let (errors, warnings): (Vec<_>, Vec<_>) = unknown
+            .into_iter()
+            .chain(config.validate())
+            .partition(|i| i.is_error());
+        if !errors.is_empty() {
+            return Err(ConfigError::Invalid(errors));
+        }
+
+        Ok(LoadedConfig {
+            config,
+            sources,
+            warnings,
+            profile,
+        })
+    }
+
+    /// Loads the configuration, falling back to defaults with a warning on error.
+    ///
+    /// Intended for hooks, which must never fail just because a config file is broken.
+    pub fn load_or_default(&self) -> SwarmToolsConfig {
+        match self.load() {
+            Ok(loaded) => {
+                for warning in &loaded.warnings {
+                    eprintln!("Warning: {}", warning);
+                }
+                loaded.config
+            }
+            Err(e) => {
+                eprintln!("Warning: Using default configuration: {}", e);
+                SwarmToolsConfig::default()
+            }
+        }
+    }
+
+    fn vars(&self) -> Vec<(String, String)> {
+        match &self.env_vars {
+            Some(vars) => vars.clone(),
+            None => std::env::vars().collect(),
+        }
+    }
+
+    fn is_strict(&self, vars: &[(String, String)]) -> bool {
+        self.strict.unwrap_or_else(|| {
+            vars.iter()
+                .any(|(k, v)| k == STRICT_ENV_VAR && matches!(v.as_str(), "1" | "true"))
+        })
+    }
+}
+
+fn env_layer(vars: &[(String, String)]) -> (Value, Vec<String>) {
+    let mut layer = Value::Object(Map::new());
+    let mut keys = Vec::new();
+
+    for (key, raw) in vars {
+        let Some(path) = env_key_to_path(key) else {
+            continue;
+        };
+        set_path(&mut layer, &path, parse_env_value(raw));
+        keys.push(key.clone());
+    }
+
+    keys.sort();
+    (layer, keys)
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns `$XDG_CONFIG_HOME/swarm-tools/config.json`, falling back to
+/// `~/.config/swarm-tools/config.json` (or `%APPDATA%` on Windows).
+pub fn default_user_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("swarm-tools").join("config.json"))
+}
+
+/// Recursively merges `overlay` into `base`.
+///
+/// Objects are merged key by key; any other value in `overlay` replaces the
+/// corresponding value in `base`.
+pub fn deep_merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn read_layer(path: &Path) -> Result<Option<Value>, ConfigError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content =
+        read_file_with_limit(path, MAX_CONFIG_FILE_SIZE).map_err(|e| ConfigError::Read {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+
+    let mut value: Value = serde_json::from_str(&content).map_err(|e| ConfigError::Parse {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+
+    if !value.is_object() {
+        return Err(ConfigError::NotAnObject {
+            path: path.display().to_string(),
+        });
+    }
+
+    // `$schema` only exists for editors.
+    if let Some(map) = value.as_object_mut() {
+        map.remove(SCHEMA_KEY);
+    }
+
+    Ok(Some(value))
+}
+
+fn from_merged(merged: &Value) -> Result<SwarmToolsConfig, ConfigError> {
+    serde_json::from_value(merged.clone()).map_err(|e| ConfigError::Shape(e.to_string()))
+}
+
+fn env_key_to_path(key: &str) -> Option<Vec<String>> {
+    let rest = key.strip_prefix(ENV_PREFIX)?;
+    if !rest.contains(ENV_PATH_SEPARATOR) {
+        return None;
+    }
+
+    let path: Vec<String> = rest
+        .split(ENV_PATH_SEPARATOR)
+        .map(|segment| segment.to_lowercase())
+        .collect();
+
+    if path.iter().any(|segment| segment.is_empty()) {
+        return None;
+    }
+
+    Some(path)
+}
+
+/// Environment values are parsed as JSON when possible (numbers, booleans,
+/// arrays, objects) and otherwise taken as plain strings.
+fn parse_env_value(raw: &str) -> Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+fn set_path(root: &mut Value, path: &[String], value: Value) {
+    let mut current = root;
+    for segment in &path[..path.len() - 1] {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        current = current
+            .as_object_mut()
+            .unwrap()
+            .entry(segment.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+
+    if !current.is_object() {
+        *current = Value::Object(Map::new());
+    }
+    current
+        .as_object_mut()
+        .unwrap()
+        .insert(path[path.len() - 1].clone(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_deep_merge_objects_and_scalars() {
+        let mut base = json!({"a": {"b": 1, "c": [1, 2]}, "d": true});
+        deep_merge(&mut base, json!({"a": {"c": [3]}, "e": "new"}));
+        assert_eq!(
+            base,
+            json!({"a": {"b": 1, "c": [3]}, "d": true, "e": "new"})
+        );
+    }
+
+    #[test]
+    fn test_env_key_to_path() {
+        assert_eq!(
+            env_key_to_path("SWARM_TOOLS_MODEL_TIERING__SIMPLE_HAIKU_THRESHOLD"),
+            Some(vec![
+                "model_tiering".to_string(),
+                "simple_haiku_threshold".to_string()
+            ])
+        );
+        assert_eq!(env_key_to_path("SWARM_TOOLS_PROFILE"), None);
+        assert_eq!(env_key_to_path("OTHER__VALUE"), None);
+        assert_eq!(env_key_to_path("SWARM_TOOLS_SWARM____X"), None);
+    }
+
+    #[test]
+    fn test_parse_env_value() {
+        assert_eq!(parse_env_value("2000"), json!(2000));
+        assert_eq!(parse_env_value("false"), json!(false));
+        assert_eq!(parse_env_value("claude-opus"), json!("claude-opus"));
+    }
+
+    #[test]
+    fn test_load_defaults_only() {
+        let loaded = ConfigLoader::new()
+            .with_user_path(None)
+            .with_project_dir("/nonexistent-swarm-tools-project")
+            .with_env_vars(Vec::<(String, String)>::new())
+            .load()
+            .unwrap();
+        assert_eq!(loaded.config, SwarmToolsConfig::default());
+        assert_eq!(loaded.sources.len(), 1);
+        assert_eq!(loaded.sources[0].kind, ConfigSourceKind::Defaults);
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let loaded = ConfigLoader::new()
+            .with_user_path(None)
+            .with_project_dir("/nonexistent-swarm-tools-project")
+            .with_env_vars(vec![
+                ("SWARM_TOOLS_MODEL_TIERING__SIMPLE_HAIKU_THRESHOLD", "1234"),
+                ("SWARM_TOOLS_SELF_HEALING__AUTO_PRUNE_ENABLED", "true"),
+                ("SWARM_TOOLS_STRICT", "0"),
+            ])
+            .load()
+            .unwrap();
+        assert_eq!(loaded.config.model_tiering.simple_haiku_threshold, 1234);
+        assert!(loaded.config.self_healing.auto_prune_enabled);
+        assert_eq!(
+            loaded.sources.last().unwrap().kind,
+            ConfigSourceKind::Environment
+        );
+    }
+}
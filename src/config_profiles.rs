@@ -0,0 +1,176 @@
+//! Named configuration profiles.
+//!
+//! A profile is a partial config stored under `profiles.<name>` that is
+//! deep-merged over the base configuration (defaults, user file, project
+//! file). A profile may `extends` another one, which is applied first.
+//!
+//! The profile for a run is picked in this order:
+//! 1. The first `agent_profiles` rule whose pattern matches the agent ID
+//! 2. An explicit choice, e.g. a hook's `--profile` argument
+//! 3. The `SWARM_TOOLS_PROFILE` environment variable
+//! 4. `active_profile` from the config files
+
+use crate::config::SwarmToolsConfig;
+
+/// Environment variable naming the profile to use.
+pub const PROFILE_ENV_VAR: &str = "SWARM_TOOLS_PROFILE";
+
+/// Top-level keys that select profiles and therefore cannot appear inside one.
+pub const PROFILE_KEYS: [&str; 3] = ["profiles", "active_profile", "agent_profiles"];
+
+/// Picks the profile to apply, or `None` to use the base configuration.
+pub fn select_profile(
+    config: &SwarmToolsConfig,
+    agent_id: Option<&str>,
+    explicit: Option<&str>,
+    env: Option<&str>,
+) -> Option<String> {
+    let pinned = agent_id.and_then(|id| {
+        config
+            .agent_profiles
+            .iter()
+            .find(|rule| matches_agent_pattern(&rule.pattern, id))
+            .map(|rule| rule.profile.as_str())
+    });
+
+    pinned
+        .or(explicit)
+        .or(env)
+        .or(config.active_profile.as_deref())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
+/// Returns the profiles to apply for `name`, most basic first.
+///
+/// Fails if `name` or anything it extends is undefined, or if the `extends`
+/// chain loops back on itself.
+pub fn profile_chain(config: &SwarmToolsConfig, name: &str) -> Result<Vec<String>, String> {
+    let mut chain: Vec<String> = Vec::new();
+    let mut current = Some(name.to_string());
+
+    while let Some(profile_name) = current {
+        if chain.contains(&profile_name) {
+            chain.push(profile_name);
+            return Err(format!("profile cycle: {}", chain.join(" -> ")));
+        }
+        let Some(profile) = config.profiles.get(&profile_name) else {
+            return Err(match chain.last() {
+                Some(child) => format!(
+                    "profile `{}` extends unknown profile `{}`",
+                    child, profile_name
+                ),
+                None => format!("unknown profile `{}`", profile_name),
+            });
+        };
+        chain.push(profile_name);
+        current = profile.extends.clone();
+    }
+
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Matches an agent ID against a pattern where `*` matches any run of
+/// characters (including none) and `?` matches exactly one.
+pub fn matches_agent_pattern(pattern: &str, agent_id: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = agent_id.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, star_t)) = backtrack {
+            p = star + 1;
+            t = star_t + 1;
+            backtrack = Some((star, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AgentProfileRule, ConfigProfile};
+
+    fn config_with_profiles() -> SwarmToolsConfig {
+        let mut config = SwarmToolsConfig::default();
+        config
+            .profiles
+            .insert("coding".to_string(), ConfigProfile::default());
+        config.profiles.insert(
+            "research".to_string(),
+            ConfigProfile {
+                extends: Some("coding".to_string()),
+                ..Default::default()
+            },
+        );
+        config.agent_profiles.push(AgentProfileRule {
+            pattern: "research-*".to_string(),
+            profile: "research".to_string(),
+        });
+        config.active_profile = Some("coding".to_string());
+        config
+    }
+
+    #[test]
+    fn test_matches_agent_pattern() {
+        assert!(matches_agent_pattern("research-*", "research-1"));
+        assert!(matches_agent_pattern("*-writer", "docs-writer"));
+        assert!(matches_agent_pattern("agent-?", "agent-7"));
+        assert!(matches_agent_pattern("*", ""));
+        assert!(matches_agent_pattern("a*b*c", "aXXbYYc"));
+        assert!(!matches_agent_pattern("agent-?", "agent-12"));
+        assert!(!matches_agent_pattern("research-*", "coder-1"));
+    }
+
+    #[test]
+    fn test_select_profile_precedence() {
+        let config = config_with_profiles();
+        assert_eq!(
+            select_profile(&config, Some("research-2"), Some("other"), Some("env")),
+            Some("research".to_string())
+        );
+        assert_eq!(
+            select_profile(&config, Some("coder-1"), Some("other"), Some("env")),
+            Some("other".to_string())
+        );
+        assert_eq!(
+            select_profile(&config, None, None, Some("env")),
+            Some("env".to_string())
+        );
+        assert_eq!(
+            select_profile(&config, None, None, None),
+            Some("coding".to_string())
+        );
+    }
+
+    #[test]
+    fn test_profile_chain() {
+        let mut config = config_with_profiles();
+        assert_eq!(
+            profile_chain(&config, "research").unwrap(),
+            vec!["coding".to_string(), "research".to_string()]
+        );
+        assert!(profile_chain(&config, "missing")
+            .unwrap_err()
+            .contains("unknown profile"));
+
+        config.profiles.get_mut("coding").unwrap().extends = Some("research".to_string());
+        assert!(profile_chain(&config, "research")
+            .unwrap_err()
+            .contains("cycle"));
+    }
+}
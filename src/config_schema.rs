@@ -0,0 +1,306 @@
+use crate::config::SwarmToolsConfig;
+use crate::config_loader::deep_merge;
+use crate::config_validation::{IssueSeverity, ValidationIssue};
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec};
+use schemars::schema_for;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// Location of the checked-in schema, relative to the repository root.
+pub const SCHEMA_PATH: &str = "schema/swarm-tools.schema.json";
+
+/// Top-level key config files may use to point editors at the schema.
+pub const SCHEMA_KEY: &str = "$schema";
+
+/// Builds the JSON Schema for [`SwarmToolsConfig`].
+///
+/// Descriptions come from the field doc comments and ranges from the
+/// `#[schemars(range(..))]` attributes. Every object rejects unknown keys so
+/// editors flag typos. Fields are optional because config files are merged
+/// over the defaults, except inside array items, which replace the default
+/// array wholesale. The top level accepts a `$schema` reference.
+pub fn config_schema() -> RootSchema {
+    let mut schema = schema_for!(SwarmToolsConfig);
+
+    let array_items = array_item_definitions(&schema);
+    for (name, definition) in schema.definitions.iter_mut() {
+        if let Schema::Object(object) = definition {
+            restrict_object(object, !array_items.contains(name));
+        }
+    }
+    restrict_object(&mut schema.schema, true);
+
+    schema.schema.metadata().description = Some(
+        "swarm-tools configuration. Every section and field is optional; omitted values keep their built-in defaults."
+            .to_string(),
+    );
+
+    schema
+        .schema
+        .object()
+        .properties
+        .insert(SCHEMA_KEY.to_string(), string_schema());
+    schema
+}
+
+/// Returns the schema as pretty-printed JSON with a trailing newline, matching
+/// the checked-in file.
+pub fn config_schema_json() -> String {
+    let mut json = serde_json::to_string_pretty(&config_schema()).unwrap();
+    json.push('\n');
+    json
+}
+
+/// Lists keys in a config layer that do not correspond to any config field.
+///
+/// Each finding names the dotted path of the key and, when a known key is
+/// close enough, suggests it as the intended spelling. A layer whose values
+/// have the wrong type yields no findings; that is reported when the merged
+/// config is parsed.
+pub fn unknown_keys(layer: &Value, origin: &str, severity: IssueSeverity) -> Vec<ValidationIssue> {
+    let defaults = serde_json::to_value(SwarmToolsConfig::default()).unwrap();
+    let mut merged = defaults.clone();
+    deep_merge(&mut merged, layer.clone());
+
+    let mut paths = Vec::new();
+    let parsed: Result<SwarmToolsConfig, _> =
+        serde_ignored::deserialize(merged, |path| paths.push(path_segments(&path)));
+    if parsed.is_err() {
+        return Vec::new();
+    }
+
+    paths
+        .into_iter()
+        .map(|segments| {
+            let (key, parent) = segments.split_last().unwrap();
+            let suggestion = match closest_key(&defaults, parent, key) {
+                Some(known) => format!("did you mean `{}`?", known),
+                None => "remove the key".to_string(),
+            };
+            ValidationIssue {
+                severity,
+                path: join_segments(&segments),
+                value: lookup(layer, &segments).cloned().unwrap_or(Value::Null),
+                message: format!("unknown key in {}", origin),
+                suggestion,
+            }
+        })
+        .collect()
+}
+
+/// Makes a struct schema reject unknown keys and, optionally, accept missing ones.
+fn restrict_object(schema: &mut SchemaObject, allow_missing: bool) {
+    if let Some(object) = &mut schema.object {
+        // Objects with flattened maps already set `additionalProperties`.
+        if !object.properties.is_empty() && object.additional_properties.is_none() {
+            object.additional_properties = Some(Box::new(Schema::Bool(false)));
+            if allow_missing {
+                object.required.clear();
+            }
+        }
+    }
+}
+
+/// Names of definitions used as array items anywhere in the schema.
+fn array_item_definitions(schema: &RootSchema) -> HashSet<String> {
+    let objects = std::iter::once(&schema.schema).chain(schema.definitions.values().filter_map(
+        |definition| match definition {
+            Schema::Object(object) => Some(object),
+            Schema::Bool(_) => None,
+        },
+    ));
+
+    let mut names = HashSet::new();
+    for object in objects {
+        let Some(validation) = &object.object else {
+            continue;
+        };
+        for property in validation.properties.values() {
+            let Schema::Object(property) = property else {
+                continue;
+            };
+            let Some(array) = &property.array else {
+                continue;
+            };
+            if let Some(SingleOrVec::Single(item)) = &array.items {
+                if let Schema::Object(SchemaObject {
+                    reference: Some(reference),
+                    ..
+                }) = item.as_ref()
+                {
+                    if let Some(name) = reference.strip_prefix("#/definitions/") {
+                        names.insert(name.to_string());
+                    }
+                }
+            }
+        }
+    }
+    names
+}
+
+fn string_schema() -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        ..Default::default()
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn path_segments(path: &serde_ignored::Path) -> Vec<Segment> {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => Vec::new(),
+        Path::Seq { parent, index } => {
+            let mut segments = path_segments(parent);
+            segments.push(Segment::Index(*index));
+            segments
+        }
+        Path::Map { parent, key } => {
+            let mut segments = path_segments(parent);
+            segments.push(Segment::Key(key.clone()));
+            segments
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => path_segments(parent),
+    }
+}
+
+/// Formats segments the same way validation paths are written: `a.b[0].c`.
+fn join_segments(segments: &[Segment]) -> String {
+    let mut out = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Key(key) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                out.push_str(key);
+            }
+            Segment::Index(index) => out.push_str(&format!("[{}]", index)),
+        }
+    }
+    out
+}
+
+fn lookup<'a>(value: &'a Value, segments: &[Segment]) -> Option<&'a Value> {
+    segments
+        .iter()
+        .try_fold(value, |current, segment| match segment {
+            Segment::Key(key) => current.get(key),
+            Segment::Index(index) => current.get(index),
+        })
+}
+
+fn closest_key(defaults: &Value, parent: &[Segment], key: &Segment) -> Option<String> {
+    let Segment::Key(key) = key else {
+        return None;
+    };
+    // Arrays of objects share one shape, so compare against the first element.
+    let parent: Vec<Segment> = parent
+        .iter()
+        .map(|segment| match segment {
+            Segment::Index(_) => Segment::Index(0),
+            other => other.clone(),
+        })
+        .collect();
+    let known: &Map<String, Value> = lookup(defaults, &parent)?.as_object()?;
+
+    known
+        .keys()
+        .map(|candidate| (candidate, strsim::jaro_winkler(key, candidate)))
+        .filter(|(_, score)| *score >= 0.8)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(candidate, _)| candidate.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unknown_keys_with_suggestion() {
+        let layer = json!({
+            "model_tiering": {"simple_haiku_thresold": 900},
+            "communication_patterns": {"redundancy_patterns": [{"pattern": "x", "weight": 0.5, "wieght": 0.4}]},
+            "totally_unrelated": true
+        });
+        let issues = unknown_keys(&layer, "test.json", IssueSeverity::Error);
+        let mut found: Vec<(String, String)> =
+            issues.into_iter().map(|i| (i.path, i.suggestion)).collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "communication_patterns.redundancy_patterns[0].wieght".to_string(),
+                    "did you mean `weight`?".to_string()
+                ),
+                (
+                    "model_tiering.simple_haiku_thresold".to_string(),
+                    "did you mean `simple_haiku_threshold`?".to_string()
+                ),
+                (
+                    "totally_unrelated".to_string(),
+                    "remove the key".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_role_filter_keys_are_not_unknown() {
+        let layer = json!({"mcp_routing": {"role_tool_filters": {"custom_role": ["grep"]}}});
+        assert!(unknown_keys(&layer, "test.json", IssueSeverity::Error).is_empty());
+    }
+
+    #[test]
+    fn test_schema_rejects_unknown_fields() {
+        let schema = serde_json::to_value(config_schema()).unwrap();
+        assert_eq!(schema["additionalProperties"], json!(false));
+        assert_eq!(
+            schema["definitions"]["ModelTieringConfig"]["additionalProperties"],
+            json!(false)
+        );
+        assert!(schema["properties"]["$schema"].is_object());
+        assert!(schema["definitions"]["SelfHealingConfig"]
+            .get("required")
+            .is_none());
+        assert_eq!(
+            schema["definitions"]["RedundancyPatternConfig"]["required"],
+            json!(["pattern", "weight"])
+        );
+    }
+
+    #[test]
+    fn test_role_routing_bounds_match_validation() {
+        let schema = serde_json::to_value(config_schema()).unwrap();
+        let properties = &schema["definitions"]["RoleRoutingConfig"]["properties"];
+        for field in ["min_margin", "temperature"] {
+            let bounds = &properties[field];
+            let schema_accepts = |value: f64| {
+                bounds["minimum"].as_f64().is_none_or(|min| value >= min)
+                    && bounds["exclusiveMinimum"]
+                        .as_f64()
+                        .is_none_or(|min| value > min)
+                    && bounds["maximum"].as_f64().is_none_or(|max| value <= max)
+            };
+            for value in [-0.1, 0.0, 0.05, 1.0, 1.5] {
+                let mut config = SwarmToolsConfig::default();
+                match field {
+                    "min_margin" => config.role_routing.min_margin = value,
+                    _ => config.role_routing.temperature = value,
+                }
+                let path = format!("role_routing.{}", field);
+                let valid = !config.validate().iter().any(|issue| issue.path == path);
+                assert_eq!(schema_accepts(value), valid, "{} = {}", field, value);
+            }
+        }
+    }
+}
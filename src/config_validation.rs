@@ -0,0 +1,720 @@
+use crate::config::SwarmToolsConfig;
+use crate::config_profiles::profile_chain;
+use crate::loop_detector::{ACTION_HISTORY_LEN, STATE_HISTORY_LEN};
+use crate::model_store::registry;
+use crate::security::{compile_regex_with_timeout, validate_filename};
+use crate::types::{is_valid_role_name, AgentRole, MAX_ROLE_NAME_LEN};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::time::Duration;
+
+/// Time budget for compiling a single configured regex.
+pub const REGEX_COMPILE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Tolerance used when checking that a set of weights sums to 1.0.
+const WEIGHT_SUM_TOLERANCE: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IssueSeverity {
+    /// The value is unusable; loading fails.
+    Error,
+    /// The value works but is probably not what was intended.
+    Warning,
+}
+
+/// A single problem found in a configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub severity: IssueSeverity,
+    /// Dotted JSON path of the offending field, e.g. `model_tiering.moderate_sonnet_threshold`.
+    pub path: String,
+    pub value: Value,
+    pub message: String,
+    pub suggestion: String,
+}
+
+impl ValidationIssue {
+    pub fn is_error(&self) -> bool {
+        self.severity == IssueSeverity::Error
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            IssueSeverity::Error => "error",
+            IssueSeverity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}: {} = {}: {} (suggestion: {})",
+            level, self.path, self.value, self.message, self.suggestion
+        )
+    }
+}
+
+/// Checks a merged configuration and returns every problem found.
+///
+/// An empty result means the configuration is valid. Issues are reported in
+/// field order so output is stable between runs.
+pub fn validate_config(config: &SwarmToolsConfig) -> Vec<ValidationIssue> {
+    let mut v = Validator::default();
+
+    let swarm = &config.swarm;
+    v.at_least("swarm.max_parallel_agents", swarm.max_parallel_agents, 1);
+    v.at_least("swarm.context_budget", swarm.context_budget, 1);
+    v.fraction_open("swarm.context_threshold", swarm.context_threshold);
+    v.at_least("swarm.loop_exact_threshold", swarm.loop_exact_threshold, 1);
+    v.at_least(
+        "swarm.loop_semantic_threshold",
+        swarm.loop_semantic_threshold,
+        1,
+    );
+    v.at_least(
+        "swarm.loop_state_oscillation_threshold",
+        swarm.loop_state_oscillation_threshold,
+        1,
+    );
+    v.at_least("swarm.loop_cycle_repeats", swarm.loop_cycle_repeats, 2);
+    v.at_least(
+        "swarm.loop_cycle_max_period",
+        swarm.loop_cycle_max_period,
+        2,
+    );
+    v.range(
+        "swarm.loop_cycle_tolerance",
+        swarm.loop_cycle_tolerance,
+        0.0,
+        0.5,
+    );
+    if swarm.loop_cycle_max_period * swarm.loop_cycle_repeats > ACTION_HISTORY_LEN
+        || swarm.loop_cycle_max_period * swarm.loop_state_oscillation_threshold > STATE_HISTORY_LEN
+    {
+        v.warning(
+            "swarm.loop_cycle_max_period",
+            json!(swarm.loop_cycle_max_period),
+            &format!(
+                "only the last {} actions and {} states are kept, so the longest cycles are never seen repeating",
+                ACTION_HISTORY_LEN, STATE_HISTORY_LEN
+            ),
+            "lower loop_cycle_max_period, loop_cycle_repeats or loop_state_oscillation_threshold",
+        );
+    }
+    v.fraction(
+        "swarm.semantic_similarity_threshold",
+        swarm.semantic_similarity_threshold,
+    );
+
+    if let Some(filters) = &config.mcp_routing.role_tool_filters {
+        let mut roles: Vec<&String> = filters.keys().collect();
+        roles.sort();
+        for role in roles {
+            let path = format!("mcp_routing.role_tool_filters.{}", role);
+            if config.resolve_role(role).is_err() {
+                v.warning(
+                    &path,
+                    json!(role),
+                    "unknown role; this filter is never applied",
+                    "use one of extractor, analyzer, writer, reviewer, synthesizer, tester, documenter, optimizer, specialist, general, or a role declared under custom_roles",
+                );
+            }
+            if filters[role].is_empty() {
+                v.warning(
+                    &path,
+                    json!(filters[role]),
+                    "empty tool list blocks every tool except default_tools for this role",
+                    "list the tools this role needs or remove the entry",
+                );
+            }
+        }
+    }
+
+    let tiering = &config.model_tiering;
+    v.at_least(
+        "model_tiering.simple_haiku_threshold",
+        tiering.simple_haiku_threshold as usize,
+        1,
+    );
+    if tiering.moderate_sonnet_threshold <= tiering.simple_haiku_threshold {
+        v.error(
+            "model_tiering.moderate_sonnet_threshold",
+            json!(tiering.moderate_sonnet_threshold),
+            &format!(
+                "must be greater than model_tiering.simple_haiku_threshold ({})",
+                tiering.simple_haiku_threshold
+            ),
+            &format!(
+                "raise it above {} or lower simple_haiku_threshold",
+                tiering.simple_haiku_threshold
+            ),
+        );
+    }
+    v.non_empty("model_tiering.fallback_model", &tiering.fallback_model);
+
+    let healing = &config.self_healing;
+    v.fraction("self_healing.prune_threshold", healing.prune_threshold);
+    v.at_least("self_healing.prune_over_turns", healing.prune_over_turns, 1);
+    v.at_least(
+        "self_healing.min_active_agents",
+        healing.min_active_agents,
+        1,
+    );
+    v.fraction(
+        "self_healing.prune_safety_margin",
+        healing.prune_safety_margin,
+    );
+    if healing.min_active_agents > swarm.max_parallel_agents {
+        v.warning(
+            "self_healing.min_active_agents",
+            json!(healing.min_active_agents),
+            &format!(
+                "exceeds swarm.max_parallel_agents ({}), so pruning can never trigger",
+                swarm.max_parallel_agents
+            ),
+            "lower min_active_agents or raise swarm.max_parallel_agents",
+        );
+    }
+
+    let shared = &config.shared_config;
+    v.non_empty("shared_config.config_dir", &shared.config_dir);
+    if let Err(e) = validate_filename(&shared.override_file) {
+        v.error(
+            "shared_config.override_file",
+            json!(shared.override_file),
+            &e.to_string(),
+            "use a plain file name such as config_override.json",
+        );
+    }
+
+    let gate = &config.quality_gate;
+    v.range(
+        "quality_gate.minimum_threshold",
+        gate.minimum_threshold,
+        0.0,
+        100.0,
+    );
+    v.weights(
+        "quality_gate",
+        &[
+            ("impact_weight", gate.impact_weight),
+            ("contribution_weight", gate.contribution_weight),
+            ("completeness_weight", gate.completeness_weight),
+            ("coherence_weight", gate.coherence_weight),
+            ("todo_penalty_weight", gate.todo_penalty_weight),
+        ],
+        true,
+    );
+
+    let patterns = &config.communication_patterns;
+    for (i, p) in patterns.redundancy_patterns.iter().enumerate() {
+        let base = format!("communication_patterns.redundancy_patterns[{}]", i);
+        v.regex(&format!("{}.pattern", base), &p.pattern);
+        v.fraction(&format!("{}.weight", base), p.weight);
+    }
+    for (i, p) in patterns.irrelevance_patterns.iter().enumerate() {
+        let base = format!("communication_patterns.irrelevance_patterns[{}]", i);
+        v.regex(&format!("{}.pattern", base), &p.pattern);
+        v.fraction(&format!("{}.weight", base), p.weight);
+    }
+
+    let cost = &config.cost_benefit_weights;
+    v.weights(
+        "cost_benefit_weights",
+        &[
+            ("tokens_weight", cost.tokens_weight),
+            ("time_weight", cost.time_weight),
+            ("quality_weight", cost.quality_weight),
+            ("iteration_weight", cost.iteration_weight),
+            ("contribution_weight", cost.contribution_weight),
+        ],
+        false,
+    );
+
+    let keywords = &config.role_router_keywords;
+    if !keywords.recency_multiplier_max.is_finite() || keywords.recency_multiplier_max < 1.0 {
+        v.error(
+            "role_router_keywords.recency_multiplier_max",
+            json!(keywords.recency_multiplier_max),
+            "must be at least 1.0; lower values penalize recent messages",
+            "use a value between 1.0 and 3.0 (default 2.0)",
+        );
+    }
+
+    let compressor = &config.trajectory_compressor;
+    v.fraction(
+        "trajectory_compressor.preserve_threshold",
+        compressor.preserve_threshold,
+    );
+    v.at_least(
+        "trajectory_compressor.max_summaries",
+        compressor.max_summaries,
+        1,
+    );
+    v.at_least("trajectory_compressor.max_tokens", compressor.max_tokens, 1);
+    for (i, pattern) in compressor.superseded_patterns.iter().enumerate() {
+        v.regex(
+            &format!("trajectory_compressor.superseded_patterns[{}]", i),
+            pattern,
+        );
+    }
+
+    let reasoning = &config.codified_reasoning;
+    v.fraction(
+        "codified_reasoning.contribution_weight",
+        reasoning.contribution_weight,
+    );
+    v.fraction(
+        "codified_reasoning.urgency_weight",
+        reasoning.urgency_weight,
+    );
+    v.fraction("codified_reasoning.impact_weight", reasoning.impact_weight);
+    let priority_sum = reasoning.contribution_weight + reasoning.urgency_weight;
+    if (priority_sum - 1.0).abs() > WEIGHT_SUM_TOLERANCE {
+        v.warning(
+            "codified_reasoning.urgency_weight",
+            json!(reasoning.urgency_weight),
+            &format!(
+                "contribution_weight + urgency_weight = {:.2}; priorities are not on a 0-1 scale",
+                priority_sum
+            ),
+            &format!(
+                "set urgency_weight to {:.2}",
+                (1.0 - reasoning.contribution_weight).max(0.0)
+            ),
+        );
+    }
+    v.at_least(
+        "codified_reasoning.default_step_tokens",
+        reasoning.default_step_tokens as usize,
+        1,
+    );
+
+    v.at_least(
+        "prompt_routing.max_plan_steps",
+        config.prompt_routing.max_plan_steps,
+        1,
+    );
+    v.at_least(
+        "session_retention.max_age_days",
+        config.session_retention.max_age_days as usize,
+        1,
+    );
+    v.at_least(
+        "session_retention.max_sessions",
+        config.session_retention.max_sessions,
+        1,
+    );
+
+    v.fraction("role_routing.min_margin", config.role_routing.min_margin);
+    v.fraction_open("role_routing.temperature", config.role_routing.temperature);
+    v.at_least(
+        "role_prototypes.max_exemplars_per_role",
+        config.role_prototypes.max_exemplars_per_role,
+        1,
+    );
+
+    let k1 = config.embedder.bm25_k1;
+    if !k1.is_finite() || k1 < 0.0 {
+        v.error(
+            "embedder.bm25_k1",
+            json!(k1),
+            "must be zero or more",
+            "use 1.2, the usual BM25 default",
+        );
+    }
+    v.fraction("embedder.bm25_b", config.embedder.bm25_b as f64);
+    v.at_least("embedder.max_seq_length", config.embedder.max_seq_length, 1);
+
+    let index = &config.vector_index;
+    v.at_least("vector_index.flat_limit", index.flat_limit, 1);
+    v.at_least("vector_index.nprobe", index.nprobe, 1);
+    v.at_least("vector_index.max_entries", index.max_entries, 1);
+
+    let tool_loops = &config.tool_loops;
+    v.at_least("tool_loops.window_secs", tool_loops.window_secs as usize, 1);
+    v.at_least("tool_loops.threshold", tool_loops.threshold, 1);
+    for (tool, &threshold) in &tool_loops.tool_thresholds {
+        v.at_least(
+            &format!("tool_loops.tool_thresholds.{}", tool),
+            threshold,
+            1,
+        );
+    }
+
+    let models = registry(&config.model_store);
+    if !models.contains_key(&config.model_store.model) {
+        let known: Vec<&str> = models.keys().map(String::as_str).collect();
+        v.error(
+            "model_store.model",
+            json!(config.model_store.model),
+            "not in the model registry",
+            &format!(
+                "use one of {} or add it under model_store.models",
+                known.join(", ")
+            ),
+        );
+    }
+    let digests = config
+        .model_store
+        .sha256
+        .iter()
+        .map(|(name, digest)| (format!("model_store.sha256.{}", name), digest));
+    let model_digests = config.model_store.models.iter().flat_map(|(id, spec)| {
+        spec.sha256.iter().map(move |(name, digest)| {
+            (format!("model_store.models.{}.sha256.{}", id, name), digest)
+        })
+    });
+    for (path, digest) in digests.chain(model_digests) {
+        if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            v.error(
+                &path,
+                json!(digest),
+                "not a SHA-256 digest",
+                "use the 64 hex characters printed by `swarm-tools models verify`",
+            );
+        }
+    }
+    for (id, spec) in &config.model_store.models {
+        // Models other than the default get a subdirectory named after them.
+        if let Err(e) = validate_filename(id) {
+            v.error(
+                &format!("model_store.models.{}", id),
+                json!(id),
+                &e.to_string(),
+                "use a plain name such as \"my-model\"",
+            );
+        }
+        v.at_least(
+            &format!("model_store.models.{}.dimension", id),
+            spec.dimension,
+            1,
+        );
+    }
+
+    for (name, custom) in &config.custom_roles {
+        let path = format!("custom_roles.{}", name);
+        if name.parse::<AgentRole>().is_ok() {
+            v.error(
+                &path,
+                json!(name),
+                "shadows a built-in role",
+                "pick a name that is not already a role, e.g. \"infra-reviewer\"",
+            );
+        } else if !is_valid_role_name(name) {
+            v.error(
+                &path,
+                json!(name),
+                "not a valid role name",
+                &format!(
+                    "use lowercase letters, digits, '-' and '_', starting with a letter, at most {} characters",
+                    MAX_ROLE_NAME_LEN
+                ),
+            );
+        }
+        if custom.description.trim().is_empty() && custom.keywords.is_empty() {
+            v.warning(
+                &path,
+                json!(name),
+                "no description or keywords, so tasks are never routed to this role",
+                "add a description, keywords or both",
+            );
+        }
+        v.fraction(&format!("{}.impact", path), custom.impact);
+        let efficiency = custom.capabilities.efficiency;
+        if !efficiency.is_finite() || efficiency <= 0.0 {
+            v.error(
+                &format!("{}.capabilities.efficiency", path),
+                json!(efficiency),
+                "must be a positive number",
+                "use 1.0 for a role as productive as an analyzer",
+            );
+        }
+        v.at_least(
+            &format!("{}.capabilities.max_concurrent_tasks", path),
+            custom.capabilities.max_concurrent_tasks,
+            1,
+        );
+    }
+
+    for name in config.profiles.keys() {
+        if let Err(e) = profile_chain(config, name) {
+            v.error(
+                &format!("profiles.{}.extends", name),
+                json!(config.profiles[name].extends),
+                &e,
+                "point `extends` at a defined profile and avoid cycles",
+            );
+        }
+    }
+    let profile_names = || {
+        config
+            .profiles
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if let Some(active) = &config.active_profile {
+        if !config.profiles.contains_key(active) {
+            v.error(
+                "active_profile",
+                json!(active),
+                "no profile with this name is defined",
+                &format!("use one of: {}", profile_names()),
+            );
+        }
+    }
+    for (i, rule) in config.agent_profiles.iter().enumerate() {
+        v.non_empty(&format!("agent_profiles[{}].pattern", i), &rule.pattern);
+        if !config.profiles.contains_key(&rule.profile) {
+            v.error(
+                &format!("agent_profiles[{}].profile", i),
+                json!(rule.profile),
+                "no profile with this name is defined",
+                &format!("use one of: {}", profile_names()),
+            );
+        }
+    }
+
+    v.issues
+}
+
+#[derive(Default)]
+struct Validator {
+    issues: Vec<ValidationIssue>,
+}
+
+impl Validator {
+    fn push(
+        &mut self,
+        severity: IssueSeverity,
+        path: &str,
+        value: Value,
+        message: &str,
+        suggestion: &str,
+    ) {
+        self.issues.push(ValidationIssue {
+            severity,
+            path: path.to_string(),
+            value,
+            message: message.to_string(),
+            suggestion: suggestion.to_string(),
+        });
+    }
+
+    fn error(&mut self, path: &str, value: Value, message: &str, suggestion: &str) {
+        self.push(IssueSeverity::Error, path, value, message, suggestion);
+    }
+
+    fn warning(&mut self, path: &str, value: Value, message: &str, suggestion: &str) {
+        self.push(IssueSeverity::Warning, path, value, message, suggestion);
+    }
+
+    fn at_least(&mut self, path: &str, value: usize, min: usize) {
+        if value < min {
+            self.error(
+                path,
+                json!(value),
+                &format!("must be at least {}", min),
+                &format!("set it to {} or more", min),
+            );
+        }
+    }
+
+    fn range(&mut self, path: &str, value: f64, min: f64, max: f64) {
+        if !value.is_finite() || value < min || value > max {
+            self.error(
+                path,
+                json!(value),
+                &format!("must be between {} and {}", min, max),
+                &format!("use a value in [{}, {}]", min, max),
+            );
+        }
+    }
+
+    fn fraction(&mut self, path: &str, value: f64) {
+        self.range(path, value, 0.0, 1.0);
+    }
+
+    /// Like [`Validator::fraction`] but rejects zero.
+    fn fraction_open(&mut self, path: &str, value: f64) {
+        if !value.is_finite() || value <= 0.0 || value > 1.0 {
+            self.error(
+                path,
+                json!(value),
+                "must be greater than 0 and at most 1",
+                "use a value in (0, 1], e.g. 0.7",
+            );
+        }
+    }
+
+    fn non_empty(&mut self, path: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.error(path, json!(value), "must not be empty", "provide a value");
+        }
+    }
+
+    fn regex(&mut self, path: &str, pattern: &str) {
+        if compile_regex_with_timeout(pattern, REGEX_COMPILE_TIMEOUT).is_none() {
+            self.error(
+                path,
+                json!(pattern),
+                "invalid regex, or too complex to compile safely",
+                "check the syntax and avoid deeply nested or repeated groups",
+            );
+        }
+    }
+
+    /// Checks a group of weights: each must be non-negative, at least one must be
+    /// positive and, when `normalized`, they should sum to 1.0.
+    fn weights(&mut self, section: &str, weights: &[(&str, f64)], normalized: bool) {
+        let mut valid = true;
+        for (name, weight) in weights {
+            if !weight.is_finite() || *weight < 0.0 {
+                self.error(
+                    &format!("{}.{}", section, name),
+                    json!(weight),
+                    "must be a non-negative number",
+                    "use 0 to disable this factor",
+                );
+                valid = false;
+            }
+        }
+        if !valid {
+            return;
+        }
+
+        let sum: f64 = weights.iter().map(|(_, w)| w).sum();
+        let (first, _) = weights[0];
+        if sum == 0.0 {
+            self.error(
+                &format!("{}.{}", section, first),
+                json!(0.0),
+                "all weights are zero, so no score can be computed",
+                "give at least one weight a positive value",
+            );
+        } else if normalized && (sum - 1.0).abs() > WEIGHT_SUM_TOLERANCE {
+            self.warning(
+                section,
+                json!(weights
+                    .iter()
+                    .map(|(name, w)| (name.to_string(), json!(w)))
+                    .collect::<serde_json::Map<_, _>>()),
+                &format!("weights sum to {:.2} instead of 1.0", sum),
+                "scale the weights so they add up to 1.0",
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feature_config::CustomRoleConfig;
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(validate_config(&SwarmToolsConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_inverted_tier_thresholds() {
+        let mut config = SwarmToolsConfig::default();
+        config.model_tiering.moderate_sonnet_threshold = 500;
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "model_tiering.moderate_sonnet_threshold");
+        assert_eq!(issues[0].value, json!(500));
+        assert!(issues[0].is_error());
+    }
+
+    #[test]
+    fn test_bad_regex_reports_index() {
+        let mut config = SwarmToolsConfig::default();
+        config.communication_patterns.irrelevance_patterns[2].pattern = "(unclosed".to_string();
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].path,
+            "communication_patterns.irrelevance_patterns[2].pattern"
+        );
+    }
+
+    #[test]
+    fn test_quality_gate_weights() {
+        let mut config = SwarmToolsConfig::default();
+        config.quality_gate.impact_weight = 0.9;
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, IssueSeverity::Warning);
+        assert_eq!(issues[0].path, "quality_gate");
+
+        config.quality_gate.coherence_weight = -0.1;
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "quality_gate.coherence_weight");
+        assert!(issues[0].is_error());
+    }
+
+    #[test]
+    fn test_custom_roles() {
+        let mut config = SwarmToolsConfig::default();
+        config.custom_roles.insert(
+            "infra-reviewer".to_string(),
+            CustomRoleConfig {
+                description: "Review terraform and k8s manifests".to_string(),
+                ..Default::default()
+            },
+        );
+        config.mcp_routing.role_tool_filters = Some(
+            [("infra-reviewer".to_string(), vec!["Read".to_string()])]
+                .into_iter()
+                .collect(),
+        );
+        assert!(validate_config(&config).is_empty());
+
+        config
+            .custom_roles
+            .insert("reviewer".to_string(), CustomRoleConfig::default());
+        config.custom_roles.insert(
+            "Infra Ops".to_string(),
+            CustomRoleConfig {
+                keywords: vec!["ops".to_string()],
+                ..Default::default()
+            },
+        );
+        let issues = validate_config(&config);
+        let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "custom_roles.Infra Ops",
+                "custom_roles.reviewer",
+                "custom_roles.reviewer",
+            ]
+        );
+        assert!(issues[1].message.contains("built-in"));
+        assert_eq!(issues[2].severity, IssueSeverity::Warning);
+    }
+
+    #[test]
+    fn test_reports_every_issue() {
+        let mut config = SwarmToolsConfig::default();
+        config.swarm.context_threshold = 1.5;
+        config.self_healing.prune_threshold = -1.0;
+        config.trajectory_compressor.superseded_patterns = vec!["[".to_string()];
+        let paths: Vec<String> = validate_config(&config)
+            .into_iter()
+            .map(|i| i.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "swarm.context_threshold",
+                "self_healing.prune_threshold",
+                "trajectory_compressor.superseded_patterns[0]",
+            ]
+        );
+    }
+}
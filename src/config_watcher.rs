@@ -0,0 +1,260 @@
+use crate::codified_reasoning::CodifiedReasoning;
+use crate::communication_optimizer::CommunicationAnalyzer;
+use crate::config::SwarmToolsConfig;
+use crate::config_loader::{ConfigError, ConfigLoader};
+use crate::config_validation::ValidationIssue;
+use crate::cost_benefit::CostBenefitAnalyzer;
+use crate::enhanced_monitor::EnhancedMonitor;
+use crate::loop_detector::LoopDetector;
+use crate::mcp_router::McpRouter;
+use crate::model_tier::ModelTierer;
+use crate::quality_gate::QualityGate;
+use crate::role_router::RoleRouter;
+use crate::self_healing::SelfHealingManager;
+use crate::trajectory_compressor::TrajectoryCompressor;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Every manager built from one [`SwarmToolsConfig`].
+///
+/// Managers that accumulate state (loop history, contributions, cost-benefit
+/// decisions, token history) keep it across [`ManagerSet::apply`]; the rest
+/// are rebuilt.
+pub struct ManagerSet {
+    config: SwarmToolsConfig,
+    pub loop_detector: LoopDetector,
+    pub mcp_router: McpRouter,
+    pub model_tierer: ModelTierer,
+    pub self_healing: SelfHealingManager,
+    pub quality_gate: QualityGate,
+    pub communication_analyzer: CommunicationAnalyzer,
+    pub cost_benefit: CostBenefitAnalyzer,
+    pub role_router: RoleRouter,
+    pub trajectory_compressor: TrajectoryCompressor,
+    pub codified_reasoning: CodifiedReasoning,
+    pub enhanced_monitor: EnhancedMonitor,
+}
+
+impl ManagerSet {
+    pub fn from_config(config: SwarmToolsConfig) -> Result<Self, ConfigError> {
+        Ok(Self {
+            loop_detector: config.loop_detector(),
+            mcp_router: config.mcp_router(),
+            model_tierer: config.model_tierer(),
+            self_healing: config.self_healing_manager(),
+            quality_gate: config.quality_gate(),
+            communication_analyzer: build_communication_analyzer(&config)?,
+            cost_benefit: config.cost_benefit_analyzer(),
+            role_router: config.role_router(),
+            trajectory_compressor: config.trajectory_compressor(),
+            codified_reasoning: config.codified_reasoning(),
+            enhanced_monitor: config.enhanced_monitor(),
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &SwarmToolsConfig {
+        &self.config
+    }
+
+    /// Switches every manager to `config`.
+    ///
+    /// Anything that can fail is built before the first manager is touched,
+    /// so on error the set is left exactly as it was.
+    pub fn apply(&mut self, config: SwarmToolsConfig) -> Result<(), ConfigError> {
+        let communication_analyzer = build_communication_analyzer(&config)?;
+
+        self.loop_detector.update_config(&config.swarm);
+        self.loop_detector.set_tool_loops(config.tool_loops.clone());
+        self.mcp_router = config.mcp_router();
+        self.model_tierer = config.model_tierer();
+        self.self_healing.update_config(config.self_healing.clone());
+        self.quality_gate = config.quality_gate();
+        self.communication_analyzer = communication_analyzer;
+        self.cost_benefit
+            .update_config(config.cost_benefit_weights.clone());
+        self.role_router
+            .update_config(config.role_router_keywords.clone());
+        self.role_router.set_routing(config.role_routing.clone());
+        self.role_router.set_custom_roles(&config.custom_roles);
+        self.trajectory_compressor
+            .update_config(config.trajectory_compressor.clone());
+        self.codified_reasoning = config.codified_reasoning();
+        self.enhanced_monitor
+            .set_total_context(config.swarm.context_budget);
+        self.config = config;
+        Ok(())
+    }
+}
+
+fn build_communication_analyzer(
+    config: &SwarmToolsConfig,
+) -> Result<CommunicationAnalyzer, ConfigError> {
+    config
+        .communication_analyzer()
+        .map_err(|e| ConfigError::Apply(e.to_string()))
+}
+
+/// Result of one [`ConfigWatcher::poll`].
+#[derive(Debug)]
+pub enum ReloadOutcome {
+    /// No watched file changed.
+    Unchanged,
+    /// The new configuration is live; any validation warnings are included.
+    Reloaded { warnings: Vec<ValidationIssue> },
+    /// The new configuration was rejected and the previous one is still live.
+    Rejected(ConfigError),
+}
+
+/// Reloads the layered configuration when a config file changes.
+///
+/// Watches the user file and the project override file resolved from the
+/// current configuration. A reload re-runs [`ConfigLoader::load`], so the
+/// result is validated before anything is swapped; on failure the previous
+/// configuration stays in place. Readers holding the [`ManagerSet`] lock see
+/// either the old managers or the new ones, never a mix.
+pub struct ConfigWatcher {
+    loader: ConfigLoader,
+    managers: Arc<RwLock<ManagerSet>>,
+    fingerprints: Vec<(PathBuf, Option<u64>)>,
+}
+
+impl ConfigWatcher {
+    pub fn new(loader: ConfigLoader) -> Result<Self, ConfigError> {
+        let loaded = loader.load()?;
+        let managers = ManagerSet::from_config(loaded.config)?;
+        let mut watcher = Self {
+            loader,
+            managers: Arc::new(RwLock::new(managers)),
+            fingerprints: Vec::new(),
+        };
+        watcher.fingerprints = watcher.current_fingerprints();
+        Ok(watcher)
+    }
+
+    /// Shared handle to the live managers.
+    pub fn managers(&self) -> Arc<RwLock<ManagerSet>> {
+        Arc::clone(&self.managers)
+    }
+
+    /// Files whose changes trigger a reload.
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        let config = self.managers.read().unwrap().config().clone();
+        let mut paths: Vec<PathBuf> = self
+            .loader
+            .user_path()
+            .map(PathBuf::from)
+            .into_iter()
+            .collect();
+        if config.shared_config.enabled {
+            paths.push(self.loader.project_path(&config));
+        }
+        paths
+    }
+
+    /// Checks the watched files once and reloads if any of them changed.
+    pub fn poll(&mut self) -> ReloadOutcome {
+        let fingerprints = self.current_fingerprints();
+        if fingerprints == self.fingerprints {
+            return ReloadOutcome::Unchanged;
+        }
+
+        // Remember the new contents even if they are rejected, so a broken file
+        // is reported once rather than on every poll.
+        self.fingerprints = fingerprints;
+        let outcome = self.reload();
+        if matches!(outcome, ReloadOutcome::Reloaded { .. }) {
+            // The project override may have moved.
+            self.fingerprints = self.current_fingerprints();
+        }
+        outcome
+    }
+
+    /// Reloads unconditionally.
+    pub fn reload(&self) -> ReloadOutcome {
+        let loaded = match self.loader.load() {
+            Ok(loaded) => loaded,
+            Err(e) => return ReloadOutcome::Rejected(e),
+        };
+
+        let mut managers = self.managers.write().unwrap();
+        match managers.apply(loaded.config) {
+            Ok(()) => ReloadOutcome::Reloaded {
+                warnings: loaded.warnings,
+            },
+            Err(e) => ReloadOutcome::Rejected(e),
+        }
+    }
+
+    /// Polls on a background thread until the returned handle is stopped.
+    pub fn spawn(mut self, interval: Duration) -> WatchHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        let thread = std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                match self.poll() {
+                    ReloadOutcome::Unchanged => {}
+                    ReloadOutcome::Reloaded { warnings } => {
+                        for warning in warnings {
+                            eprintln!("Warning: {}", warning);
+                        }
+                    }
+                    ReloadOutcome::Rejected(e) => {
+                        eprintln!("Warning: Keeping previous configuration: {}", e);
+                    }
+                }
+                std::thread::sleep(interval);
+            }
+        });
+
+        WatchHandle {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    fn current_fingerprints(&self) -> Vec<(PathBuf, Option<u64>)> {
+        self.watched_paths()
+            .into_iter()
+            .map(|path| {
+                let fingerprint = std::fs::read(&path).ok().map(|bytes| {
+                    let mut hasher = DefaultHasher::new();
+                    bytes.hash(&mut hasher);
+                    hasher.finish()
+                });
+                (path, fingerprint)
+            })
+            .collect()
+    }
+}
+
+/// Stops a [`ConfigWatcher::spawn`] thread when dropped.
+pub struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
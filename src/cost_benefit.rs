@@ -65,6 +65,13 @@ impl CostBenefitAnalyzer {
         }
     }
 
+    /// Applies new weights while keeping the decision history.
+    pub fn update_config(&mut self, config: CostBenefitWeightsConfig) {
+        let history = std::mem::take(&mut self.history);
+        *self = Self::with_config(config);
+        self.history = history;
+    }
+
     pub fn estimate_cost(&self, action: &serde_json::Value) -> Result<f64> {
         let tokens_required = action
             .get("tokens_required")
@@ -0,0 +1,388 @@
+//! Resident daemon serving hook events over a Unix domain socket.
+//!
+//! `swarm-toolsd` keeps [`SharedEngines`] warm, so a hook does not load the
+//! semantic model or rebuild role embeddings on every event. Each message is
+//! one line of JSON: the client writes a [`Request`] and reads back a
+//! [`Response`], and a connection may carry several in turn. `swarm-tools
+//! hook` tries the daemon first and handles the event in-process when it is
+//! not running.
+
+use crate::hook_handlers::{self, HookContext, HookOptions, SharedEngines};
+use crate::hook_io::{HookInput, HookOutput};
+use crate::security::MAX_FILE_SIZE;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, DirBuilder};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Environment variable overriding the socket location.
+pub const SOCKET_ENV_VAR: &str = "SWARM_TOOLS_SOCKET";
+
+/// Socket file name inside `$XDG_RUNTIME_DIR`.
+const SOCKET_FILE: &str = "swarm-tools.sock";
+
+/// How long a hook waits on the daemon before handling the event itself.
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest request or response line accepted.
+const MAX_MESSAGE_SIZE: usize = MAX_FILE_SIZE;
+
+#[derive(Debug, Error)]
+pub enum DaemonError {
+    #[error("Daemon connection failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid daemon message: {0}")]
+    Protocol(String),
+    #[error("A daemon is already listening on {0}")]
+    AlreadyRunning(PathBuf),
+    #[error("Daemon could not handle the request: {0}")]
+    Remote(String),
+    #[error("Refusing {0}: {1}")]
+    Untrusted(PathBuf, String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Hook {
+        input: Box<HookInput>,
+        options: HookOptions,
+    },
+    Status,
+    Shutdown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Hook { output: HookOutput },
+    Status(DaemonStatus),
+    ShuttingDown,
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub socket_path: PathBuf,
+    pub uptime_secs: u64,
+    pub requests_served: u64,
+    pub semantic_loaded: bool,
+    /// Name of the embedder kept warm, if any.
+    pub embedder: Option<String>,
+    /// Agents with contributions recorded by the self-healing manager.
+    pub tracked_agents: usize,
+}
+
+/// Socket path from [`SOCKET_ENV_VAR`], else `$XDG_RUNTIME_DIR`, else a
+/// private per-user directory in the temp directory.
+pub fn default_socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(SOCKET_ENV_VAR) {
+        return PathBuf::from(path);
+    }
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+        return PathBuf::from(dir).join(SOCKET_FILE);
+    }
+    std::env::temp_dir()
+        .join(format!("swarm-tools-{}", current_uid()))
+        .join(SOCKET_FILE)
+}
+
+fn current_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    unsafe { libc::geteuid() }
+}
+
+/// Creates `dir` accessible to the current user only, or checks that an
+/// existing one belongs to the user and nobody else can write to it, so no
+/// one else can put a socket in it.
+fn ensure_private_dir(dir: &Path) -> Result<(), DaemonError> {
+    if !dir.exists() {
+        DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    }
+    let metadata = fs::metadata(dir)?;
+    if metadata.uid() != current_uid() {
+        return Err(DaemonError::Untrusted(
+            dir.to_path_buf(),
+            format!("directory is owned by uid {}", metadata.uid()),
+        ));
+    }
+    if metadata.mode() & 0o022 != 0 {
+        return Err(DaemonError::Untrusted(
+            dir.to_path_buf(),
+            format!(
+                "directory is writable by other users (mode {:o})",
+                metadata.mode() & 0o777
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Binds under a umask that makes the socket readable by the current user
+/// only from the moment it exists, instead of narrowing it after `bind`.
+fn bind_private(path: &Path) -> io::Result<UnixListener> {
+    // SAFETY: umask cannot fail, and the previous mask is restored right away.
+    let previous = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(previous) };
+    listener
+}
+
+/// Checks that the socket belongs to the current user and nobody else can
+/// connect to it, so a reply on it comes from this user's daemon.
+fn check_socket(path: &Path) -> Result<(), DaemonError> {
+    let metadata = fs::metadata(path)?;
+    if metadata.uid() != current_uid() {
+        return Err(DaemonError::Untrusted(
+            path.to_path_buf(),
+            format!("socket is owned by uid {}", metadata.uid()),
+        ));
+    }
+    if metadata.mode() & 0o077 != 0 {
+        return Err(DaemonError::Untrusted(
+            path.to_path_buf(),
+            format!(
+                "socket is accessible to other users (mode {:o})",
+                metadata.mode() & 0o777
+            ),
+        ));
+    }
+    Ok(())
+}
+
+pub struct Daemon {
+    engines: SharedEngines,
+    socket_path: PathBuf,
+    started: Instant,
+    requests_served: AtomicU64,
+    shutting_down: AtomicBool,
+}
+
+impl Daemon {
+    pub fn new(engines: SharedEngines, socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            engines,
+            socket_path: socket_path.into(),
+            started: Instant::now(),
+            requests_served: AtomicU64::new(0),
+            shutting_down: AtomicBool::new(false),
+        }
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Binds the socket, readable by the current user only, in a directory
+    /// no other user can write to. A missing directory is created private.
+    ///
+    /// A socket file nobody answers on is left over from a daemon that died
+    /// and is replaced.
+    pub fn bind(&self) -> Result<UnixListener, DaemonError> {
+        if let Some(parent) = self
+            .socket_path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            ensure_private_dir(parent)?;
+        }
+        if self.socket_path.exists() {
+            if UnixStream::connect(&self.socket_path).is_ok() {
+                return Err(DaemonError::AlreadyRunning(self.socket_path.clone()));
+            }
+            fs::remove_file(&self.socket_path)?;
+        }
+        Ok(bind_private(&self.socket_path)?)
+    }
+
+    pub fn handle(&self, request: Request) -> Response {
+        self.requests_served.fetch_add(1, Ordering::Relaxed);
+        match request {
+            Request::Hook { input, options } => {
+                let ctx = HookContext::load(&input, &options).with_engines(self.engines.clone());
+                Response::Hook {
+                    output: hook_handlers::dispatch(&ctx, &input),
+                }
+            }
+            Request::Status => Response::Status(self.status()),
+            Request::Shutdown => {
+                self.shutting_down.store(true, Ordering::SeqCst);
+                Response::ShuttingDown
+            }
+        }
+    }
+
+    pub fn status(&self) -> DaemonStatus {
+        let tracked_agents = self
+            .engines
+            .self_healing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_state()
+            .agent_turns
+            .len();
+        DaemonStatus {
+            pid: std::process::id(),
+            socket_path: self.socket_path.clone(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            requests_served: self.requests_served.load(Ordering::Relaxed),
+            semantic_loaded: self.engines.embedder.is_some(),
+            embedder: self
+                .engines
+                .embedder
+                .as_ref()
+                .map(|embedder| embedder.name().to_string()),
+            tracked_agents,
+        }
+    }
+
+    /// Serves each connection on its own thread until a [`Request::Shutdown`],
+    /// then removes the socket file.
+    pub fn serve(self: Arc<Self>, listener: UnixListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            if self.shutting_down.load(Ordering::SeqCst) {
+                break;
+            }
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("Warning: Could not accept connection: {}", e);
+                    continue;
+                }
+            };
+            let daemon = Arc::clone(&self);
+            std::thread::spawn(move || {
+                if let Err(e) = daemon.serve_connection(stream) {
+                    eprintln!("Warning: {}", e);
+                }
+            });
+        }
+        fs::remove_file(&self.socket_path)
+    }
+
+    fn serve_connection(&self, stream: UnixStream) -> Result<(), DaemonError> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+        loop {
+            let Some(line) = read_message(&mut reader)? else {
+                return Ok(());
+            };
+            let response = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => self.handle(request),
+                Err(e) => Response::Error {
+                    message: format!("Invalid request: {}", e),
+                },
+            };
+            write_message(&mut writer, &response)?;
+
+            if response == Response::ShuttingDown {
+                // Wake the accept loop so it sees the flag.
+                let _ = UnixStream::connect(&self.socket_path);
+                return Ok(());
+            }
+        }
+    }
+}
+
+pub struct DaemonClient {
+    socket_path: PathBuf,
+    timeout: Duration,
+}
+
+impl DaemonClient {
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+            timeout: CLIENT_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends one request and reads the reply, refusing a socket another user
+    /// owns or can connect to.
+    pub fn request(&self, request: &Request) -> Result<Response, DaemonError> {
+        check_socket(&self.socket_path)?;
+        let stream = UnixStream::connect(&self.socket_path)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        write_message(&stream, request)?;
+        let line = read_message(&mut BufReader::new(&stream))?
+            .ok_or_else(|| DaemonError::Protocol("connection closed".to_string()))?;
+        serde_json::from_str(&line).map_err(|e| DaemonError::Protocol(e.to_string()))
+    }
+
+    pub fn hook(
+        &self,
+        input: &HookInput,
+        options: &HookOptions,
+    ) -> Result<HookOutput, DaemonError> {
+        let request = Request::Hook {
+            input: Box::new(input.clone()),
+            options: options.clone(),
+        };
+        match self.request(&request)? {
+            Response::Hook { output } => Ok(output),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    pub fn status(&self) -> Result<DaemonStatus, DaemonError> {
+        match self.request(&Request::Status)? {
+            Response::Status(status) => Ok(status),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    pub fn shutdown(&self) -> Result<(), DaemonError> {
+        match self.request(&Request::Shutdown)? {
+            Response::ShuttingDown => Ok(()),
+            other => Err(unexpected(other)),
+        }
+    }
+}
+
+fn unexpected(response: Response) -> DaemonError {
+    match response {
+        Response::Error { message } => DaemonError::Remote(message),
+        other => DaemonError::Protocol(format!("unexpected response {:?}", other)),
+    }
+}
+
+/// Reads one line, or `None` once the peer has closed the connection.
+fn read_message(reader: &mut impl BufRead) -> Result<Option<String>, DaemonError> {
+    let mut line = String::new();
+    let read = reader
+        .take(MAX_MESSAGE_SIZE as u64 + 1)
+        .read_line(&mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.len() > MAX_MESSAGE_SIZE {
+        return Err(DaemonError::Protocol(format!(
+            "message exceeds {} bytes",
+            MAX_MESSAGE_SIZE
+        )));
+    }
+    Ok(Some(line))
+}
+
+fn write_message(mut writer: impl Write, message: &impl Serialize) -> Result<(), DaemonError> {
+    let mut line =
+        serde_json::to_string(message).map_err(|e| DaemonError::Protocol(e.to_string()))?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
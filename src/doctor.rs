@@ -0,0 +1,572 @@
+//! Environment diagnostics behind `swarm-tools doctor`.
+//!
+//! Semantic routing degrades to fallback embeddings without failing, and the
+//! hooks degrade to doing nothing when their executables are missing.
+//! [`run`] checks each link in that chain and says which one broke.
+
+use crate::cli::Workspace;
+use crate::embedder::Embedder;
+use crate::feature_config::ModelStoreConfig;
+use crate::model_store::{FileState, ModelStore};
+use crate::semantic_engine::{
+    EmbeddingBackend, SemanticEngine, DEFAULT_EMBEDDING_DIM, DEFAULT_MAX_SEQ_LENGTH,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SAMPLE_TEXT: &str = "Review this pull request for security issues";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    /// Works, but degraded or unverified.
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DoctorCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl DoctorCheck {
+    fn new(name: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Cargo features this binary was built with.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CompiledFeatures {
+    pub semantic: bool,
+    pub ort: bool,
+    pub ci: bool,
+}
+
+impl CompiledFeatures {
+    #[allow(unexpected_cfgs)]
+    pub fn current() -> Self {
+        Self {
+            semantic: cfg!(feature = "semantic"),
+            ort: cfg!(feature = "ort"),
+            ci: cfg!(feature = "ci"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DoctorReport {
+    pub features: CompiledFeatures,
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    /// True when no check failed; warnings do not count.
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Fail)
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, check) in self.checks.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            let status = match check.status {
+                CheckStatus::Ok => "ok",
+                CheckStatus::Warn => "warn",
+                CheckStatus::Fail => "FAIL",
+            };
+            write!(f, "[{}] {}: {}", status, check.name, check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs every check for `project_dir`.
+///
+/// `hooks_file` overrides where `hooks.json` is looked for; see
+/// [`find_hooks_file`]. Nothing is downloaded: the semantic engine only loads
+/// model files that are already present.
+pub fn run(project_dir: &Path, profile: Option<String>, hooks_file: Option<&Path>) -> DoctorReport {
+    let features = CompiledFeatures::current();
+    let mut checks = vec![check_features(features)];
+
+    let workspace = Workspace::load(project_dir, profile);
+    let (store, max_seq_length) = match &workspace {
+        Ok(workspace) => (
+            ModelStore::resolve(&workspace.config().model_store),
+            workspace.config().embedder.max_seq_length,
+        ),
+        Err(_) => (
+            ModelStore::resolve(&ModelStoreConfig::default()),
+            DEFAULT_MAX_SEQ_LENGTH,
+        ),
+    };
+    checks.push(check_model_files(&store, features));
+    let mut engine = SemanticEngine::for_store(&store).with_max_seq_length(max_seq_length);
+    let _ = engine.initialize();
+    checks.push(check_onnx_session(&engine, features));
+    checks.push(check_embedding(&engine));
+
+    match workspace {
+        Ok(workspace) => {
+            checks.push(check_config(&workspace));
+            checks.push(check_state_dir(&workspace.state_dir));
+        }
+        Err(e) => checks.push(DoctorCheck::new("config", CheckStatus::Fail, e.to_string())),
+    }
+
+    checks.push(match find_hooks_file(hooks_file, project_dir) {
+        Some(path) => check_hooks_file(&path),
+        None => DoctorCheck::new(
+            "hooks",
+            CheckStatus::Warn,
+            "no hooks.json found; pass --hooks-file or set CLAUDE_PLUGIN_ROOT",
+        ),
+    });
+
+    #[cfg(unix)]
+    checks.push(check_daemon());
+
+    DoctorReport { features, checks }
+}
+
+fn check_features(features: CompiledFeatures) -> DoctorCheck {
+    let enabled: Vec<&str> = [
+        ("semantic", features.semantic),
+        ("ort", features.ort),
+        ("ci", features.ci),
+    ]
+    .into_iter()
+    .filter_map(|(name, on)| on.then_some(name))
+    .collect();
+    let listed = if enabled.is_empty() {
+        "none".to_string()
+    } else {
+        enabled.join(", ")
+    };
+
+    if !features.semantic {
+        DoctorCheck::new(
+            "features",
+            CheckStatus::Warn,
+            format!(
+                "{}; built without `semantic`, routing uses fallback embeddings",
+                listed
+            ),
+        )
+    } else if !features.ort {
+        DoctorCheck::new(
+            "features",
+            CheckStatus::Warn,
+            format!(
+                "{}; built without `ort`, the ONNX model is never loaded",
+                listed
+            ),
+        )
+    } else {
+        DoctorCheck::new("features", CheckStatus::Ok, listed)
+    }
+}
+
+fn check_model_files(store: &ModelStore, features: CompiledFeatures) -> DoctorCheck {
+    let dir = std::path::absolute(store.dir()).unwrap_or_else(|_| store.dir().to_path_buf());
+    let location = format!(
+        "{} in {} (from {})",
+        store.model(),
+        dir.display(),
+        store.source()
+    );
+    let reports = match store.verify() {
+        Ok(reports) => reports,
+        Err(e) => {
+            return DoctorCheck::new(
+                "model_files",
+                CheckStatus::Fail,
+                format!("{}: {}", location, e),
+            )
+        }
+    };
+
+    let mut status = CheckStatus::Ok;
+    let mut notes = Vec::new();
+    for report in reports {
+        let digest = report.sha256.unwrap_or_default();
+        let (file_status, note) = match report.state {
+            FileState::Verified => (CheckStatus::Ok, format!("{} sha256 ok", report.name)),
+            FileState::Unpinned => (
+                CheckStatus::Warn,
+                format!("{} sha256 {} (no expected hash)", report.name, digest),
+            ),
+            FileState::Mismatch => (
+                CheckStatus::Fail,
+                format!(
+                    "{} sha256 {} does not match expected {}",
+                    report.name,
+                    digest,
+                    report.expected.unwrap_or_default()
+                ),
+            ),
+            // Only the semantic build reads model files at all.
+            FileState::Missing if features.semantic => {
+                (CheckStatus::Fail, format!("{} missing", report.name))
+            }
+            FileState::Missing => (CheckStatus::Warn, format!("{} missing", report.name)),
+        };
+        status = worst(status, file_status);
+        notes.push(note);
+    }
+    if status == CheckStatus::Fail {
+        notes.push("run `swarm-tools models fetch`".to_string());
+    }
+
+    DoctorCheck::new(
+        "model_files",
+        status,
+        format!("{}: {}", location, notes.join("; ")),
+    )
+}
+
+fn worst(a: CheckStatus, b: CheckStatus) -> CheckStatus {
+    match (a, b) {
+        (CheckStatus::Fail, _) | (_, CheckStatus::Fail) => CheckStatus::Fail,
+        (CheckStatus::Warn, _) | (_, CheckStatus::Warn) => CheckStatus::Warn,
+        _ => CheckStatus::Ok,
+    }
+}
+
+fn check_onnx_session(engine: &SemanticEngine, features: CompiledFeatures) -> DoctorCheck {
+    if !features.ort {
+        return DoctorCheck::new(
+            "onnx_session",
+            CheckStatus::Warn,
+            "ONNX Runtime is not compiled in",
+        );
+    }
+    if engine.backend() == EmbeddingBackend::Onnx {
+        return DoctorCheck::new("onnx_session", CheckStatus::Ok, "model.onnx loaded");
+    }
+    let reason = if engine.load_warnings().is_empty() {
+        "session was not created".to_string()
+    } else {
+        engine.load_warnings().join("; ")
+    };
+    DoctorCheck::new("onnx_session", CheckStatus::Fail, reason)
+}
+
+fn check_embedding(engine: &SemanticEngine) -> DoctorCheck {
+    let backend = engine.backend();
+    let embedding = match engine.embed(SAMPLE_TEXT) {
+        Ok(embedding) => embedding,
+        Err(e) => {
+            return DoctorCheck::new(
+                "embedding",
+                CheckStatus::Fail,
+                format!("{:?} backend failed: {}", backend, e),
+            )
+        }
+    };
+
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let detail = format!(
+        "{:?} backend, dimension {}, norm {:.4}",
+        backend,
+        embedding.len(),
+        norm
+    );
+    let status = if embedding.len() != DEFAULT_EMBEDDING_DIM || !norm.is_finite() || norm == 0.0 {
+        CheckStatus::Fail
+    } else if backend == EmbeddingBackend::Fallback {
+        CheckStatus::Warn
+    } else {
+        CheckStatus::Ok
+    };
+    DoctorCheck::new("embedding", status, detail)
+}
+
+fn check_config(workspace: &Workspace) -> DoctorCheck {
+    let loaded = &workspace.loaded;
+    let mut parts: Vec<String> = loaded
+        .sources
+        .iter()
+        .map(|source| format!("{:?} ({})", source.kind, source.origin))
+        .collect();
+    if let Some(profile) = &loaded.profile {
+        parts.push(format!("profile `{}`", profile));
+    }
+    if loaded.warnings.is_empty() {
+        return DoctorCheck::new("config", CheckStatus::Ok, parts.join(", "));
+    }
+    parts.extend(loaded.warnings.iter().map(|warning| warning.to_string()));
+    DoctorCheck::new("config", CheckStatus::Warn, parts.join(", "))
+}
+
+fn check_state_dir(state_dir: &Path) -> DoctorCheck {
+    let probe = state_dir.join(".doctor");
+    let writable = fs::create_dir_all(state_dir)
+        .and_then(|_| fs::write(&probe, b""))
+        .and_then(|_| fs::remove_file(&probe));
+    match writable {
+        Ok(()) => DoctorCheck::new(
+            "state_dir",
+            CheckStatus::Ok,
+            format!("{} is writable", state_dir.display()),
+        ),
+        Err(e) => DoctorCheck::new(
+            "state_dir",
+            CheckStatus::Fail,
+            format!("{}: {}", state_dir.display(), e),
+        ),
+    }
+}
+
+/// Finds `hooks.json`: `explicit`, else `$CLAUDE_PLUGIN_ROOT/hooks/`, else
+/// the project's `hooks/`, else the checkout the running binary was built in.
+pub fn find_hooks_file(explicit: Option<&Path>, project_dir: &Path) -> Option<PathBuf> {
+    if let Some(path) = explicit {
+        return Some(path.to_path_buf());
+    }
+    let plugin_root = std::env::var_os("CLAUDE_PLUGIN_ROOT").map(PathBuf::from);
+    // target/<profile>/swarm-tools -> checkout root
+    let checkout = std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.parent()?.parent()?.to_path_buf()));
+
+    [plugin_root, Some(project_dir.to_path_buf()), checkout]
+        .into_iter()
+        .flatten()
+        .map(|root| root.join("hooks").join("hooks.json"))
+        .find(|path| path.is_file())
+}
+
+fn check_hooks_file(path: &Path) -> DoctorCheck {
+    let parsed = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str::<Value>(&content).map_err(|e| e.to_string()));
+    let hooks_json = match parsed {
+        Ok(value) => value,
+        Err(e) => {
+            return DoctorCheck::new(
+                "hooks",
+                CheckStatus::Fail,
+                format!("{}: {}", path.display(), e),
+            )
+        }
+    };
+
+    // hooks.json lives in <plugin root>/hooks/.
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let plugin_root = absolute
+        .parent()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new("."));
+    let commands = hook_commands(&hooks_json);
+    let broken: BTreeSet<String> = commands
+        .iter()
+        .filter_map(|command| {
+            let program = hook_program(command, plugin_root);
+            (!program_is_executable(&program)).then_some(program)
+        })
+        .collect();
+
+    if commands.is_empty() {
+        DoctorCheck::new(
+            "hooks",
+            CheckStatus::Warn,
+            format!("{} registers no commands", path.display()),
+        )
+    } else if broken.is_empty() {
+        DoctorCheck::new(
+            "hooks",
+            CheckStatus::Ok,
+            format!(
+                "{}: {} command(s) point at executables",
+                path.display(),
+                commands.len()
+            ),
+        )
+    } else {
+        DoctorCheck::new(
+            "hooks",
+            CheckStatus::Fail,
+            format!(
+                "{}: not executable: {}",
+                path.display(),
+                broken.into_iter().collect::<Vec<_>>().join(", ")
+            ),
+        )
+    }
+}
+
+/// Every `command` in a hooks file, in order.
+fn hook_commands(hooks_json: &Value) -> Vec<String> {
+    let Some(events) = hooks_json.get("hooks").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    events
+        .values()
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(|matcher| matcher.get("hooks").and_then(Value::as_array))
+        .flatten()
+        .filter_map(|hook| hook.get("command").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The program a hook command runs, with `${CLAUDE_PLUGIN_ROOT}` expanded.
+fn hook_program(command: &str, plugin_root: &Path) -> String {
+    command
+        .replace("${CLAUDE_PLUGIN_ROOT}", &plugin_root.to_string_lossy())
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn program_is_executable(program: &str) -> bool {
+    let program = Path::new(program);
+    if program.as_os_str().is_empty() {
+        return false;
+    }
+    if program.components().count() > 1 {
+        return is_executable(program);
+    }
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| is_executable(&dir.join(program))))
+        .unwrap_or(false)
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path)
+            .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file() || path.with_extension("exe").is_file()
+    }
+}
+
+#[cfg(unix)]
+fn check_daemon() -> DoctorCheck {
+    use crate::daemon::{default_socket_path, DaemonClient};
+
+    // Hooks work without the daemon, so this never fails.
+    let socket_path = default_socket_path();
+    let detail = match DaemonClient::new(&socket_path).status() {
+        Ok(status) => format!(
+            "running (pid {}) on {}, {} request(s) served",
+            status.pid,
+            socket_path.display(),
+            status.requests_served
+        ),
+        Err(_) => format!(
+            "not running on {}; hooks run in-process",
+            socket_path.display()
+        ),
+    };
+    DoctorCheck::new("daemon", CheckStatus::Ok, detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_store::sha256_file;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_hook_commands_resolve_plugin_root() {
+        let root = std::env::temp_dir().join(format!(
+            "swarm_tools_doctor_{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_micros()
+        ));
+        fs::create_dir_all(root.join("hooks")).unwrap();
+        let hooks = json!({"hooks": {
+            "Stop": [{"hooks": [{"type": "command", "command": "${CLAUDE_PLUGIN_ROOT}/bin/present hook stop"}]}],
+            "PreToolUse": [{"matcher": "*", "hooks": [{"type": "command", "command": "${CLAUDE_PLUGIN_ROOT}/bin/missing hook pre-tool-use"}]}],
+        }});
+        fs::write(root.join("hooks/hooks.json"), hooks.to_string()).unwrap();
+        let present = std::env::current_exe().unwrap();
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::copy(&present, root.join("bin/present")).unwrap();
+
+        let check = check_hooks_file(&root.join("hooks/hooks.json"));
+
+        assert_eq!(hook_commands(&hooks).len(), 2);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.detail.contains("bin/missing"));
+        assert!(!check.detail.contains("bin/present"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_model_files_compared_with_pinned_digests() {
+        let dir = std::env::temp_dir().join(format!(
+            "swarm_tools_doctor_models_{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_micros()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let store = ModelStore::resolve(&ModelStoreConfig {
+            model_dir: Some(dir.clone()),
+            ..Default::default()
+        });
+        for file in store.files() {
+            fs::write(dir.join(&file.name), &file.name).unwrap();
+        }
+        let features = CompiledFeatures {
+            semantic: true,
+            ort: true,
+            ci: false,
+        };
+
+        let unverified = check_model_files(&store, features);
+        assert_eq!(unverified.status, CheckStatus::Warn);
+
+        let store = ModelStore::resolve(&ModelStoreConfig {
+            model_dir: Some(dir.clone()),
+            sha256: BTreeMap::from([
+                (
+                    "tokenizer.json".to_string(),
+                    sha256_file(&dir.join("tokenizer.json")).unwrap(),
+                ),
+                ("model.onnx".to_string(), "0".repeat(64)),
+            ]),
+            ..Default::default()
+        });
+        let checked = check_model_files(&store, features);
+        assert_eq!(checked.status, CheckStatus::Fail);
+        assert!(checked.detail.contains("tokenizer.json sha256 ok"));
+        assert!(checked.detail.contains("model.onnx sha256"));
+
+        fs::remove_file(dir.join("model.onnx")).unwrap();
+        let missing = check_model_files(&store, features);
+        assert!(missing.detail.contains("model.onnx missing"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,882 @@
+//! Text embedders behind semantic loop detection and role routing.
+//!
+//! An [`Embedder`] turns text into fixed-size vectors compared with
+//! [`cosine_similarity`]. [`SemanticEngine`] loads the model files and embeds
+//! with the best backend it could load; [`select_embedder`] builds the one
+//! named by `embedder.backend` instead.
+
+use crate::feature_config::{EmbedderConfig, EmbedderKind};
+use crate::model_store::ModelStore;
+use crate::semantic_engine::{EmbeddingBackend, SemanticEngine, DEFAULT_EMBEDDING_DIM};
+#[cfg(feature = "semantic")]
+use crate::types::AgentRole;
+use crate::Result;
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "semantic")]
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(all(feature = "semantic", feature = "ort"))]
+use crate::feature_config::{EmbeddingModelSpec, Pooling};
+#[cfg(all(feature = "semantic", feature = "ort"))]
+use crate::semantic_engine::ModelConfig;
+#[cfg(all(feature = "semantic", feature = "ort"))]
+use ort::session::Session;
+#[cfg(all(feature = "semantic", feature = "ort"))]
+use std::sync::Mutex;
+#[cfg(feature = "semantic")]
+use tokenizers::Tokenizer;
+#[cfg(all(feature = "semantic", feature = "ort"))]
+use tokenizers::{PaddingParams, PaddingStrategy, TruncationParams};
+
+pub trait Embedder: fmt::Debug + Send + Sync {
+    /// Short identifier for logs and diagnostics, e.g.
+    /// `onnx-all-minilm-l6-v2`. Cached embeddings are keyed by it, so it
+    /// names the model as well as the backend.
+    fn name(&self) -> &str;
+
+    /// Length of every vector this embedder returns.
+    fn dimension(&self) -> usize;
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>>;
+
+    /// Embeds several texts; backends that can batch override this.
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        texts.iter().map(|text| self.embed(text)).collect()
+    }
+
+    /// Embeds texts looked up against others, such as prompts matched to role
+    /// descriptions. Models trained with query prefixes override this; for the
+    /// rest a query is embedded like any other text.
+    fn embed_queries(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.embed_batch(texts)
+    }
+}
+
+/// Cosine similarity of two vectors; 0.0 when their lengths differ or either
+/// is all zeros.
+///
+/// Vectors from different models must not be compared: stored embeddings are
+/// keyed by [`Embedder::name`] and indexes tagged with it, so switching models
+/// discards them rather than mixing dimensions.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot_product / (norm_a * norm_b)
+}
+
+/// Builds the embedder `config.backend` names.
+///
+/// `auto` uses the best backend for the model `store` resolved and gives
+/// `None` when none loads, so callers keep their word-overlap fallbacks. The
+/// other backends always give an embedder, falling back to word hashing with a
+/// warning when their model files are missing. `corpus` trains `tfidf`.
+pub fn select_embedder(
+    config: &EmbedderConfig,
+    store: &ModelStore,
+    corpus: &[String],
+) -> Option<Arc<dyn Embedder>> {
+    let load_engine = || {
+        let mut engine =
+            SemanticEngine::for_store(store).with_max_seq_length(config.max_seq_length);
+        let _ = engine.initialize();
+        engine
+    };
+
+    match config.backend {
+        EmbedderKind::Auto => {
+            let engine = load_engine();
+            engine.is_loaded().then(|| engine.embedder())
+        }
+        EmbedderKind::Onnx => {
+            let engine = load_engine();
+            if engine.backend() != EmbeddingBackend::Onnx {
+                eprintln!(
+                    "Warning: ONNX embedder unavailable; using {}",
+                    engine.embedder().name()
+                );
+            }
+            Some(engine.embedder())
+        }
+        EmbedderKind::TokenizerHash => {
+            #[cfg(feature = "semantic")]
+            {
+                match TokenizerHashEmbedder::from_file(&store.dir().join("tokenizer.json")) {
+                    Ok(embedder) => return Some(Arc::new(embedder)),
+                    Err(e) => eprintln!("Warning: {}; using word hashing", e),
+                }
+            }
+            Some(Arc::new(WordHashEmbedder::new(DEFAULT_EMBEDDING_DIM)))
+        }
+        EmbedderKind::WordHash => Some(Arc::new(WordHashEmbedder::new(DEFAULT_EMBEDDING_DIM))),
+        EmbedderKind::Tfidf => Some(Arc::new(TfIdfEmbedder::fit(
+            corpus.iter().map(String::as_str),
+            DEFAULT_EMBEDDING_DIM,
+            config.bm25_k1,
+            config.bm25_b,
+        ))),
+        EmbedderKind::Stub => Some(Arc::new(StubEmbedder::new(DEFAULT_EMBEDDING_DIM))),
+    }
+}
+
+/// Scales `embedding` to unit length; a zero vector is left as is.
+pub(crate) fn normalize(embedding: &mut [f32]) {
+    let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for v in embedding {
+            *v /= norm;
+        }
+    }
+}
+
+fn simple_hash(word: &str) -> u32 {
+    let mut hash = 0u32;
+    for byte in word.bytes() {
+        hash = hash.wrapping_mul(31).wrapping_add(byte as u32);
+    }
+    hash
+}
+
+/// 64-bit FNV-1a, stable across platforms and releases.
+fn fnv1a(text: &str) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Hashes words into buckets weighted by position; needs no model files.
+#[derive(Debug, Clone)]
+pub struct WordHashEmbedder {
+    dimension: usize,
+}
+
+impl WordHashEmbedder {
+    pub const NAME: &'static str = "word-hash";
+
+    pub fn new(dimension: usize) -> Self {
+        Self { dimension }
+    }
+}
+
+impl Embedder for WordHashEmbedder {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let lowercase_text = text.to_lowercase();
+        let words: Vec<&str> = lowercase_text.split_whitespace().collect();
+        let mut embedding = vec![0.0f32; self.dimension];
+
+        for (i, word) in words.iter().enumerate() {
+            let hash = simple_hash(word) as usize % self.dimension;
+            let weight = (i as f32 + 1.0).recip();
+            embedding[hash] += weight;
+        }
+
+        normalize(&mut embedding);
+        Ok(embedding)
+    }
+}
+
+/// Hashes the MiniLM tokenizer's token ids into buckets, boosted by role
+/// keywords; needs `tokenizer.json` but not the ONNX model.
+#[cfg(feature = "semantic")]
+#[derive(Debug, Clone)]
+pub struct TokenizerHashEmbedder {
+    tokenizer: Tokenizer,
+    dimension: usize,
+}
+
+#[cfg(feature = "semantic")]
+impl TokenizerHashEmbedder {
+    pub const NAME: &'static str = "tokenizer-hash";
+
+    pub fn new(tokenizer: Tokenizer, dimension: usize) -> Self {
+        Self {
+            tokenizer,
+            dimension,
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let tokenizer = Tokenizer::from_file(path)
+            .map_err(|e| format!("Could not load tokenizer from {:?}: {}", path, e))?;
+        Ok(Self::new(tokenizer, DEFAULT_EMBEDDING_DIM))
+    }
+}
+
+#[cfg(feature = "semantic")]
+impl Embedder for TokenizerHashEmbedder {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let encoding = self
+            .tokenizer
+            .encode(text, false)
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        let ids = encoding.get_ids();
+        let attention_mask = encoding.get_attention_mask();
+        let lowercase_text = text.to_lowercase();
+
+        let hidden_size = self.dimension;
+        let mut embedding = vec![0.0f32; hidden_size];
+
+        // Token-based embedding
+        for (i, &id) in ids.iter().enumerate() {
+            let mask_weight = if i < attention_mask.len() && attention_mask[i] > 0 {
+                1.0
+            } else {
+                0.0
+            };
+
+            let base_idx = id as usize % hidden_size;
+            embedding[base_idx] += mask_weight;
+
+            if base_idx + 1 < hidden_size {
+                embedding[base_idx + 1] += mask_weight * 0.5;
+            }
+        }
+
+        // Role-specific keyword boosting
+        let role_keywords: Vec<(AgentRole, Vec<&str>)> = vec![
+            (
+                AgentRole::Extractor,
+                vec!["git", "diff", "extract", "file", "change", "delta"],
+            ),
+            (
+                AgentRole::Analyzer,
+                vec!["analyze", "metric", "pattern", "statistic", "find"],
+            ),
+            (
+                AgentRole::Reviewer,
+                vec!["review", "security", "bug", "error", "quality", "check"],
+            ),
+            (
+                AgentRole::Writer,
+                vec!["write", "doc", "update", "content", "text"],
+            ),
+            (
+                AgentRole::Synthesizer,
+                vec!["summar", "conclu", "recommend", "consolid"],
+            ),
+            (AgentRole::Tester, vec!["test", "verif", "unit", "check"]),
+            (
+                AgentRole::Documenter,
+                vec!["document", "comment", "api", "guide"],
+            ),
+            (
+                AgentRole::Optimizer,
+                vec!["optim", "perf", "refactor", "efficien"],
+            ),
+        ];
+
+        for (position, (_, keywords)) in role_keywords.iter().enumerate() {
+            for keyword in keywords {
+                if lowercase_text.contains(keyword) {
+                    let boost_idx = position % hidden_size;
+                    embedding[boost_idx] += 0.3;
+                }
+            }
+        }
+
+        normalize(&mut embedding);
+        Ok(embedding)
+    }
+}
+
+/// Averages token embeddings over the positions `attention_mask` keeps, as
+/// sentence-transformers' mean pooling does. `token_embeddings` holds one
+/// `hidden_size` row per mask entry.
+pub fn mean_pool(token_embeddings: &[f32], attention_mask: &[i64], hidden_size: usize) -> Vec<f32> {
+    let mut pooled = vec![0.0f32; hidden_size];
+    let mut kept = 0.0f32;
+    for (row, &mask) in token_embeddings
+        .chunks_exact(hidden_size)
+        .zip(attention_mask)
+    {
+        if mask == 0 {
+            continue;
+        }
+        kept += 1.0;
+        for (sum, value) in pooled.iter_mut().zip(row) {
+            *sum += value;
+        }
+    }
+    // sentence-transformers clamps the token count the same way.
+    let kept = kept.max(1e-9);
+    for value in &mut pooled {
+        *value /= kept;
+    }
+    pooled
+}
+
+/// The ONNX Runtime library for this platform.
+#[cfg(all(feature = "semantic", feature = "ort"))]
+pub const ORT_LIBRARY: &str = if cfg!(windows) {
+    "onnxruntime.dll"
+} else if cfg!(target_os = "macos") {
+    "libonnxruntime.dylib"
+} else {
+    "libonnxruntime.so"
+};
+
+/// Where to load ONNX Runtime from: [`ORT_LIBRARY`] in `model_dir`, else the
+/// file `ORT_DYLIB_PATH` names.
+#[cfg(all(feature = "semantic", feature = "ort"))]
+fn ort_library(model_dir: &Path) -> std::result::Result<std::path::PathBuf, String> {
+    let bundled = model_dir.join(ORT_LIBRARY);
+    if bundled.exists() {
+        return Ok(bundled);
+    }
+    match std::env::var_os("ORT_DYLIB_PATH") {
+        Some(path) if Path::new(&path).exists() => Ok(path.into()),
+        _ => Err(format!(
+            "{} not found at {:?} and ORT_DYLIB_PATH is not set to it",
+            ORT_LIBRARY, bundled
+        )),
+    }
+}
+
+/// Texts ONNX Runtime embeds per run.
+#[cfg(all(feature = "semantic", feature = "ort"))]
+const ONNX_BATCH_SIZE: usize = 32;
+
+/// Model inputs for a batch of texts, padded to the longest: `rows` rows of
+/// `tokens` entries each.
+#[cfg(all(feature = "semantic", feature = "ort"))]
+struct Encoded {
+    rows: usize,
+    tokens: usize,
+    input_ids: Vec<i64>,
+    token_type_ids: Vec<i64>,
+    attention_mask: Vec<i64>,
+}
+
+/// A registry model through ONNX Runtime, pooled and normalized the way
+/// sentence-transformers does: prefix and special tokens added, inputs
+/// truncated, token embeddings pooled as the model's entry says, then
+/// L2-normalized.
+#[cfg(all(feature = "semantic", feature = "ort"))]
+pub struct OnnxEmbedder {
+    /// `onnx-<registry id>`.
+    name: String,
+    pooling: Pooling,
+    query_prefix: String,
+    passage_prefix: String,
+    session: Mutex<Session>,
+    tokenizer: Option<Tokenizer>,
+    config: ModelConfig,
+    max_length: usize,
+    /// Whether the exported graph declares a `token_type_ids` input.
+    token_type_ids: bool,
+}
+
+#[cfg(all(feature = "semantic", feature = "ort"))]
+impl OnnxEmbedder {
+    /// Creates a session for `<model_dir>/model.onnx`, the registry model
+    /// `model`, that sees at most `max_length` tokens per text. Without a
+    /// tokenizer, token ids are hashed from words.
+    pub fn load(
+        model_dir: &Path,
+        tokenizer: Option<Tokenizer>,
+        config: ModelConfig,
+        max_length: usize,
+        model: &str,
+        spec: &EmbeddingModelSpec,
+    ) -> std::result::Result<Self, String> {
+        let onnx_path = model_dir.join("model.onnx");
+        if !onnx_path.exists() {
+            return Err(format!("model.onnx not found at {:?}", onnx_path));
+        }
+
+        // ONNX Runtime is loaded from the library next to the model, or the
+        // one `ORT_DYLIB_PATH` names; ort panics on a library it can't open.
+        let runtime_path = ort_library(model_dir)?;
+        if ort::init_from(runtime_path.to_string_lossy().as_ref())
+            .commit()
+            .is_err()
+        {
+            return Err("Could not initialize ONNX Runtime".to_string());
+        }
+
+        // Room for [CLS] and [SEP] plus one token of text.
+        let max_length = max_length.clamp(3, config.max_position_embeddings.max(3));
+        let tokenizer = match tokenizer {
+            Some(mut tokenizer) => {
+                // tokenizer.json ships its own truncation and padding; the
+                // reference truncates to max_seq_length and pads per batch.
+                tokenizer
+                    .with_truncation(Some(TruncationParams {
+                        max_length,
+                        ..Default::default()
+                    }))
+                    .map_err(|e| format!("Could not set tokenizer truncation: {}", e))?;
+                tokenizer.with_padding(Some(PaddingParams {
+                    strategy: PaddingStrategy::BatchLongest,
+                    pad_id: config.pad_token_id,
+                    ..Default::default()
+                }));
+                Some(tokenizer)
+            }
+            None => None,
+        };
+
+        let session = Session::builder()
+            .map_err(|e| format!("Could not create session builder: {}", e))?
+            .commit_from_file(&onnx_path)
+            .map_err(|e| format!("Could not load ONNX model: {}", e))?;
+        let token_type_ids = session
+            .inputs
+            .iter()
+            .any(|input| input.name == "token_type_ids");
+        Ok(Self {
+            name: format!("onnx-{}", model),
+            pooling: spec.pooling,
+            query_prefix: spec.query_prefix.clone(),
+            passage_prefix: spec.passage_prefix.clone(),
+            session: Mutex::new(session),
+            tokenizer,
+            config,
+            max_length,
+            token_type_ids,
+        })
+    }
+
+    fn encode(&self, texts: &[&str]) -> Result<Encoded> {
+        let rows: Vec<(Vec<u32>, Vec<u32>, Vec<u32>)> = match &self.tokenizer {
+            Some(tokenizer) => tokenizer
+                .encode_batch(texts.to_vec(), true)
+                .map_err(|e| anyhow::anyhow!(e))?
+                .into_iter()
+                .map(|encoding| {
+                    (
+                        encoding.get_ids().to_vec(),
+                        encoding.get_type_ids().to_vec(),
+                        encoding.get_attention_mask().to_vec(),
+                    )
+                })
+                .collect(),
+            None => texts
+                .iter()
+                .map(|text| {
+                    let ids = self.tokenize_fallback(text);
+                    let len = ids.len();
+                    (ids, vec![0; len], vec![1; len])
+                })
+                .collect(),
+        };
+
+        // The tokenizer pads to the longest text; the fallback does not.
+        let tokens = rows.iter().map(|(ids, _, _)| ids.len()).max().unwrap_or(0);
+        let mut encoded = Encoded {
+            rows: rows.len(),
+            tokens,
+            input_ids: Vec::with_capacity(rows.len() * tokens),
+            token_type_ids: Vec::with_capacity(rows.len() * tokens),
+            attention_mask: Vec::with_capacity(rows.len() * tokens),
+        };
+        for (ids, type_ids, mask) in rows {
+            let padding = tokens - ids.len();
+            let pad_id = self.config.pad_token_id as i64;
+            encoded.input_ids.extend(ids.iter().map(|&v| v as i64));
+            encoded
+                .input_ids
+                .extend(std::iter::repeat_n(pad_id, padding));
+            encoded
+                .token_type_ids
+                .extend(type_ids.iter().map(|&v| v as i64));
+            encoded
+                .token_type_ids
+                .extend(std::iter::repeat_n(0, padding));
+            encoded
+                .attention_mask
+                .extend(mask.iter().map(|&v| v as i64));
+            encoded
+                .attention_mask
+                .extend(std::iter::repeat_n(0, padding));
+        }
+        Ok(encoded)
+    }
+
+    fn embed_chunk(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        use ort::value::Tensor;
+
+        let encoded = self.encode(texts)?;
+        let shape = vec![encoded.rows as i64, encoded.tokens as i64];
+        let tensor = |values: Vec<i64>| Tensor::from_array((shape.clone(), values));
+
+        let mut inputs = vec![
+            ("input_ids", tensor(encoded.input_ids)?),
+            ("attention_mask", tensor(encoded.attention_mask.clone())?),
+        ];
+        if self.token_type_ids {
+            inputs.push(("token_type_ids", tensor(encoded.token_type_ids)?));
+        }
+
+        let mut session = self
+            .session
+            .lock()
+            .map_err(|_| anyhow::anyhow!("ONNX session lock poisoned"))?;
+        let outputs = session.run(inputs)?;
+
+        // last_hidden_state: [batch, sequence, hidden]
+        let (output_shape, token_embeddings) = outputs[0].try_extract_tensor::<f32>()?;
+        let hidden_size = self.config.hidden_size;
+        let expected = [
+            encoded.rows as i64,
+            encoded.tokens as i64,
+            hidden_size as i64,
+        ];
+        if output_shape[..] != expected {
+            return Err(format!(
+                "Unexpected ONNX output shape {:?}; expected {:?}",
+                output_shape, expected
+            )
+            .into());
+        }
+
+        let row_len = encoded.tokens * hidden_size;
+        Ok(token_embeddings
+            .chunks_exact(row_len.max(1))
+            .zip(encoded.attention_mask.chunks_exact(encoded.tokens.max(1)))
+            .map(|(row, mask)| {
+                let mut embedding = match self.pooling {
+                    Pooling::Mean => mean_pool(row, mask, hidden_size),
+                    Pooling::Cls => row[..hidden_size].to_vec(),
+                };
+                normalize(&mut embedding);
+                embedding
+            })
+            .collect())
+    }
+
+    /// Embeds `texts` with `prefix` prepended, up to [`ONNX_BATCH_SIZE`] at a
+    /// time, each batch padded to its longest text.
+    fn embed_prefixed(&self, prefix: &str, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let prefixed: Vec<String> = texts
+            .iter()
+            .map(|text| format!("{}{}", prefix, text))
+            .collect();
+        let prefixed: Vec<&str> = prefixed.iter().map(String::as_str).collect();
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for chunk in prefixed.chunks(ONNX_BATCH_SIZE) {
+            embeddings.extend(self.embed_chunk(chunk)?);
+        }
+        Ok(embeddings)
+    }
+
+    fn tokenize_fallback(&self, text: &str) -> Vec<u32> {
+        let mut tokens = Vec::new();
+        tokens.push(self.config.bos_token_id);
+
+        let clean_text: String = text
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+            .collect();
+
+        let vocab_size_u32 = self.config.vocab_size as u32;
+        for word in clean_text.split_whitespace().take(self.max_length - 2) {
+            tokens.push(simple_hash(word) % vocab_size_u32);
+        }
+
+        tokens.push(self.config.eos_token_id);
+        tokens
+    }
+}
+
+#[cfg(all(feature = "semantic", feature = "ort"))]
+impl fmt::Debug for OnnxEmbedder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnnxEmbedder")
+            .field("name", &self.name)
+            .field("pooling", &self.pooling)
+            .field("tokenizer", &self.tokenizer.is_some())
+            .field("config", &self.config)
+            .field("max_length", &self.max_length)
+            .field("token_type_ids", &self.token_type_ids)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(feature = "semantic", feature = "ort"))]
+impl Embedder for OnnxEmbedder {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dimension(&self) -> usize {
+        self.config.hidden_size
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_batch(&[text])?
+            .pop()
+            .ok_or_else(|| "ONNX Runtime returned no embedding".into())
+    }
+
+    /// Embeds passages, with the model's passage prefix.
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.embed_prefixed(&self.passage_prefix, texts)
+    }
+
+    fn embed_queries(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.embed_prefixed(&self.query_prefix, texts)
+    }
+}
+
+/// Sparse BM25-weighted bag of words, hashed into `dimension` signed buckets.
+///
+/// Document frequencies come from a corpus such as the session's own prompts,
+/// so words every prompt shares count for little and rare ones dominate.
+#[derive(Debug, Clone)]
+pub struct TfIdfEmbedder {
+    dimension: usize,
+    doc_freq: HashMap<String, usize>,
+    documents: usize,
+    avg_doc_len: f32,
+    k1: f32,
+    b: f32,
+}
+
+impl TfIdfEmbedder {
+    pub const NAME: &'static str = "tfidf-bm25";
+
+    /// Fits document frequencies on `corpus`. An empty corpus weighs every
+    /// word the same.
+    pub fn fit<'a>(
+        corpus: impl IntoIterator<Item = &'a str>,
+        dimension: usize,
+        k1: f32,
+        b: f32,
+    ) -> Self {
+        let mut doc_freq: HashMap<String, usize> = HashMap::new();
+        let mut documents = 0;
+        let mut total_len = 0;
+        for document in corpus {
+            let terms = Self::terms(document);
+            documents += 1;
+            total_len += terms.len();
+            let mut unique = terms;
+            unique.sort();
+            unique.dedup();
+            for term in unique {
+                *doc_freq.entry(term).or_insert(0) += 1;
+            }
+        }
+        let avg_doc_len = if documents == 0 {
+            0.0
+        } else {
+            total_len as f32 / documents as f32
+        };
+        Self {
+            dimension,
+            doc_freq,
+            documents,
+            avg_doc_len,
+            k1,
+            b,
+        }
+    }
+
+    /// Number of documents the embedder was fitted on.
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    fn terms(text: &str) -> Vec<String> {
+        text.to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|term| !term.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn idf(&self, term: &str) -> f32 {
+        let df = self.doc_freq.get(term).copied().unwrap_or(0) as f32;
+        let n = self.documents as f32;
+        (1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+    }
+}
+
+impl Embedder for TfIdfEmbedder {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let terms = Self::terms(text);
+        let mut term_freq: HashMap<&str, f32> = HashMap::new();
+        for term in &terms {
+            *term_freq.entry(term).or_insert(0.0) += 1.0;
+        }
+
+        let doc_len = terms.len() as f32;
+        let length_norm = if self.avg_doc_len > 0.0 {
+            1.0 - self.b + self.b * doc_len / self.avg_doc_len
+        } else {
+            1.0
+        };
+        let mut embedding = vec![0.0f32; self.dimension];
+        for (term, tf) in term_freq {
+            let weight = self.idf(term) * tf * (self.k1 + 1.0) / (tf + self.k1 * length_norm);
+            let hash = fnv1a(term);
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            embedding[hash as usize % self.dimension] += sign * weight;
+        }
+
+        normalize(&mut embedding);
+        Ok(embedding)
+    }
+}
+
+/// Deterministic embeddings for tests: registered vectors for known texts and
+/// a one-hot vector per distinct text otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct StubEmbedder {
+    dimension: usize,
+    fixed: HashMap<String, Vec<f32>>,
+}
+
+impl StubEmbedder {
+    pub const NAME: &'static str = "stub";
+
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension,
+            fixed: HashMap::new(),
+        }
+    }
+
+    /// Returns `embedding` for exactly `text`.
+    pub fn with_embedding(mut self, text: &str, embedding: Vec<f32>) -> Self {
+        self.fixed.insert(text.to_string(), embedding);
+        self
+    }
+}
+
+impl Embedder for StubEmbedder {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        if let Some(embedding) = self.fixed.get(text) {
+            return Ok(embedding.clone());
+        }
+        let mut embedding = vec![0.0f32; self.dimension];
+        embedding[fnv1a(text) as usize % self.dimension] = 1.0;
+        Ok(embedding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tfidf_weights_rare_terms() {
+        let corpus = [
+            "run the build",
+            "run the tests",
+            "run the linter",
+            "deploy the service",
+        ];
+        let embedder = TfIdfEmbedder::fit(corpus, DEFAULT_EMBEDDING_DIM, 1.2, 0.75);
+        assert_eq!(embedder.documents(), 4);
+        assert!(embedder.idf("deploy") > embedder.idf("run"));
+
+        let query = embedder.embed("run the tests").unwrap();
+        let same_rare = embedder.embed("tests for the parser").unwrap();
+        let same_common = embedder.embed("run the linter").unwrap();
+        assert!(cosine_similarity(&query, &same_rare) > cosine_similarity(&query, &same_common));
+        assert_eq!(query.len(), embedder.dimension());
+    }
+
+    #[test]
+    fn test_mean_pool_ignores_padding() {
+        let token_embeddings = [1.0, 2.0, 3.0, 4.0, 100.0, 100.0];
+        let pooled = mean_pool(&token_embeddings, &[1, 1, 0], 2);
+        assert_eq!(pooled, vec![2.0, 3.0]);
+
+        assert_eq!(mean_pool(&token_embeddings, &[0, 0, 0], 2), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_stub_is_deterministic() {
+        let embedder = StubEmbedder::new(8)
+            .with_embedding("known", vec![1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(embedder.embed("known").unwrap()[0], 1.0);
+        assert_eq!(
+            embedder.embed("other").unwrap(),
+            embedder.embed("other").unwrap()
+        );
+        assert_eq!(
+            embedder.embed_batch(&["a", "known"]).unwrap()[1],
+            embedder.embed("known").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_select_embedder_by_kind() {
+        let missing = ModelStore::resolve(&crate::feature_config::ModelStoreConfig {
+            model_dir: Some("/nonexistent/swarm-tools-models".into()),
+            ..Default::default()
+        });
+        let config = |backend| EmbedderConfig {
+            backend,
+            ..Default::default()
+        };
+
+        assert!(select_embedder(&config(EmbedderKind::Auto), &missing, &[]).is_none());
+        let names: Vec<String> = [
+            EmbedderKind::TokenizerHash,
+            EmbedderKind::WordHash,
+            EmbedderKind::Tfidf,
+            EmbedderKind::Stub,
+        ]
+        .into_iter()
+        .map(|kind| {
+            select_embedder(&config(kind), &missing, &[])
+                .unwrap()
+                .name()
+                .to_string()
+        })
+        .collect();
+        assert_eq!(names, ["word-hash", "word-hash", "tfidf-bm25", "stub"]);
+    }
+}
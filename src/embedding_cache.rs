@@ -0,0 +1,342 @@
+//! Embeddings computed once and reused.
+//!
+//! Both caches key an embedding by [`cache_key`], the SHA-256 of the
+//! embedder's name and the text, so switching models never returns a stale
+//! vector. [`CachedEmbedder`] keeps recent embeddings in memory for as long as
+//! it lives, e.g. inside the daemon; [`EmbeddingFile`] stores one agent's
+//! prompt-history embeddings on disk, next to its `_history.json`, so a hook
+//! only embeds each prompt once.
+
+use crate::embedder::Embedder;
+use crate::Result;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Content address of `text` embedded by the model named `model_id`.
+pub fn cache_key(model_id: &str, text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(model_id.as_bytes());
+    hasher.update([0]);
+    hasher.update(text.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Embeds `texts`, calling `embedder` once for the ones `lookup` misses.
+///
+/// Each missed text is embedded once however often it repeats, and handed to
+/// `store` with its key.
+fn embed_with(
+    embedder: &dyn Embedder,
+    texts: &[&str],
+    mut lookup: impl FnMut(&str) -> Option<Vec<f32>>,
+    mut store: impl FnMut(String, Vec<f32>),
+) -> Result<Vec<Vec<f32>>> {
+    let keys: Vec<String> = texts
+        .iter()
+        .map(|text| cache_key(embedder.name(), text))
+        .collect();
+    let mut found: HashMap<&str, Vec<f32>> = HashMap::new();
+    let mut missing: Vec<(&str, &str)> = Vec::new();
+    for (key, text) in keys.iter().zip(texts) {
+        if found.contains_key(key.as_str()) || missing.iter().any(|(k, _)| k == key) {
+            continue;
+        }
+        match lookup(key) {
+            Some(embedding) => {
+                found.insert(key, embedding);
+            }
+            None => missing.push((key, text)),
+        }
+    }
+
+    if !missing.is_empty() {
+        let batch: Vec<&str> = missing.iter().map(|(_, text)| *text).collect();
+        let embeddings = embedder.embed_batch(&batch)?;
+        if embeddings.len() != batch.len() {
+            return Err(format!(
+                "{} returned {} embeddings for {} texts",
+                embedder.name(),
+                embeddings.len(),
+                batch.len()
+            )
+            .into());
+        }
+        for ((key, _), embedding) in missing.into_iter().zip(embeddings) {
+            store(key.to_string(), embedding.clone());
+            found.insert(key, embedding);
+        }
+    }
+
+    Ok(keys.iter().map(|key| found[key.as_str()].clone()).collect())
+}
+
+/// Wraps an embedder with an in-memory LRU cache of its embeddings.
+pub struct CachedEmbedder {
+    inner: Arc<dyn Embedder>,
+    memory: Mutex<LruCache<String, Vec<f32>>>,
+}
+
+impl CachedEmbedder {
+    pub fn new(inner: Arc<dyn Embedder>, capacity: NonZeroUsize) -> Self {
+        Self {
+            inner,
+            memory: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// The embedder misses go to.
+    pub fn inner(&self) -> &Arc<dyn Embedder> {
+        &self.inner
+    }
+
+    /// Embeddings currently cached.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruCache<String, Vec<f32>>> {
+        self.memory.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for CachedEmbedder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let memory = self.lock();
+        f.debug_struct("CachedEmbedder")
+            .field("inner", &self.inner)
+            .field("cached", &memory.len())
+            .field("capacity", &memory.cap())
+            .finish()
+    }
+}
+
+impl Embedder for CachedEmbedder {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_batch(&[text])?
+            .pop()
+            .ok_or_else(|| "embedder returned no embedding".into())
+    }
+
+    /// Only texts missing from the cache reach the inner embedder, in one
+    /// batch. The lock is not held while it runs.
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut fresh = Vec::new();
+        let embeddings = embed_with(
+            self.inner.as_ref(),
+            texts,
+            |key| self.lock().get(key).cloned(),
+            |key, embedding| fresh.push((key, embedding)),
+        )?;
+        let mut memory = self.lock();
+        for (key, embedding) in fresh {
+            memory.put(key, embedding);
+        }
+        Ok(embeddings)
+    }
+
+    /// Queries skip the cache: each prompt is routed once, and models with
+    /// query prefixes embed it differently from a passage.
+    fn embed_queries(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed_queries(texts)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredEmbedding {
+    key: String,
+    embedding: Vec<f32>,
+}
+
+/// Embeddings persisted in a JSON file, loaded and saved whole.
+///
+/// The file is a list of `{ "key", "embedding" }` records; a missing or
+/// unreadable file starts the cache empty.
+#[derive(Debug)]
+pub struct EmbeddingFile {
+    path: PathBuf,
+    entries: HashMap<String, Vec<f32>>,
+    changed: bool,
+}
+
+impl EmbeddingFile {
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Vec<StoredEmbedding>>(&content).ok())
+            .map(|stored| {
+                stored
+                    .into_iter()
+                    .map(|entry| (entry.key, entry.embedding))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            path,
+            entries,
+            changed: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Embeds `texts` in order, reusing stored embeddings and storing new ones.
+    pub fn embed(&mut self, embedder: &dyn Embedder, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let entries = &mut self.entries;
+        let mut fresh = Vec::new();
+        let dimension = embedder.dimension();
+        let embeddings = embed_with(
+            embedder,
+            texts,
+            |key| {
+                entries
+                    .get(key)
+                    .filter(|embedding| embedding.len() == dimension)
+                    .cloned()
+            },
+            |key, embedding| fresh.push((key, embedding)),
+        )?;
+        self.changed |= !fresh.is_empty();
+        entries.extend(fresh);
+        Ok(embeddings)
+    }
+
+    /// Drops embeddings of anything but `texts`, so the file shrinks as the
+    /// history it mirrors does.
+    pub fn retain(&mut self, embedder: &dyn Embedder, texts: &[&str]) {
+        let keep: HashSet<String> = texts
+            .iter()
+            .map(|text| cache_key(embedder.name(), text))
+            .collect();
+        let before = self.entries.len();
+        self.entries.retain(|key, _| keep.contains(key));
+        self.changed |= self.entries.len() != before;
+    }
+
+    /// Writes the file if anything changed since it was loaded.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut stored: Vec<StoredEmbedding> = self
+            .entries
+            .iter()
+            .map(|(key, embedding)| StoredEmbedding {
+                key: key.clone(),
+                embedding: embedding.clone(),
+            })
+            .collect();
+        stored.sort_by(|a, b| a.key.cmp(&b.key));
+        fs::write(&self.path, serde_json::to_string(&stored)?)?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts the texts it embeds.
+    #[derive(Debug, Default)]
+    struct Counting {
+        embedded: AtomicUsize,
+    }
+
+    impl Embedder for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn dimension(&self) -> usize {
+            8
+        }
+
+        fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            self.embedded.fetch_add(1, Ordering::SeqCst);
+            let mut embedding = vec![0.0; 8];
+            embedding[text.len() % 8] = 1.0;
+            Ok(embedding)
+        }
+    }
+
+    #[test]
+    fn test_cache_key_includes_model() {
+        assert_eq!(cache_key("a", "text"), cache_key("a", "text"));
+        assert_ne!(cache_key("a", "text"), cache_key("b", "text"));
+        assert_eq!(cache_key("a", "text").len(), 64);
+    }
+
+    #[test]
+    fn test_cached_embedder_embeds_each_text_once() {
+        let inner = Arc::new(Counting::default());
+        let cached = CachedEmbedder::new(inner.clone(), NonZeroUsize::new(16).unwrap());
+
+        let first = cached.embed_batch(&["a", "bb", "a"]).unwrap();
+        assert_eq!(first[0], first[2]);
+        assert_eq!(inner.embedded.load(Ordering::SeqCst), 2);
+
+        let second = cached.embed_batch(&["bb", "ccc"]).unwrap();
+        assert_eq!(second[0], first[1]);
+        assert_eq!(inner.embedded.load(Ordering::SeqCst), 3);
+        assert_eq!(cached.len(), 3);
+    }
+
+    #[test]
+    fn test_embedding_file_persists_history() {
+        let path = std::env::temp_dir().join(format!(
+            "swarm_tools_embedding_file_{}/agent_embeddings.json",
+            std::process::id()
+        ));
+        let embedder = Counting::default();
+
+        let mut file = EmbeddingFile::load(&path);
+        file.embed(&embedder, &["one", "three"]).unwrap();
+        file.save().unwrap();
+
+        let mut reloaded = EmbeddingFile::load(&path);
+        assert_eq!(reloaded.len(), 2);
+        let embeddings = reloaded.embed(&embedder, &["three", "four!"]).unwrap();
+        assert_eq!(embeddings.len(), 2);
+        assert_eq!(embedder.embedded.load(Ordering::SeqCst), 3);
+
+        reloaded.retain(&embedder, &["four!"]);
+        reloaded.save().unwrap();
+        assert_eq!(EmbeddingFile::load(&path).len(), 1);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -147,6 +147,11 @@ impl EnhancedMonitor {
         }
     }
 
+    /// Changes the context budget percentages are computed against, keeping history.
+    pub fn set_total_context(&mut self, total_context: usize) {
+        self.total_context = total_context;
+    }
+
     pub fn with_auto_reduce(
         total_context: usize,
         auto_reduce: bool,
@@ -1,10 +1,18 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Restricts which tools each agent role may call.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct McpRoutingConfig {
+    /// Whether tool filtering is applied at all.
     pub enabled: bool,
+    /// Allowed tool names per role name, such as Claude Code's `Read` or
+    /// `Bash`. Names match case-insensitively, and an MCP tool
+    /// (`mcp__<server>__<tool>`) also matches its bare `<tool>` name.
     pub role_tool_filters: Option<HashMap<String, Vec<String>>>,
+    /// Tool names every role may use, matched like `role_tool_filters`.
     pub default_tools: Option<Vec<String>>,
 }
 
@@ -16,82 +24,103 @@ impl Default for McpRoutingConfig {
                 (
                     "extractor".to_string(),
                     vec![
-                        "read_file".to_string(),
-                        "git_diff".to_string(),
-                        "grep".to_string(),
-                        "glob".to_string(),
+                        "Read".to_string(),
+                        "Grep".to_string(),
+                        "Glob".to_string(),
+                        "LS".to_string(),
+                        "Bash".to_string(),
                     ],
                 ),
                 (
                     "analyzer".to_string(),
                     vec![
-                        "search_code".to_string(),
-                        "browse_web".to_string(),
-                        "grep".to_string(),
-                        "analyze".to_string(),
+                        "Read".to_string(),
+                        "Grep".to_string(),
+                        "Glob".to_string(),
+                        "LS".to_string(),
+                        "WebSearch".to_string(),
+                        "WebFetch".to_string(),
                     ],
                 ),
                 (
                     "writer".to_string(),
                     vec![
-                        "write_file".to_string(),
-                        "edit_file".to_string(),
-                        "create".to_string(),
+                        "Read".to_string(),
+                        "Write".to_string(),
+                        "Edit".to_string(),
+                        "MultiEdit".to_string(),
+                        "NotebookEdit".to_string(),
+                        "Bash".to_string(),
                     ],
                 ),
                 (
                     "reviewer".to_string(),
                     vec![
-                        "read_file".to_string(),
-                        "grep".to_string(),
-                        "check".to_string(),
-                        "lint".to_string(),
+                        "Read".to_string(),
+                        "Grep".to_string(),
+                        "Glob".to_string(),
+                        "LS".to_string(),
+                        "Bash".to_string(),
                     ],
                 ),
                 (
                     "synthesizer".to_string(),
                     vec![
-                        "summarize".to_string(),
-                        "browse_web".to_string(),
-                        "search".to_string(),
+                        "Read".to_string(),
+                        "WebSearch".to_string(),
+                        "WebFetch".to_string(),
                     ],
                 ),
                 (
                     "tester".to_string(),
                     vec![
-                        "run_test".to_string(),
-                        "execute".to_string(),
-                        "verify".to_string(),
+                        "Read".to_string(),
+                        "Grep".to_string(),
+                        "Glob".to_string(),
+                        "Bash".to_string(),
                     ],
                 ),
                 (
                     "documenter".to_string(),
                     vec![
-                        "read_file".to_string(),
-                        "write_file".to_string(),
-                        "generate".to_string(),
+                        "Read".to_string(),
+                        "Write".to_string(),
+                        "Edit".to_string(),
+                        "MultiEdit".to_string(),
+                        "Glob".to_string(),
+                        "Grep".to_string(),
                     ],
                 ),
                 (
                     "optimizer".to_string(),
                     vec![
-                        "profile".to_string(),
-                        "analyze".to_string(),
-                        "benchmark".to_string(),
+                        "Read".to_string(),
+                        "Grep".to_string(),
+                        "Glob".to_string(),
+                        "Bash".to_string(),
                     ],
                 ),
             ])),
-            default_tools: Some(vec!["message".to_string(), "communication".to_string()]),
+            default_tools: Some(vec!["TodoWrite".to_string(), "Task".to_string()]),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Picks a model tier from the estimated token count of a task.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct ModelTieringConfig {
+    /// Whether tiering is applied; when off every task uses the fallback model.
     pub enabled: bool,
+    /// Tasks estimated below this many tokens go to Haiku.
+    #[schemars(range(min = 1))]
     pub simple_haiku_threshold: u32,
+    /// Tasks estimated below this many tokens go to Sonnet. Must exceed `simple_haiku_threshold`.
+    #[schemars(range(min = 1))]
     pub moderate_sonnet_threshold: u32,
+    /// Model used for complex tasks and when tiering is disabled.
+    #[schemars(length(min = 1))]
     pub fallback_model: String,
+    /// Whether high-impact roles are bumped up one tier.
     pub high_impact_boost_enabled: bool,
 }
 
@@ -107,14 +136,26 @@ impl Default for ModelTieringConfig {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Prunes agents whose contribution stays low and rebalances the swarm.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SelfHealingConfig {
+    /// Whether contribution tracking is active.
     pub enabled: bool,
+    /// Whether low contributors are pruned automatically.
     pub auto_prune_enabled: bool,
+    /// Contribution score below which an agent becomes a prune candidate.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub prune_threshold: f64,
+    /// Consecutive turns below the threshold before pruning.
+    #[schemars(range(min = 1))]
     pub prune_over_turns: usize,
+    /// Whether remaining agents are rebalanced after a prune.
     pub auto_rebalance_on_prune: bool,
+    /// Pruning never reduces the swarm below this many agents.
+    #[schemars(range(min = 1))]
     pub min_active_agents: usize,
+    /// Extra margin below the threshold required before pruning.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub prune_safety_margin: f64,
 }
 
@@ -132,10 +173,16 @@ impl Default for SelfHealingConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Location of the per-project override file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct SharedConfigSettings {
+    /// Whether the project override file is read.
     pub enabled: bool,
+    /// Directory, relative to the project root, holding swarm-tools state.
+    #[schemars(length(min = 1))]
     pub config_dir: String,
+    /// File name of the project override inside `config_dir`.
+    #[schemars(length(min = 1, max = 255))]
     pub override_file: String,
 }
 
@@ -149,6 +196,459 @@ impl Default for SharedConfigSettings {
     }
 }
 
+/// What the `user_prompt_submit` hook adds to Claude's context for each prompt.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct PromptRoutingConfig {
+    /// Whether the hook adds anything at all.
+    pub enabled: bool,
+    /// Suggest the agent role that best fits the prompt.
+    pub suggest_role: bool,
+    /// Suggest a team composition for the task.
+    pub suggest_team: bool,
+    /// Suggest a model tier for the task.
+    pub suggest_model: bool,
+    /// Include a codified step-by-step plan.
+    pub include_plan: bool,
+    /// Most plan steps listed in the context.
+    #[schemars(range(min = 1))]
+    pub max_plan_steps: usize,
+    /// Prompts shorter than this many characters are left alone.
+    pub min_prompt_chars: usize,
+    /// Record the suggested role for the session, so `pre_tool_use` filters
+    /// tools by it. Off by default: `pre_tool_use` then only filters agents
+    /// given a role with `--role`, `SWARM_TOOLS_AGENT_ROLE` or a role file,
+    /// rather than denying tools on the strength of a prompt's wording.
+    pub record_role: bool,
+}
+
+impl Default for PromptRoutingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            suggest_role: true,
+            suggest_team: true,
+            suggest_model: true,
+            include_plan: true,
+            max_plan_steps: 5,
+            min_prompt_chars: 20,
+            record_role: false,
+        }
+    }
+}
+
+/// Second stage [`crate::role_router::RoleRouter::route`] runs when the top
+/// roles score too close to call.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RerankerKind {
+    /// Break embedding ties with the roles' keyword tables.
+    #[default]
+    Keyword,
+    /// Report close calls as ambiguous straight away, unless the router was
+    /// given a reranker of its own.
+    None,
+}
+
+/// How sure role routing must be before it names a role.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct RoleRoutingConfig {
+    /// Smallest lead over the runner-up, in similarity, for the first stage to
+    /// decide on its own.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub min_margin: f64,
+    /// Softmax temperature turning role scores into a confidence; lower is
+    /// more decisive. Must be above 0.
+    #[schemars(schema_with = "temperature_schema")]
+    pub temperature: f64,
+    pub reranker: RerankerKind,
+}
+
+/// Scores are divided by the temperature, so 0 is excluded, matching
+/// `config_validation`.
+fn temperature_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    let mut schema = f64::json_schema(gen).into_object();
+    schema.number().exclusive_minimum = Some(0.0);
+    schema.number().maximum = Some(1.0);
+    schema.into()
+}
+
+impl Default for RoleRoutingConfig {
+    fn default() -> Self {
+        Self {
+            min_margin: 0.02,
+            temperature: 0.05,
+            reranker: RerankerKind::Keyword,
+        }
+    }
+}
+
+/// How corrections recorded with `swarm-tools roles label` reshape a role's
+/// prototype.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PrototypeStrategy {
+    /// Route by similarity to the mean of the description and its
+    /// corrections.
+    #[default]
+    Centroid,
+    /// Route by similarity to the closest of the description and its
+    /// corrections.
+    Exemplars,
+}
+
+/// Role prototypes learned from routing corrections.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct RolePrototypesConfig {
+    /// Whether recorded corrections are used by semantic routing.
+    pub enabled: bool,
+    pub strategy: PrototypeStrategy,
+    /// Newest corrections kept per role.
+    #[schemars(range(min = 1))]
+    pub max_exemplars_per_role: usize,
+}
+
+impl Default for RolePrototypesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            strategy: PrototypeStrategy::Centroid,
+            max_exemplars_per_role: 32,
+        }
+    }
+}
+
+/// Model tier a custom role's tasks are sent to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TierPreference {
+    Haiku,
+    Sonnet,
+    Opus,
+}
+
+/// How the team optimizer staffs a custom role.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct RoleCapabilitiesConfig {
+    /// Work done per hour relative to an analyzer.
+    pub efficiency: f64,
+    /// Estimated tokens the role spends per hour.
+    pub cost_per_hour: usize,
+    #[schemars(range(min = 1))]
+    pub max_concurrent_tasks: usize,
+}
+
+impl Default for RoleCapabilitiesConfig {
+    fn default() -> Self {
+        Self {
+            efficiency: 1.0,
+            cost_per_hour: 5000,
+            max_concurrent_tasks: 2,
+        }
+    }
+}
+
+/// A user-defined role, declared under `custom_roles` by name. Fields left
+/// out keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct CustomRoleConfig {
+    /// What the role does; embedded as its prototype for semantic routing.
+    pub description: String,
+    /// Words that route a task to the role, add it to the task's team and
+    /// mark context as relevant to it.
+    pub keywords: Vec<String>,
+    /// Tools the role may call, matched like `mcp_routing.role_tool_filters`;
+    /// empty allows every tool.
+    pub tools: Vec<String>,
+    /// Model tier for the role's tasks; unset leaves it to `model_tiering`.
+    pub model_tier: Option<TierPreference>,
+    /// Weight of the role's plan steps; the built-in roles range from 0.5 to
+    /// 0.9.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub impact: f64,
+    pub capabilities: RoleCapabilitiesConfig,
+}
+
+impl CustomRoleConfig {
+    /// The text embedded for the role named `name`: its description, or
+    /// without one, its name and keywords.
+    pub fn routing_description(&self, name: &str) -> String {
+        if !self.description.trim().is_empty() {
+            return self.description.clone();
+        }
+        format!(
+            "{}: {}",
+            name.replace(['-', '_'], " "),
+            self.keywords.join(", ")
+        )
+    }
+}
+
+impl Default for CustomRoleConfig {
+    fn default() -> Self {
+        Self {
+            description: String::new(),
+            keywords: Vec::new(),
+            tools: Vec::new(),
+            model_tier: None,
+            impact: 0.7,
+            capabilities: RoleCapabilitiesConfig::default(),
+        }
+    }
+}
+
+/// How long finished sessions' hook state is kept.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct SessionRetentionConfig {
+    /// Whether stale sessions are cleaned up when a session stops.
+    pub enabled: bool,
+    /// Sessions with no activity for this many days are stale.
+    #[schemars(range(min = 1))]
+    pub max_age_days: u64,
+    /// Most sessions kept, counting the current one; older ones are stale.
+    #[schemars(range(min = 1))]
+    pub max_sessions: usize,
+    /// Move stale sessions to `<config_dir>/archive/` instead of deleting them.
+    pub archive: bool,
+}
+
+impl Default for SessionRetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age_days: 7,
+            max_sessions: 50,
+            archive: false,
+        }
+    }
+}
+
+/// Where the embedding model files live and where `swarm-tools models fetch`
+/// gets them. Nothing is downloaded outside that command.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ModelStoreConfig {
+    /// Model directory. `SWARM_TOOLS_MODEL_DIR` takes precedence; when neither
+    /// is set, models provisioned by the build are used, then
+    /// `$XDG_DATA_HOME/swarm-tools/models`. The default model's files sit
+    /// directly in it, every other model's in a subdirectory named after it.
+    pub model_dir: Option<PathBuf>,
+    /// Never touch the network; `models fetch` fails instead.
+    /// `SWARM_TOOLS_OFFLINE=1` does the same.
+    pub offline: bool,
+    /// Fetch each file from `<mirror_url>/<file name>` instead of Hugging Face;
+    /// files of models other than the default from
+    /// `<mirror_url>/<model>/<file name>`.
+    pub mirror_url: Option<String>,
+    /// Expected SHA-256 per model file name. These take precedence over the
+    /// model's registry entry; `models fetch` refuses files neither pins.
+    pub sha256: BTreeMap<String, String>,
+    /// Registry id of the embedding model to fetch and embed with; see
+    /// [`crate::model_store::builtin_models`].
+    pub model: String,
+    /// Registry entries added to, or replacing, the built-in ones.
+    pub models: BTreeMap<String, EmbeddingModelSpec>,
+}
+
+impl Default for ModelStoreConfig {
+    fn default() -> Self {
+        Self {
+            model_dir: None,
+            offline: false,
+            mirror_url: None,
+            sha256: BTreeMap::new(),
+            model: crate::model_store::DEFAULT_MODEL.to_string(),
+            models: BTreeMap::new(),
+        }
+    }
+}
+
+/// How a model's token embeddings become one text embedding.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Pooling {
+    /// Average over the tokens the attention mask keeps.
+    #[default]
+    Mean,
+    /// The first (`[CLS]`) token's embedding.
+    Cls,
+}
+
+/// An embedding model in the registry. Fields left out keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(default)]
+pub struct EmbeddingModelSpec {
+    /// Download URL per file in the model's directory; the engine loads
+    /// `tokenizer.json`, `config.json` and `model.onnx`.
+    pub files: BTreeMap<String, String>,
+    /// Expected SHA-256 per file name.
+    pub sha256: BTreeMap<String, String>,
+    pub pooling: Pooling,
+    /// Length of the model's embeddings.
+    #[schemars(range(min = 1))]
+    pub dimension: usize,
+    /// Prepended to texts being looked up, e.g. prompts routed to roles.
+    pub query_prefix: String,
+    /// Prepended to texts being searched, e.g. role descriptions and history.
+    pub passage_prefix: String,
+}
+
+impl Default for EmbeddingModelSpec {
+    fn default() -> Self {
+        Self {
+            files: BTreeMap::new(),
+            sha256: BTreeMap::new(),
+            pooling: Pooling::Mean,
+            dimension: crate::semantic_engine::DEFAULT_EMBEDDING_DIM,
+            query_prefix: String::new(),
+            passage_prefix: String::new(),
+        }
+    }
+}
+
+/// Which [`crate::embedder::Embedder`] semantic loop detection and role
+/// routing use.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbedderKind {
+    /// The best model the daemon loaded; word overlap and keywords otherwise.
+    #[default]
+    Auto,
+    /// all-MiniLM-L6-v2 through ONNX Runtime.
+    Onnx,
+    /// The MiniLM tokenizer's token ids, hashed.
+    TokenizerHash,
+    /// Hashed words; needs no model files.
+    WordHash,
+    /// BM25-weighted words, fitted on the session's own prompts.
+    Tfidf,
+    /// Deterministic one-hot vectors, for tests.
+    Stub,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct EmbedderConfig {
+    pub backend: EmbedderKind,
+    /// BM25 term-frequency saturation for `tfidf`.
+    #[schemars(range(min = 0.0))]
+    pub bm25_k1: f32,
+    /// BM25 document-length normalization for `tfidf`, from 0 to 1.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub bm25_b: f32,
+    /// Tokens the ONNX model sees per text; longer texts are truncated. Capped
+    /// at the model's `max_position_embeddings`.
+    #[schemars(range(min = 1))]
+    pub max_seq_length: usize,
+    /// Embeddings the daemon keeps in memory; 0 turns the cache off.
+    pub cache_capacity: usize,
+    /// Store embeddings of each agent's prompt history next to its
+    /// `_history.json`, so each prompt is embedded once.
+    pub disk_cache: bool,
+}
+
+impl Default for EmbedderConfig {
+    fn default() -> Self {
+        Self {
+            backend: EmbedderKind::Auto,
+            bm25_k1: 1.2,
+            bm25_b: 0.75,
+            max_seq_length: crate::semantic_engine::DEFAULT_MAX_SEQ_LENGTH,
+            cache_capacity: 1024,
+            disk_cache: true,
+        }
+    }
+}
+
+/// Nearest-neighbour index over every embedded prompt; see
+/// [`crate::vector_index`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct VectorIndexConfig {
+    /// Look for repeats across each agent's whole prompt history, not only the
+    /// last `loop_semantic_threshold` prompts. Needs an embedder.
+    pub enabled: bool,
+    /// Entries searched exhaustively; larger indexes are clustered.
+    #[schemars(range(min = 1))]
+    pub flat_limit: usize,
+    /// Clusters searched per query once an index is clustered.
+    #[schemars(range(min = 1))]
+    pub nprobe: usize,
+    /// Entries kept per index; the oldest are dropped first.
+    #[schemars(range(min = 1))]
+    pub max_entries: usize,
+}
+
+impl Default for VectorIndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            flat_limit: 1024,
+            nprobe: 4,
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// Loop detection on tool calls; see
+/// [`crate::loop_detector::LoopDetector::check_tool_call`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ToolLoopConfig {
+    /// Report a tool called over and over with the same arguments.
+    pub enabled: bool,
+    /// Seconds a call keeps counting towards a loop.
+    #[schemars(range(min = 1))]
+    pub window_secs: u64,
+    /// Identical calls within the window before a tool-call loop is
+    /// reported.
+    #[schemars(range(min = 1))]
+    pub threshold: usize,
+    /// Per-tool thresholds that replace `threshold`, keyed by tool name.
+    pub tool_thresholds: BTreeMap<String, usize>,
+    /// Argument fields ignored when comparing calls, at any depth, such as
+    /// timestamps or request IDs.
+    pub volatile_fields: Vec<String>,
+    /// Top-level arguments ignored for one tool only, keyed by tool name,
+    /// such as the free-text description of a shell command.
+    pub tool_volatile_fields: BTreeMap<String, Vec<String>>,
+}
+
+impl ToolLoopConfig {
+    /// Identical calls to `tool` that make a loop.
+    pub fn threshold_for(&self, tool: &str) -> usize {
+        self.tool_thresholds
+            .get(tool)
+            .copied()
+            .unwrap_or(self.threshold)
+    }
+}
+
+impl Default for ToolLoopConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_secs: 600,
+            threshold: 3,
+            tool_thresholds: ["Read", "Grep", "Glob", "LS"]
+                .into_iter()
+                .map(|tool| (tool.to_string(), 5))
+                .collect(),
+            volatile_fields: [
+                "timestamp",
+                "created_at",
+                "updated_at",
+                "request_id",
+                "nonce",
+            ]
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+            tool_volatile_fields: BTreeMap::from([(
+                "Bash".to_string(),
+                vec!["description".to_string()],
+            )]),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -186,4 +686,13 @@ mod tests {
         assert!(config.enabled);
         assert_eq!(config.config_dir, ".claude/swarm-tools");
     }
+
+    #[test]
+    fn test_prompt_routing_config_defaults() {
+        let config = PromptRoutingConfig::default();
+        assert!(config.enabled);
+        assert!(config.suggest_role && config.suggest_team && config.suggest_model);
+        assert!(config.include_plan);
+        assert!(!config.record_role);
+    }
 }
@@ -0,0 +1,1038 @@
+//! Hook event handlers shared by the hook binaries.
+//!
+//! Each handler takes a parsed [`HookInput`] and returns the [`HookOutput`]
+//! to print. State lives under `<cwd>/<shared_config.config_dir>`, so the
+//! hooks write into the project Claude Code is running in, wherever the
+//! binary itself is installed. Per-agent state is scoped to the session; see
+//! [`crate::session_state`]. Handlers run the same in-process and inside the
+//! daemon, which only adds [`SharedEngines`].
+
+use crate::config::SwarmToolsConfig;
+use crate::config_loader::{default_user_config_path, ConfigLoader};
+use crate::embedder::{select_embedder, Embedder};
+use crate::embedding_cache::CachedEmbedder;
+use crate::enhanced_monitor::{EnhancedMonitor, TrajectoryCompression};
+use crate::feature_config::{EmbedderKind, PromptRoutingConfig};
+use crate::hook_io::{recent_user_prompts, HookEvent, HookInput, HookOutput, PermissionDecision};
+use crate::loop_detector::LoopDetector;
+use crate::mcp_router::McpRoutingDecision;
+use crate::model_store::ModelStore;
+use crate::model_tier::ModelSelection;
+use crate::role_feedback::RoleFeedback;
+use crate::role_router::{MultiRouting, RoleRouter, RoutingDecision};
+use crate::savings_ledger::{LedgerEntry, SavingsLedger};
+use crate::security::{
+    read_file_with_limit, sanitize_agent_id, sanitize_error_message, MAX_FILE_SIZE,
+};
+use crate::self_healing::SelfHealingManager;
+use crate::semantic_engine::RoleEmbeddingStore;
+use crate::session_state::{
+    apply_retention, load_session, save_session, session_dir, SessionRecord, SessionSummary,
+};
+use crate::types::{AgentRole, LoopCycle, Plan, TeamComposition, TrajectoryEntry, TrajectoryLog};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Oldest entries are dropped once a trajectory file holds this many.
+pub const MAX_TRAJECTORY_ENTRIES: usize = 500;
+
+/// Longest `action` / `outcome` text stored per trajectory entry.
+const MAX_ENTRY_TEXT: usize = 200;
+
+/// Longest text quoted for each step of a detected cycle.
+const MAX_CYCLE_STEP_TEXT: usize = 80;
+
+/// How many previous entries are compared when flagging a repeat.
+const REPEAT_WINDOW: usize = 10;
+
+/// Environment variable naming the agent's role for tool routing.
+pub const ROLE_ENV_VAR: &str = "SWARM_TOOLS_AGENT_ROLE";
+
+/// Compaction only runs once the context window is (nearly) full.
+const PRE_COMPACT_CONTEXT_PCT: f64 = 1.0;
+
+/// Configuration and locations for handling one hook event.
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub config: SwarmToolsConfig,
+    pub session_id: String,
+    pub agent_id: String,
+    /// `<cwd>/<shared_config.config_dir>`; holds state shared by all sessions.
+    pub state_dir: PathBuf,
+    /// `<state_dir>/sessions/<session_id>`; holds this session's state.
+    pub session_dir: PathBuf,
+    /// Role given on the command line or in [`ROLE_ENV_VAR`].
+    pub role: Option<AgentRole>,
+    /// Engines kept warm by the daemon; `None` in-process.
+    pub engines: Option<SharedEngines>,
+}
+
+/// Settings for one hook event taken from where the hook runs, so the daemon
+/// applies the caller's environment rather than its own.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct HookOptions {
+    pub profile: Option<String>,
+    pub role: Option<AgentRole>,
+    /// User config file; `None` skips the user layer.
+    pub user_config_path: Option<PathBuf>,
+    /// The caller's `SWARM_TOOLS_*` environment variables.
+    pub env_vars: Vec<(String, String)>,
+}
+
+impl HookOptions {
+    /// Captures the process environment. The role falls back to
+    /// [`ROLE_ENV_VAR`].
+    pub fn from_env(profile: Option<String>, role: Option<AgentRole>) -> Self {
+        let role = role.or_else(|| {
+            let name = std::env::var(ROLE_ENV_VAR).ok()?;
+            AgentRole::from_name(&name)
+                .map_err(|_| eprintln!("Warning: Ignoring unknown role `{}`", name))
+                .ok()
+        });
+        Self {
+            profile,
+            role,
+            user_config_path: default_user_config_path(),
+            env_vars: std::env::vars()
+                .filter(|(key, _)| key.starts_with("SWARM_TOOLS_"))
+                .collect(),
+        }
+    }
+}
+
+impl HookContext {
+    /// Loads the layered configuration for the event's project and agent.
+    pub fn load(input: &HookInput, options: &HookOptions) -> Self {
+        let config = ConfigLoader::new()
+            .with_user_path(options.user_config_path.clone())
+            .with_env_vars(options.env_vars.clone())
+            .with_project_dir(input.project_dir())
+            .with_agent_id(agent_id(input))
+            .with_profile(options.profile.clone())
+            .load_or_default();
+        let role = options.role.clone().filter(|role| {
+            let known = config.knows_role(role);
+            if !known {
+                eprintln!("Warning: Ignoring undeclared custom role `{}`", role);
+            }
+            known
+        });
+        Self::new(config, input).with_role(role)
+    }
+
+    pub fn new(config: SwarmToolsConfig, input: &HookInput) -> Self {
+        let state_dir = input.project_dir().join(&config.shared_config.config_dir);
+        Self {
+            session_id: input.session_id.clone(),
+            agent_id: agent_id(input),
+            session_dir: session_dir(&state_dir, &input.session_id),
+            state_dir,
+            config,
+            role: None,
+            engines: None,
+        }
+    }
+
+    pub fn with_role(mut self, role: Option<AgentRole>) -> Self {
+        self.role = role;
+        self
+    }
+
+    pub fn with_engines(mut self, engines: SharedEngines) -> Self {
+        self.engines = Some(engines);
+        self
+    }
+
+    /// Role for this agent: the explicit one, else the one recorded in
+    /// `<session_dir>/roles/<agent_id>`.
+    pub fn resolve_role(&self) -> Option<AgentRole> {
+        self.role.clone().or_else(|| {
+            fs::read_to_string(self.role_path())
+                .ok()
+                .and_then(|name| self.config.resolve_role(name.trim()).ok())
+        })
+    }
+
+    pub fn role_path(&self) -> PathBuf {
+        self.session_dir.join("roles").join(&self.agent_id)
+    }
+
+    /// Tool calls recorded by [`post_tool_use`]. Tool events don't name the
+    /// subagent that made them, so this is keyed by the session even when the
+    /// context is a subagent's.
+    pub fn trajectory_path(&self) -> PathBuf {
+        self.session_dir.join("loop-detector").join(format!(
+            "{}_trajectory.json",
+            sanitize_agent_id(&self.session_id)
+        ))
+    }
+
+    /// Shared engines, when they were built for the configured embedder and
+    /// model; after a switch, their embeddings would not compare with ones
+    /// made since.
+    ///
+    /// A TF-IDF embedder is fitted per session, so it is never shared.
+    fn shared(&self) -> Option<&SharedEngines> {
+        let kind = self.config.embedder.backend;
+        self.engines.as_ref().filter(|engines| {
+            engines.embedder_kind == kind
+                && engines.model == self.config.model_store.model
+                && kind != EmbedderKind::Tfidf
+        })
+    }
+
+    /// Embedder chosen by `embedder.backend`: the shared one when it matches,
+    /// else one built for this event.
+    ///
+    /// `auto` only embeds with engines the daemon keeps warm; loading a model
+    /// for every in-process event would cost more than the hook is worth.
+    /// `tfidf` is fitted on the prompts recorded for this session.
+    pub fn embedder(&self) -> Option<Arc<dyn Embedder>> {
+        if let Some(engines) = self.shared() {
+            return engines.embedder.clone();
+        }
+        let corpus = match self.config.embedder.backend {
+            EmbedderKind::Auto => return None,
+            EmbedderKind::Tfidf => self.session_prompts(),
+            _ => Vec::new(),
+        };
+        let store = ModelStore::resolve(&self.config.model_store);
+        select_embedder(&self.config.embedder, &store, &corpus)
+    }
+
+    /// Prompts the loop detector recorded for any agent in this session.
+    fn session_prompts(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(self.session_dir.join("loop-detector")) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.ends_with("_history.json"))
+            })
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .filter_map(|path| read_file_with_limit(path, MAX_FILE_SIZE).ok())
+            .filter_map(|content| serde_json::from_str::<Vec<String>>(&content).ok())
+            .flatten()
+            .collect()
+    }
+
+    fn loop_detector(&self) -> LoopDetector {
+        let detector = match self.embedder() {
+            Some(embedder) => LoopDetector::with_embedder(&self.config.swarm, embedder),
+            None => self.config.loop_detector(),
+        };
+        detector
+            .with_base_dir(&self.session_dir)
+            .with_disk_cache(self.config.embedder.disk_cache)
+            .with_vector_index(self.config.vector_index.clone())
+            .with_tool_loops(self.config.tool_loops.clone())
+    }
+
+    /// Role router using the shared role embeddings when they are loaded,
+    /// with the configured custom roles and prototypes learned from the
+    /// project's routing corrections.
+    pub fn role_router(&self) -> RoleRouter {
+        let keywords = self.config.role_router_keywords.clone();
+        let routing = self.config.role_routing.clone();
+        let router = match self
+            .shared()
+            .and_then(|engines| engines.role_embeddings.clone())
+        {
+            Some(store) => RoleRouter::with_role_embeddings(keywords, store).with_routing(routing),
+            None => match self.embedder() {
+                Some(embedder) => {
+                    RoleRouter::with_embedder(keywords, embedder).with_routing(routing)
+                }
+                None => return self.config.role_router(),
+            },
+        }
+        .with_custom_roles(&self.config.custom_roles);
+        let prototypes = &self.config.role_prototypes;
+        if !prototypes.enabled {
+            return router;
+        }
+        let exemplars =
+            RoleFeedback::new(&self.state_dir).exemplars(prototypes.max_exemplars_per_role);
+        router.with_exemplars(&exemplars, prototypes.strategy)
+    }
+
+    /// Runs `f` with the shared monitor, or a fresh one in-process.
+    fn with_monitor<R>(&self, f: impl FnOnce(&mut EnhancedMonitor) -> R) -> R {
+        match &self.engines {
+            Some(engines) => {
+                let mut monitor = engines
+                    .enhanced_monitor
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                monitor.set_total_context(self.config.swarm.context_budget);
+                f(&mut monitor)
+            }
+            None => f(&mut self.config.enhanced_monitor()),
+        }
+    }
+
+    /// Records activity in the shared monitor and self-healing manager.
+    ///
+    /// In-process there is nothing that outlives the event, so this does
+    /// nothing.
+    fn track(&self, f: impl FnOnce(&mut EnhancedMonitor, &mut SelfHealingManager)) {
+        let Some(engines) = &self.engines else {
+            return;
+        };
+        let mut healing = engines
+            .self_healing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        healing.update_config(self.config.self_healing.clone());
+        self.with_monitor(|monitor| f(monitor, &mut healing));
+    }
+}
+
+/// Engines that are expensive to build, kept across hook events by the
+/// daemon; see [`crate::daemon`].
+#[derive(Clone)]
+pub struct SharedEngines {
+    /// `None` when no embedder could be loaded, or semantic matching is off.
+    pub embedder: Option<Arc<dyn Embedder>>,
+    /// The `embedder.backend` the embedder was selected for.
+    pub embedder_kind: EmbedderKind,
+    /// The `model_store.model` the embedder was loaded for.
+    pub model: String,
+    /// Only present when there is an embedder.
+    pub role_embeddings: Option<Arc<RoleEmbeddingStore>>,
+    pub enhanced_monitor: Arc<Mutex<EnhancedMonitor>>,
+    pub self_healing: Arc<Mutex<SelfHealingManager>>,
+}
+
+impl SharedEngines {
+    /// Wraps `embedder` in an in-memory cache of `embedder.cache_capacity`
+    /// embeddings, so repeated prompts are embedded once per daemon.
+    pub fn new(embedder: Option<Arc<dyn Embedder>>, config: &SwarmToolsConfig) -> Self {
+        let embedder = match NonZeroUsize::new(config.embedder.cache_capacity) {
+            Some(capacity) => embedder.map(|embedder| {
+                Arc::new(CachedEmbedder::new(embedder, capacity)) as Arc<dyn Embedder>
+            }),
+            None => embedder,
+        };
+        let role_embeddings = embedder
+            .as_ref()
+            .map(|embedder| Arc::new(RoleEmbeddingStore::new(Arc::clone(embedder))));
+        Self {
+            embedder,
+            embedder_kind: config.embedder.backend,
+            model: config.model_store.model.clone(),
+            role_embeddings,
+            enhanced_monitor: Arc::new(Mutex::new(config.enhanced_monitor())),
+            self_healing: Arc::new(Mutex::new(config.self_healing_manager())),
+        }
+    }
+}
+
+impl fmt::Debug for SharedEngines {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedEngines")
+            .field("embedder", &self.embedder.as_ref().map(|e| e.name()))
+            .field("embedder_kind", &self.embedder_kind)
+            .field("model", &self.model)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Identifies the agent an event belongs to: the subagent when Claude Code
+/// names one, otherwise the session.
+pub fn agent_id(input: &HookInput) -> String {
+    let raw = match &input.event {
+        HookEvent::SubagentStop {
+            agent_id: Some(id), ..
+        } => id.as_str(),
+        _ => input.session_id.as_str(),
+    };
+    sanitize_agent_id(raw)
+}
+
+/// Routes an event to its handler. Events without one proceed untouched.
+pub fn dispatch(ctx: &HookContext, input: &HookInput) -> HookOutput {
+    match &input.event {
+        HookEvent::PreCompact { trigger, .. } => pre_compact(ctx, input, trigger),
+        HookEvent::SubagentStop {
+            stop_hook_active, ..
+        } => subagent_stop(ctx, input, *stop_hook_active),
+        HookEvent::PreToolUse {
+            tool_name,
+            tool_input,
+        } => pre_tool_use(ctx, input, tool_name, tool_input),
+        HookEvent::PostToolUse {
+            tool_name,
+            tool_input,
+            tool_response,
+        } => post_tool_use(ctx, tool_name, tool_input, tool_response),
+        HookEvent::UserPromptSubmit { prompt } => user_prompt_submit(ctx, prompt),
+        HookEvent::SessionStart { source } => session_start(ctx, source),
+        HookEvent::Stop { stop_hook_active } => stop(ctx, *stop_hook_active),
+        _ => HookOutput::proceed(),
+    }
+}
+
+/// Applies the MCP router's decision for the agent's role to a tool call.
+///
+/// Without a known role every call proceeds untouched, so the hook never
+/// restricts an agent nobody assigned a role to. An allowed call is left to
+/// Claude Code's normal permission checks; a denied one is refused with the
+/// router's reason. Trimmed arguments replace the original input but the call
+/// is put to the user (`ask`), since `allow` would also skip the permission
+/// prompt the user would otherwise see. Routing
+/// decisions and their estimated token savings are appended to the
+/// [`SavingsLedger`].
+pub fn pre_tool_use(
+    ctx: &HookContext,
+    input: &HookInput,
+    tool_name: &str,
+    tool_input: &Value,
+) -> HookOutput {
+    let Some(role) = ctx.resolve_role() else {
+        return HookOutput::proceed();
+    };
+
+    let result = ctx
+        .config
+        .mcp_router()
+        .route_tool_call(role.clone(), tool_name, tool_input);
+
+    let (decision, output) = match result.decision {
+        McpRoutingDecision::Allow => ("allow", HookOutput::proceed()),
+        McpRoutingDecision::Deny { reason } => (
+            "deny",
+            HookOutput::permission(PermissionDecision::Deny, reason),
+        ),
+        McpRoutingDecision::ModifyArgs { new_args } => {
+            let reason = format!("swarm-tools trimmed arguments for role '{}'", role.as_str());
+            (
+                "modify_args",
+                HookOutput::permission(PermissionDecision::Ask, reason)
+                    .with_updated_input(new_args),
+            )
+        }
+    };
+
+    if decision != "allow" || result.token_savings_estimate.is_some() {
+        let entry = LedgerEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            session_id: input.session_id.clone(),
+            agent_id: ctx.agent_id.clone(),
+            tool_name: tool_name.to_string(),
+            role: role.as_str().to_string(),
+            decision: decision.to_string(),
+            token_savings_estimate: result.token_savings_estimate,
+        };
+        if let Err(e) = SavingsLedger::new(&ctx.state_dir).record(&entry) {
+            eprintln!(
+                "Warning: Could not record tool savings: {}",
+                sanitize_error_message(&e.to_string())
+            );
+        }
+    }
+
+    output
+}
+
+/// Routing suggestions for one prompt.
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptRouting {
+    pub role: AgentRole,
+    /// How `role` was chosen and how sure the router is of it.
+    pub decision: RoutingDecision,
+    /// The role each clause of a compound prompt routes to.
+    pub multi: MultiRouting,
+    pub team: Option<TeamComposition>,
+    pub model: Option<ModelSelection>,
+    pub plan: Plan,
+}
+
+impl PromptRouting {
+    /// Runs role routing, task analysis, team optimization, model tiering and
+    /// plan codification on a prompt. The team is staffed with the roles the
+    /// prompt's clauses route to.
+    ///
+    /// Analysis failures drop the team and model suggestions rather than
+    /// failing the whole routing.
+    pub fn analyze(config: &SwarmToolsConfig, router: &RoleRouter, prompt: &str) -> Self {
+        let decision = router.route(prompt);
+        let role = decision.role.clone();
+        let multi = router.route_multi(prompt);
+        let plan = config
+            .codified_reasoning()
+            .codify_prompt(prompt, role.as_str());
+
+        let analysis = config.task_analyzer().analyze_task(prompt).ok();
+        let team = analysis.as_ref().and_then(|analysis| {
+            config
+                .team_optimizer()
+                .optimize_routed_team(analysis, &multi)
+                .ok()
+        });
+        let model = analysis.as_ref().map(|analysis| {
+            let impact = plan
+                .steps
+                .iter()
+                .map(|step| step.impact_score)
+                .fold(0.5, f64::max);
+            config.model_tierer().select_model_for_role(
+                &role,
+                plan.total_expected_tokens,
+                analysis.complexity,
+                impact,
+            )
+        });
+
+        Self {
+            role,
+            decision,
+            multi,
+            team,
+            model,
+            plan,
+        }
+    }
+
+    /// Formats the suggestions switched on in `settings`, one per line.
+    pub fn render(&self, settings: &PromptRoutingConfig) -> Vec<String> {
+        let mut lines = Vec::new();
+        if settings.suggest_role && self.multi.roles.len() > 1 {
+            let roles: Vec<&str> = self.multi.roles.iter().map(|r| r.as_str()).collect();
+            lines.push(format!("- Roles: {}", roles.join(", ")));
+            for clause in self.multi.clauses.iter().filter(|c| !c.decision.ambiguous) {
+                lines.push(format!(
+                    "  - {}: {}",
+                    clause.decision.role.as_str(),
+                    clause.clause
+                ));
+            }
+        } else if settings.suggest_role {
+            let note = if self.decision.ambiguous {
+                "no clear match, "
+            } else {
+                ""
+            };
+            lines.push(format!(
+                "- Role: {} ({}confidence {:.2})",
+                self.role.as_str(),
+                note,
+                self.decision.confidence
+            ));
+        }
+        if let Some(team) = self.team.as_ref().filter(|_| settings.suggest_team) {
+            let roles: Vec<&str> = team.roles.iter().map(|r| r.role.as_str()).collect();
+            lines.push(format!(
+                "- Team: {} agent(s) ({}), ~{:.1}h, efficiency {:.2}",
+                team.team_size,
+                roles.join(", "),
+                team.estimated_completion_time,
+                team.efficiency_score
+            ));
+        }
+        if let Some(model) = self.model.as_ref().filter(|_| settings.suggest_model) {
+            lines.push(format!(
+                "- Model: {} ({})",
+                model.model_name, model.reasoning
+            ));
+        }
+        if settings.include_plan && !self.plan.steps.is_empty() {
+            lines.push(format!(
+                "- Plan ({} step(s), ~{} tokens):",
+                self.plan.steps.len(),
+                self.plan.total_expected_tokens
+            ));
+            for step in self.plan.steps.iter().take(settings.max_plan_steps) {
+                lines.push(format!(
+                    "  {}. {} {} -> {}",
+                    step.step_number, step.action, step.target, step.expected_outcome
+                ));
+            }
+        }
+        lines
+    }
+}
+
+/// Suggests a role, team, model tier and plan for a prompt and adds them to
+/// Claude's context.
+///
+/// Each suggestion can be switched off under `prompt_routing`.
+pub fn user_prompt_submit(ctx: &HookContext, prompt: &str) -> HookOutput {
+    let settings = &ctx.config.prompt_routing;
+    if !settings.enabled || prompt.trim().chars().count() < settings.min_prompt_chars {
+        return HookOutput::proceed();
+    }
+
+    let routing = PromptRouting::analyze(&ctx.config, &ctx.role_router(), prompt);
+    if settings.record_role {
+        let recorded = fs::create_dir_all(ctx.session_dir.join("roles"))
+            .and_then(|_| fs::write(ctx.role_path(), routing.role.as_str()));
+        if let Err(e) = recorded {
+            eprintln!(
+                "Warning: Could not record role: {}",
+                sanitize_error_message(&e.to_string())
+            );
+        }
+    }
+
+    let mut lines = routing.render(settings);
+    if lines.is_empty() {
+        return HookOutput::proceed();
+    }
+    lines.insert(0, "swarm-tools routing for this prompt:".to_string());
+    HookOutput::proceed().with_additional_context("UserPromptSubmit", lines.join("\n"))
+}
+
+/// Checks the latest prompt for loops and compresses the stored trajectory
+/// before Claude Code compacts the conversation.
+///
+/// Compaction cannot be blocked, so findings are reported to the user as a
+/// system message.
+pub fn pre_compact(ctx: &HookContext, input: &HookInput, trigger: &str) -> HookOutput {
+    let mut notes = Vec::new();
+
+    let prompt = input
+        .transcript_path
+        .as_deref()
+        .and_then(|path| recent_user_prompts(path, 1).pop());
+    if let Some(prompt) = prompt {
+        let mut detector = ctx.loop_detector();
+        match detector.check_all_loops(&ctx.agent_id, &prompt, trigger) {
+            Ok(Some(detection)) => notes.push(match &detection.cycle {
+                Some(cycle) => format!(
+                    "loop detected ({:?}, {}); compaction will break it",
+                    detection.detection_type,
+                    describe_cycle(cycle)
+                ),
+                None => format!(
+                    "loop detected ({:?}, {} repeats); compaction will break it",
+                    detection.detection_type, detection.loop_count
+                ),
+            }),
+            Ok(None) => {}
+            Err(e) => eprintln!(
+                "Warning: Could not check for loops: {}",
+                sanitize_error_message(&e.to_string())
+            ),
+        }
+    }
+
+    match compress_trajectory(ctx) {
+        Ok(Some(summary)) => notes.push(summary),
+        Ok(None) => {}
+        Err(e) => eprintln!("Warning: {}", sanitize_error_message(&e)),
+    }
+
+    if notes.is_empty() {
+        HookOutput::proceed()
+    } else {
+        HookOutput::proceed().with_system_message(format!("swarm-tools: {}", notes.join("; ")))
+    }
+}
+
+fn compress_trajectory(ctx: &HookContext) -> Result<Option<String>, String> {
+    let path = ctx.trajectory_path();
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = read_file_with_limit(&path, MAX_FILE_SIZE)
+        .map_err(|e| format!("Could not read trajectory: {}", e))?;
+    let trajectory: TrajectoryLog =
+        serde_json::from_str(&content).map_err(|e| format!("Could not parse trajectory: {}", e))?;
+
+    let compressed = ctx.with_monitor(|monitor| {
+        monitor
+            .should_compress(
+                PRE_COMPACT_CONTEXT_PCT,
+                trajectory.entries.len(),
+                trajectory.tokens_used as usize,
+            )
+            .then(|| {
+                monitor.record_compaction(None);
+                monitor.compress_trajectory(&trajectory)
+            })
+    });
+    let Some(compressed) = compressed else {
+        return Ok(None);
+    };
+    let compressed_path =
+        path.with_file_name(format!("{}_trajectory_compressed.json", ctx.agent_id));
+    fs::write(
+        &compressed_path,
+        serde_json::to_string_pretty(&compressed).unwrap_or_default(),
+    )
+    .map_err(|e| format!("Could not save compressed trajectory: {}", e))?;
+
+    Ok(Some(format!(
+        "trajectory compressed from {} to {} entries (ratio {:.2})",
+        trajectory.entries.len(),
+        compressed.preserved.len(),
+        compressed.compression_ratio
+    )))
+}
+
+/// Records a finished tool call in the agent's trajectory and checks the
+/// call signature for loops.
+///
+/// On a loop the result is blocked, which shows Claude the reason so it can
+/// change approach; the tool itself has already run.
+pub fn post_tool_use(
+    ctx: &HookContext,
+    tool_name: &str,
+    tool_input: &Value,
+    tool_response: &Value,
+) -> HookOutput {
+    let signature = tool_call_signature(tool_name, tool_input);
+    let succeeded = tool_succeeded(tool_response);
+
+    let mut trajectory = load_trajectory(ctx);
+    let action = truncate(&signature, MAX_ENTRY_TEXT);
+    let is_repeat = trajectory
+        .entries
+        .iter()
+        .rev()
+        .take(REPEAT_WINDOW)
+        .any(|entry| entry.action == action);
+    let impact = impact_score(tool_name, succeeded, is_repeat);
+    let tokens_used = (tool_input.to_string().len() + tool_response.to_string().len()) / 4;
+    let entry = TrajectoryEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        impact_score: impact,
+        outcome: outcome_summary(tool_response),
+        tokens_used: tokens_used as u32,
+        action,
+        is_repeat,
+        succeeded,
+    };
+    append_entry(&mut trajectory, entry);
+    if let Err(e) = write_json(
+        &ctx.trajectory_path(),
+        &serde_json::to_value(&trajectory).unwrap_or_default(),
+    ) {
+        eprintln!(
+            "Warning: Could not save trajectory: {}",
+            sanitize_error_message(&e.to_string())
+        );
+    }
+
+    let state = if succeeded { "succeeded" } else { "failed" };
+    let mut detector = ctx.loop_detector();
+    let detection = detector.check_tool_loops(&ctx.agent_id, tool_name, tool_input, state);
+    ctx.track(|monitor, healing| {
+        monitor.record_token_usage(&ctx.agent_id, tokens_used, None);
+        if matches!(detection, Ok(Some(_))) {
+            monitor.record_loop_detection(&ctx.agent_id, None);
+        }
+        healing.record_contribution(&ctx.agent_id, if succeeded { impact } else { 0.0 });
+    });
+    match detection {
+        Ok(Some(detection)) => HookOutput::block(match &detection.cycle {
+            Some(cycle) => format!(
+                "swarm-tools: loop detected ({:?}): you have gone round {}. \
+                 Stop repeating these steps and try a different approach.",
+                detection.detection_type,
+                describe_cycle(cycle)
+            ),
+            None => format!(
+                "swarm-tools: loop detected ({:?}): `{}` has now run {} times with the same input. \
+                 Stop repeating it and try a different approach.",
+                detection.detection_type, tool_name, detection.loop_count
+            ),
+        }),
+        Ok(None) => HookOutput::proceed(),
+        Err(e) => {
+            eprintln!(
+                "Warning: Could not check for loops: {}",
+                sanitize_error_message(&e.to_string())
+            );
+            HookOutput::proceed()
+        }
+    }
+}
+
+/// Identifies a tool call by its name and input, e.g. `Bash {"command":"ls"}`.
+///
+/// Object keys are serialized in sorted order, so equal inputs always give the
+/// same signature.
+pub fn tool_call_signature(tool_name: &str, tool_input: &Value) -> String {
+    match tool_input {
+        Value::Null => tool_name.to_string(),
+        input => format!("{} {}", tool_name, input),
+    }
+}
+
+fn tool_succeeded(tool_response: &Value) -> bool {
+    if let Some(success) = tool_response.get("success").and_then(Value::as_bool) {
+        return success;
+    }
+    let is_error = tool_response
+        .get("is_error")
+        .or_else(|| tool_response.get("isError"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let has_error = tool_response
+        .get("error")
+        .is_some_and(|error| !error.is_null() && error != "");
+    !is_error && !has_error
+}
+
+fn outcome_summary(tool_response: &Value) -> String {
+    let text = match tool_response {
+        Value::String(text) => text.clone(),
+        Value::Null => "no output".to_string(),
+        other => other.to_string(),
+    };
+    let first_line = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    truncate(first_line.trim(), MAX_ENTRY_TEXT)
+}
+
+/// Failures and changes to files matter most when a trajectory is compressed;
+/// repeated calls matter least.
+fn impact_score(tool_name: &str, succeeded: bool, is_repeat: bool) -> f64 {
+    if !succeeded {
+        return 0.9;
+    }
+    if is_repeat {
+        return 0.2;
+    }
+    let name = tool_name.to_lowercase();
+    if ["write", "edit", "create", "delete", "move"]
+        .iter()
+        .any(|verb| name.contains(verb))
+    {
+        0.8
+    } else if name == "bash" || name.contains("exec") || name.contains("run") {
+        0.6
+    } else {
+        0.4
+    }
+}
+
+/// Quotes a cycle's steps, e.g. "the same 2 steps 3 times: `Read ...` ->
+/// `Edit ...`".
+fn describe_cycle(cycle: &LoopCycle) -> String {
+    let steps: Vec<String> = cycle
+        .body
+        .iter()
+        .map(|step| format!("`{}`", truncate(step, MAX_CYCLE_STEP_TEXT)))
+        .collect();
+    format!(
+        "the same {} steps {} times: {}",
+        cycle.period,
+        cycle.repeats,
+        steps.join(" -> ")
+    )
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars).collect();
+    truncated.push_str("...");
+    truncated
+}
+
+/// Reads the agent's trajectory, starting a new one if it is missing or unreadable.
+fn load_trajectory(ctx: &HookContext) -> TrajectoryLog {
+    read_file_with_limit(&ctx.trajectory_path(), MAX_FILE_SIZE)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_else(|| TrajectoryLog {
+            entries: Vec::new(),
+            tokens_used: 0,
+            compressibility_score: 0.0,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        })
+}
+
+fn append_entry(trajectory: &mut TrajectoryLog, entry: TrajectoryEntry) {
+    trajectory.entries.push(entry);
+    if trajectory.entries.len() > MAX_TRAJECTORY_ENTRIES {
+        let excess = trajectory.entries.len() - MAX_TRAJECTORY_ENTRIES;
+        trajectory.entries.drain(..excess);
+    }
+    trajectory.tokens_used = trajectory.entries.iter().map(|e| e.tokens_used).sum();
+    let repeats = trajectory.entries.iter().filter(|e| e.is_repeat).count();
+    trajectory.compressibility_score = repeats as f64 / trajectory.entries.len() as f64;
+}
+
+/// Creates the session directory and its `session.json`.
+///
+/// A resumed or compacted session keeps its existing record and state.
+pub fn session_start(ctx: &HookContext, source: &str) -> HookOutput {
+    if load_session(&ctx.session_dir).is_none() {
+        let record = SessionRecord {
+            session_id: ctx.session_id.clone(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            source: source.to_string(),
+            ..Default::default()
+        };
+        if let Err(e) = save_session(&ctx.session_dir, &record) {
+            eprintln!(
+                "Warning: Could not initialise session: {}",
+                sanitize_error_message(&e.to_string())
+            );
+        }
+    }
+    HookOutput::proceed().with_suppressed_output()
+}
+
+/// Refreshes the session summary and cleans up stale sessions.
+///
+/// Stop fires at the end of every turn, so the summary always reflects the
+/// session so far. Retention never touches the current session.
+pub fn stop(ctx: &HookContext, stop_hook_active: bool) -> HookOutput {
+    if stop_hook_active {
+        return HookOutput::proceed();
+    }
+
+    let mut record = load_session(&ctx.session_dir).unwrap_or_else(|| SessionRecord {
+        session_id: ctx.session_id.clone(),
+        started_at: chrono::Utc::now().to_rfc3339(),
+        source: "unknown".to_string(),
+        ..Default::default()
+    });
+    record.last_stopped_at = Some(chrono::Utc::now().to_rfc3339());
+    record.summary = Some(session_summary(ctx));
+    if let Err(e) = save_session(&ctx.session_dir, &record) {
+        eprintln!(
+            "Warning: Could not save session summary: {}",
+            sanitize_error_message(&e.to_string())
+        );
+    }
+
+    match apply_retention(
+        &ctx.state_dir,
+        &ctx.config.session_retention,
+        Some(&ctx.session_id),
+        std::time::SystemTime::now(),
+    ) {
+        Ok(report) => {
+            let removed = report.expired_sessions.len() + report.archived_sessions.len();
+            if removed > 0 || report.legacy_files > 0 || report.pruned_archive > 0 {
+                eprintln!(
+                    "swarm-tools: cleaned up {} stale session(s), {} legacy file(s) and {} archived item(s)",
+                    removed, report.legacy_files, report.pruned_archive
+                );
+            }
+        }
+        Err(e) => eprintln!(
+            "Warning: Could not apply session retention: {}",
+            sanitize_error_message(&e.to_string())
+        ),
+    }
+
+    HookOutput::proceed().with_suppressed_output()
+}
+
+fn session_summary(ctx: &HookContext) -> SessionSummary {
+    let mut summary = SessionSummary::default();
+
+    if let Ok(entries) = fs::read_dir(ctx.session_dir.join("loop-detector")) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_trajectory = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with("_trajectory.json"));
+            if !is_trajectory {
+                continue;
+            }
+            let Some(trajectory) = read_file_with_limit(&path, MAX_FILE_SIZE)
+                .ok()
+                .and_then(|content| serde_json::from_str::<TrajectoryLog>(&content).ok())
+            else {
+                continue;
+            };
+            summary.tool_calls += trajectory.entries.len();
+            summary.failed_tool_calls += trajectory.entries.iter().filter(|e| !e.succeeded).count();
+            summary.repeated_tool_calls +=
+                trajectory.entries.iter().filter(|e| e.is_repeat).count();
+            summary.trajectory_tokens += trajectory.tokens_used;
+        }
+    }
+
+    for entry in SavingsLedger::new(&ctx.state_dir).entries() {
+        if entry.session_id != ctx.session_id {
+            continue;
+        }
+        if entry.decision == "deny" {
+            summary.denied_tool_calls += 1;
+        }
+        summary.tokens_saved += entry.token_savings_estimate.unwrap_or(0);
+    }
+
+    summary
+}
+
+/// Saves state and a checkpoint for a finishing subagent so it can resume.
+///
+/// The subagent is always allowed to stop. When `stop_hook_active` is set the
+/// stop was already continued by a hook once, so nothing is written twice.
+pub fn subagent_stop(ctx: &HookContext, input: &HookInput, stop_hook_active: bool) -> HookOutput {
+    if stop_hook_active {
+        return HookOutput::proceed();
+    }
+
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    let trajectory_entries = fs::read_to_string(ctx.trajectory_path())
+        .ok()
+        .and_then(|content| serde_json::from_str::<TrajectoryLog>(&content).ok())
+        .map(|trajectory| trajectory.entries.len())
+        .unwrap_or(0);
+
+    let state = json!({
+        "agent_id": ctx.agent_id,
+        "session_id": input.session_id,
+        "transcript_path": input.transcript_path,
+        "timestamp": timestamp,
+        "status": "stopped",
+        "reason": "subagent_stop_hook",
+    });
+    let checkpoint = json!({
+        "agent_id": ctx.agent_id,
+        "timestamp": timestamp,
+        "checkpoint": true,
+        "metadata": {
+            "reason": "subagent_stop",
+            "hooks_triggered": ["subagent_stop"],
+            "trajectory_entries": trajectory_entries,
+        },
+    });
+
+    let file_name = format!("{}.json", ctx.agent_id);
+    for (dir, data) in [("states", &state), ("checkpoints", &checkpoint)] {
+        if let Err(e) = write_json(&ctx.session_dir.join(dir).join(&file_name), data) {
+            eprintln!(
+                "Warning: Could not save {}: {}",
+                dir,
+                sanitize_error_message(&e.to_string())
+            );
+        }
+    }
+
+    HookOutput::proceed().with_suppressed_output()
+}
+
+fn write_json(path: &Path, data: &serde_json::Value) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(data).unwrap_or_default())
+}
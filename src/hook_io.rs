@@ -0,0 +1,442 @@
+//! Claude Code hook protocol.
+//!
+//! Claude Code starts a hook command with a JSON payload on stdin and reads a
+//! JSON object from stdout when the command exits with status 0. Exit status 2
+//! is a blocking error whose stderr is fed back to Claude; any other non-zero
+//! status is a non-blocking error shown to the user.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Maximum size of a hook payload read from stdin.
+pub const MAX_HOOK_INPUT_SIZE: usize = crate::security::MAX_FILE_SIZE;
+
+/// How much of the end of a transcript is scanned for recent prompts.
+pub const TRANSCRIPT_TAIL_BYTES: u64 = 1024 * 1024; // 1MB
+
+/// Exit status that tells Claude Code to treat stderr as a blocking error.
+pub const EXIT_BLOCKING_ERROR: i32 = 2;
+
+#[derive(Debug, Error)]
+pub enum HookIoError {
+    #[error("Could not read hook input: {0}")]
+    Read(#[from] std::io::Error),
+    #[error("Hook input exceeds {0} bytes")]
+    TooLarge(usize),
+    #[error("Invalid hook input: {0}")]
+    Parse(String),
+    #[error("Unsupported hook event: {0}")]
+    UnsupportedEvent(String),
+}
+
+/// Fields Claude Code sends with every hook event.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HookInput {
+    pub session_id: String,
+    #[serde(default)]
+    pub transcript_path: Option<PathBuf>,
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    #[serde(default)]
+    pub permission_mode: Option<String>,
+    #[serde(flatten)]
+    pub event: HookEvent,
+}
+
+/// The event-specific part of a hook payload, keyed by `hook_event_name`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "hook_event_name")]
+pub enum HookEvent {
+    PreToolUse {
+        tool_name: String,
+        #[serde(default)]
+        tool_input: Value,
+    },
+    PostToolUse {
+        tool_name: String,
+        #[serde(default)]
+        tool_input: Value,
+        #[serde(default)]
+        tool_response: Value,
+    },
+    UserPromptSubmit {
+        prompt: String,
+    },
+    Notification {
+        #[serde(default)]
+        message: String,
+    },
+    Stop {
+        #[serde(default)]
+        stop_hook_active: bool,
+    },
+    SubagentStop {
+        #[serde(default)]
+        stop_hook_active: bool,
+        #[serde(default)]
+        agent_id: Option<String>,
+        #[serde(default)]
+        agent_transcript_path: Option<PathBuf>,
+    },
+    PreCompact {
+        /// `manual` or `auto`.
+        #[serde(default)]
+        trigger: String,
+        #[serde(default)]
+        custom_instructions: Option<String>,
+    },
+    SessionStart {
+        /// `startup`, `resume`, `clear` or `compact`.
+        #[serde(default)]
+        source: String,
+    },
+    SessionEnd {
+        #[serde(default)]
+        reason: String,
+    },
+}
+
+impl HookEvent {
+    pub const NAMES: [&'static str; 9] = [
+        "PreToolUse",
+        "PostToolUse",
+        "UserPromptSubmit",
+        "Notification",
+        "Stop",
+        "SubagentStop",
+        "PreCompact",
+        "SessionStart",
+        "SessionEnd",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::PreToolUse { .. } => "PreToolUse",
+            HookEvent::PostToolUse { .. } => "PostToolUse",
+            HookEvent::UserPromptSubmit { .. } => "UserPromptSubmit",
+            HookEvent::Notification { .. } => "Notification",
+            HookEvent::Stop { .. } => "Stop",
+            HookEvent::SubagentStop { .. } => "SubagentStop",
+            HookEvent::PreCompact { .. } => "PreCompact",
+            HookEvent::SessionStart { .. } => "SessionStart",
+            HookEvent::SessionEnd { .. } => "SessionEnd",
+        }
+    }
+}
+
+impl HookInput {
+    /// Parses a hook payload.
+    pub fn from_json(json: &str) -> Result<Self, HookIoError> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| HookIoError::Parse(e.to_string()))?;
+        let event_name = value
+            .get("hook_event_name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| HookIoError::Parse("missing hook_event_name".to_string()))?;
+        if !HookEvent::NAMES.contains(&event_name) {
+            return Err(HookIoError::UnsupportedEvent(event_name.to_string()));
+        }
+        serde_json::from_value(value).map_err(|e| HookIoError::Parse(e.to_string()))
+    }
+
+    /// Reads and parses a hook payload, refusing inputs over [`MAX_HOOK_INPUT_SIZE`].
+    pub fn read_from(reader: impl Read) -> Result<Self, HookIoError> {
+        let mut buffer = String::new();
+        reader
+            .take(MAX_HOOK_INPUT_SIZE as u64 + 1)
+            .read_to_string(&mut buffer)?;
+        if buffer.len() > MAX_HOOK_INPUT_SIZE {
+            return Err(HookIoError::TooLarge(MAX_HOOK_INPUT_SIZE));
+        }
+        Self::from_json(&buffer)
+    }
+
+    /// Project directory the hook runs for, falling back to the process directory.
+    pub fn project_dir(&self) -> PathBuf {
+        self.cwd.clone().unwrap_or_else(|| PathBuf::from("."))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    /// Stops the action; `reason` is shown to Claude.
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionDecision {
+    Allow,
+    Deny,
+    Ask,
+}
+
+/// Event-specific output, nested under `hookSpecificOutput`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HookSpecificOutput {
+    pub hook_event_name: String,
+    /// Text added to Claude's context (UserPromptSubmit, SessionStart, PostToolUse).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_context: Option<String>,
+    /// PreToolUse only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_decision: Option<PermissionDecision>,
+    /// PreToolUse only; shown to Claude on deny, to the user otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_decision_reason: Option<String>,
+    /// PreToolUse only; replaces the tool input before the call runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_input: Option<Value>,
+}
+
+/// JSON written to stdout for Claude Code. An empty object means "carry on".
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HookOutput {
+    /// `false` stops Claude entirely, showing `stop_reason` to the user.
+    #[serde(rename = "continue", skip_serializing_if = "Option::is_none")]
+    pub continue_: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    /// Hides stdout from the transcript view.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppress_output: Option<bool>,
+    /// Message shown to the user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<Decision>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook_specific_output: Option<HookSpecificOutput>,
+}
+
+impl HookOutput {
+    /// Lets the action proceed without comment.
+    pub fn proceed() -> Self {
+        Self::default()
+    }
+
+    /// Blocks the action (a stop, prompt or tool result) and tells Claude why.
+    pub fn block(reason: impl Into<String>) -> Self {
+        Self {
+            decision: Some(Decision::Block),
+            reason: Some(reason.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Stops Claude altogether.
+    pub fn halt(stop_reason: impl Into<String>) -> Self {
+        Self {
+            continue_: Some(false),
+            stop_reason: Some(stop_reason.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Answers a PreToolUse event.
+    pub fn permission(decision: PermissionDecision, reason: impl Into<String>) -> Self {
+        Self {
+            hook_specific_output: Some(HookSpecificOutput {
+                hook_event_name: "PreToolUse".to_string(),
+                permission_decision: Some(decision),
+                permission_decision_reason: Some(reason.into()),
+                ..Default::default()
+            }),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_system_message(mut self, message: impl Into<String>) -> Self {
+        self.system_message = Some(message.into());
+        self
+    }
+
+    pub fn with_suppressed_output(mut self) -> Self {
+        self.suppress_output = Some(true);
+        self
+    }
+
+    /// Adds context for Claude, creating `hookSpecificOutput` for `event_name` if needed.
+    pub fn with_additional_context(mut self, event_name: &str, context: impl Into<String>) -> Self {
+        let specific = self
+            .hook_specific_output
+            .get_or_insert_with(|| HookSpecificOutput {
+                hook_event_name: event_name.to_string(),
+                ..Default::default()
+            });
+        specific.additional_context = Some(context.into());
+        self
+    }
+
+    /// Replaces the tool input of a PreToolUse call.
+    pub fn with_updated_input(mut self, input: Value) -> Self {
+        let specific = self
+            .hook_specific_output
+            .get_or_insert_with(|| HookSpecificOutput {
+                hook_event_name: "PreToolUse".to_string(),
+                ..Default::default()
+            });
+        specific.updated_input = Some(input);
+        self
+    }
+
+    pub fn is_blocking(&self) -> bool {
+        self.decision == Some(Decision::Block)
+            || self.continue_ == Some(false)
+            || self
+                .hook_specific_output
+                .as_ref()
+                .and_then(|s| s.permission_decision)
+                == Some(PermissionDecision::Deny)
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        serde_json::to_writer(&mut writer, self)?;
+        writeln!(writer)
+    }
+}
+
+/// Returns up to `limit` of the most recent user prompts in a transcript,
+/// oldest first.
+///
+/// Only the last [`TRANSCRIPT_TAIL_BYTES`] of the file are scanned, so long
+/// sessions stay cheap. Tool results, which the transcript also records as
+/// user messages, are skipped.
+pub fn recent_user_prompts(transcript_path: &Path, limit: usize) -> Vec<String> {
+    let Ok(tail) = read_tail(transcript_path, TRANSCRIPT_TAIL_BYTES) else {
+        return Vec::new();
+    };
+
+    let mut prompts: Vec<String> = tail
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|entry| entry.get("type").and_then(Value::as_str) == Some("user"))
+        .filter_map(|entry| user_text(entry.get("message")?.get("content")?))
+        .take(limit)
+        .collect();
+    prompts.reverse();
+    prompts
+}
+
+fn user_text(content: &Value) -> Option<String> {
+    match content {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(blocks) => {
+            let text: Vec<&str> = blocks
+                .iter()
+                .filter(|b| b.get("type").and_then(Value::as_str) == Some("text"))
+                .filter_map(|b| b.get("text").and_then(Value::as_str))
+                .collect();
+            if text.is_empty() {
+                None
+            } else {
+                Some(text.join("\n"))
+            }
+        }
+        _ => None,
+    }
+}
+
+fn read_tail(path: &Path, max_bytes: u64) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(max_bytes);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+
+    // Drop the partial first line when starting mid-file.
+    if start > 0 {
+        Ok(text
+            .split_once('\n')
+            .map(|(_, rest)| rest.to_string())
+            .unwrap_or_default())
+    } else {
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_pre_compact() {
+        let input = HookInput::from_json(
+            r#"{"session_id": "abc", "transcript_path": "/tmp/t.jsonl", "cwd": "/proj",
+                "hook_event_name": "PreCompact", "trigger": "auto", "custom_instructions": ""}"#,
+        )
+        .unwrap();
+        assert_eq!(input.session_id, "abc");
+        assert_eq!(input.project_dir(), PathBuf::from("/proj"));
+        assert_eq!(input.event.name(), "PreCompact");
+        assert!(
+            matches!(input.event, HookEvent::PreCompact { ref trigger, .. } if trigger == "auto")
+        );
+    }
+
+    #[test]
+    fn test_parse_pre_tool_use() {
+        let input = HookInput::from_json(
+            r#"{"session_id": "abc", "hook_event_name": "PreToolUse",
+                "tool_name": "Bash", "tool_input": {"command": "ls"}}"#,
+        )
+        .unwrap();
+        let HookEvent::PreToolUse {
+            tool_name,
+            tool_input,
+        } = input.event
+        else {
+            panic!("expected PreToolUse");
+        };
+        assert_eq!(tool_name, "Bash");
+        assert_eq!(tool_input["command"], "ls");
+    }
+
+    #[test]
+    fn test_unsupported_event() {
+        let err =
+            HookInput::from_json(r#"{"session_id": "a", "hook_event_name": "Other"}"#).unwrap_err();
+        assert!(matches!(err, HookIoError::UnsupportedEvent(name) if name == "Other"));
+    }
+
+    #[test]
+    fn test_output_serialization() {
+        let output = HookOutput::block("loop detected")
+            .with_additional_context("SubagentStop", "ctx")
+            .with_suppressed_output();
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({
+                "decision": "block",
+                "reason": "loop detected",
+                "suppressOutput": true,
+                "hookSpecificOutput": {"hookEventName": "SubagentStop", "additionalContext": "ctx"}
+            })
+        );
+        assert!(output.is_blocking());
+
+        let deny = HookOutput::permission(PermissionDecision::Deny, "not for this role");
+        assert_eq!(
+            serde_json::to_value(&deny).unwrap()["hookSpecificOutput"]["permissionDecision"],
+            "deny"
+        );
+        assert_eq!(
+            serde_json::to_value(HookOutput::proceed()).unwrap(),
+            json!({})
+        );
+    }
+}
@@ -1,22 +1,40 @@
+pub mod cli;
 pub mod codified_reasoning;
 pub mod communication_optimizer;
 pub mod config;
+pub mod config_loader;
+pub mod config_profiles;
+pub mod config_schema;
+pub mod config_validation;
+pub mod config_watcher;
 pub mod cost_benefit;
+#[cfg(unix)]
+pub mod daemon;
+pub mod doctor;
+pub mod embedder;
+pub mod embedding_cache;
 pub mod enhanced_monitor;
 pub mod feature_config;
+pub mod hook_handlers;
+pub mod hook_io;
 pub mod iterative_refinement;
 pub mod loop_detector;
 pub mod mcp_router;
+pub mod model_store;
 pub mod model_tier;
 pub mod omac_optimizer;
 pub mod parallel_execution;
 pub mod quality_gate;
+pub mod role_feedback;
 pub mod role_router;
+pub mod savings_ledger;
 pub mod security;
 pub mod self_healing;
 pub mod semantic_engine;
+pub mod session_state;
 pub mod team_optimizer;
 pub mod trajectory_compressor;
 pub mod types;
+pub mod vector_index;
 
 pub use types::*;
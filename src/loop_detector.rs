@@ -1,58 +1,130 @@
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::semantic_engine::SemanticEngine;
-use crate::types::{LoopDetection, LoopType, Result};
+use crate::embedder::{cosine_similarity, Embedder};
+use crate::embedding_cache::EmbeddingFile;
+use crate::feature_config::{ToolLoopConfig, VectorIndexConfig};
+use crate::types::{LoopCycle, LoopDetection, LoopType, Result};
+use crate::vector_index::{Neighbor, VectorIndex};
 use hex::encode;
+use serde_json::Value;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+/// Actions kept per agent for cycle detection.
+pub const ACTION_HISTORY_LEN: usize = 50;
+
+/// States kept per agent for oscillation detection.
+pub const STATE_HISTORY_LEN: usize = 20;
+
+/// Tool calls kept per agent, however recent.
+const TOOL_CALL_HISTORY_LEN: usize = 1000;
+
+/// One tool call, by the hash of its normalized signature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ToolCallRecord {
+    signature: String,
+    tool: String,
+    /// Unix time in seconds.
+    at: i64,
+}
+
 pub struct LoopDetector {
     exact_loop_threshold: usize,
     semantic_loop_threshold: usize,
     state_oscillation_threshold: usize,
+    cycle_repeats: usize,
+    cycle_max_period: usize,
+    cycle_tolerance: f64,
     semantic_similarity_threshold: f64,
     base_dir: PathBuf,
-    semantic_engine: Arc<SemanticEngine>,
-    use_semantic: bool,
+    /// Compares prompts by embedding when set, else by Jaccard similarity.
+    embedder: Option<Arc<dyn Embedder>>,
+    /// Keeps history embeddings in `<agent>_embeddings.json`.
+    disk_cache: bool,
+    /// Indexes every embedded prompt under `vector-index/`, so repeats are
+    /// found across the whole history rather than only the recent window.
+    vector_index: VectorIndexConfig,
+    /// Thresholds and normalization for [`LoopDetector::check_tool_call`].
+    tool_loops: ToolLoopConfig,
 }
 
 impl LoopDetector {
     pub fn new(config: &crate::types::SwarmConfig) -> Self {
-        let semantic_engine = Arc::new(SemanticEngine::new());
-        let use_semantic = semantic_engine.is_loaded();
-
         Self {
             exact_loop_threshold: config.loop_exact_threshold,
             semantic_loop_threshold: config.loop_semantic_threshold,
             state_oscillation_threshold: config.loop_state_oscillation_threshold,
+            cycle_repeats: config.loop_cycle_repeats,
+            cycle_max_period: config.loop_cycle_max_period,
+            cycle_tolerance: config.loop_cycle_tolerance,
             semantic_similarity_threshold: 0.85,
             base_dir: PathBuf::from(".claude/swarm-tools"),
-            semantic_engine,
-            use_semantic,
+            embedder: None,
+            disk_cache: true,
+            vector_index: VectorIndexConfig::default(),
+            tool_loops: ToolLoopConfig::default(),
         }
     }
 
-    pub fn with_semantic_engine(
-        config: &crate::types::SwarmConfig,
-        semantic_engine: Arc<SemanticEngine>,
-    ) -> Self {
-        let use_semantic = semantic_engine.is_loaded();
-
+    pub fn with_embedder(config: &crate::types::SwarmConfig, embedder: Arc<dyn Embedder>) -> Self {
         Self {
             exact_loop_threshold: config.loop_exact_threshold,
             semantic_loop_threshold: config.loop_semantic_threshold,
             state_oscillation_threshold: config.loop_state_oscillation_threshold,
+            cycle_repeats: config.loop_cycle_repeats,
+            cycle_max_period: config.loop_cycle_max_period,
+            cycle_tolerance: config.loop_cycle_tolerance,
             semantic_similarity_threshold: 0.85,
             base_dir: PathBuf::from(".claude/swarm-tools"),
-            semantic_engine,
-            use_semantic,
+            embedder: Some(embedder),
+            disk_cache: true,
+            vector_index: VectorIndexConfig::default(),
+            tool_loops: ToolLoopConfig::default(),
         }
     }
 
+    /// Keeps loop history under `base_dir` instead of `.claude/swarm-tools`.
+    pub fn with_base_dir(mut self, base_dir: impl Into<PathBuf>) -> Self {
+        self.base_dir = base_dir.into();
+        self
+    }
+
+    /// Whether to store history embeddings on disk; on by default.
+    pub fn with_disk_cache(mut self, disk_cache: bool) -> Self {
+        self.disk_cache = disk_cache;
+        self
+    }
+
+    /// Configures the prompt indexes; see [`VectorIndexConfig`].
+    pub fn with_vector_index(mut self, config: VectorIndexConfig) -> Self {
+        self.vector_index = config;
+        self
+    }
+
+    /// Configures tool-call loop detection; see [`ToolLoopConfig`].
+    pub fn with_tool_loops(mut self, config: ToolLoopConfig) -> Self {
+        self.tool_loops = config;
+        self
+    }
+
+    pub fn set_tool_loops(&mut self, config: ToolLoopConfig) {
+        self.tool_loops = config;
+    }
+
+    /// Applies new thresholds while keeping the embedder and on-disk history.
+    pub fn update_config(&mut self, config: &crate::types::SwarmConfig) {
+        self.exact_loop_threshold = config.loop_exact_threshold;
+        self.semantic_loop_threshold = config.loop_semantic_threshold;
+        self.state_oscillation_threshold = config.loop_state_oscillation_threshold;
+        self.cycle_repeats = config.loop_cycle_repeats;
+        self.cycle_max_period = config.loop_cycle_max_period;
+        self.cycle_tolerance = config.loop_cycle_tolerance;
+    }
+
     fn hash_prompt(&self, prompt: &str) -> String {
         let mut hasher = Sha256::new();
         hasher.update(prompt.as_bytes());
@@ -71,12 +143,40 @@ impl LoopDetector {
             .join(format!("{}_history.json", agent_id))
     }
 
+    fn get_embeddings_path(&self, agent_id: &str) -> PathBuf {
+        self.base_dir
+            .join("loop-detector")
+            .join(format!("{}_embeddings.json", agent_id))
+    }
+
+    fn get_agent_index_path(&self, agent_id: &str) -> PathBuf {
+        self.base_dir
+            .join("vector-index")
+            .join(format!("agent-{}.idx", agent_id))
+    }
+
+    fn get_swarm_index_path(&self) -> PathBuf {
+        self.base_dir.join("vector-index").join("swarm.idx")
+    }
+
     fn get_state_history_path(&self, agent_id: &str) -> PathBuf {
         self.base_dir
             .join("loop-detector")
             .join(format!("{}_state.json", agent_id))
     }
 
+    fn get_action_history_path(&self, agent_id: &str) -> PathBuf {
+        self.base_dir
+            .join("loop-detector")
+            .join(format!("{}_actions.json", agent_id))
+    }
+
+    fn get_tool_calls_path(&self, agent_id: &str) -> PathBuf {
+        self.base_dir
+            .join("loop-detector")
+            .join(format!("{}_tool_calls.json", agent_id))
+    }
+
     fn load_hashes(&self, agent_id: &str) -> Result<HashMap<String, usize>> {
         let path = self.get_prompt_hashes_path(agent_id);
         if path.exists() {
@@ -137,6 +237,46 @@ impl LoopDetector {
         Ok(())
     }
 
+    fn load_action_history(&self, agent_id: &str) -> Result<Vec<String>> {
+        let path = self.get_action_history_path(agent_id);
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    fn save_action_history(&self, agent_id: &str, history: &Vec<String>) -> Result<()> {
+        let path = self.get_action_history_path(agent_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(history)?;
+        fs::write(&path, content)?;
+        Ok(())
+    }
+
+    fn load_tool_calls(&self, agent_id: &str) -> Result<Vec<ToolCallRecord>> {
+        let path = self.get_tool_calls_path(agent_id);
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    fn save_tool_calls(&self, agent_id: &str, calls: &Vec<ToolCallRecord>) -> Result<()> {
+        let path = self.get_tool_calls_path(agent_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(calls)?;
+        fs::write(&path, content)?;
+        Ok(())
+    }
+
     pub fn check_exact_loop(
         &mut self,
         agent_id: &str,
@@ -156,31 +296,123 @@ impl LoopDetector {
                 loop_count: count + 1,
                 prompt_hash,
                 timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                cycle: None,
             }))
         } else {
             Ok(None)
         }
     }
 
-    /// Semantic similarity using embedding cosine similarity
-    /// Falls back to Jaccard similarity if embeddings are not available
-    fn semantic_similarity(&self, prompt1: &str, prompt2: &str) -> f64 {
-        if !self.use_semantic {
-            // Fallback to Jaccard similarity
-            self.jaccard_similarity(prompt1, prompt2)
+    /// Similarity of `prompt` to each of `recent`, embedding them in one batch,
+    /// and the prompt's embedding when there is one.
+    ///
+    /// With the disk cache on, embeddings of the agent's `history` are kept
+    /// next to it, so each prompt is embedded once across hook calls.
+    fn similarities(
+        &self,
+        agent_id: &str,
+        prompt: &str,
+        recent: &[&str],
+        history: &[String],
+    ) -> Result<(Vec<f64>, Option<Vec<f32>>)> {
+        let jaccard = || {
+            recent
+                .iter()
+                .map(|other| self.jaccard_similarity(prompt, other))
+                .collect()
+        };
+        let Some(embedder) = &self.embedder else {
+            return Ok((jaccard(), None));
+        };
+
+        let mut texts = vec![prompt];
+        texts.extend_from_slice(recent);
+        let embedded = if self.disk_cache {
+            let mut file = EmbeddingFile::load(self.get_embeddings_path(agent_id));
+            let embedded = file.embed(embedder.as_ref(), &texts);
+            if embedded.is_ok() {
+                let mut keep: Vec<&str> = history.iter().map(String::as_str).collect();
+                keep.push(prompt);
+                file.retain(embedder.as_ref(), &keep);
+                file.save()?;
+            }
+            embedded
         } else {
-            // Use semantic embeddings
-            match self.semantic_engine.embed(prompt1) {
-                Ok(vec1) => match self.semantic_engine.embed(prompt2) {
-                    Ok(vec2) => {
-                        let similarity = self.semantic_engine.cosine_similarity(&vec1, &vec2);
-                        similarity as f64
-                    }
-                    Err(_) => self.jaccard_similarity(prompt1, prompt2),
-                },
-                Err(_) => self.jaccard_similarity(prompt1, prompt2),
+            embedder.embed_batch(&texts)
+        };
+
+        match embedded {
+            Ok(mut vectors) if vectors.len() == texts.len() => {
+                let prompt_vector = vectors.remove(0);
+                let similarities = vectors
+                    .iter()
+                    .map(|other| cosine_similarity(&prompt_vector, other) as f64)
+                    .collect();
+                Ok((similarities, Some(prompt_vector)))
             }
+            _ => Ok((jaccard(), None)),
+        }
+    }
+
+    /// Adds `prompt` to the agent's and the swarm's indexes and returns how
+    /// many of the agent's indexed prompts, itself included, are similar to it.
+    fn index_prompt(&self, agent_id: &str, prompt: &str, vector: Vec<f32>) -> Result<usize> {
+        let Some(embedder) = self.embedder.as_ref().filter(|_| self.vector_index.enabled) else {
+            return Ok(0);
+        };
+        let id = self.hash_prompt(prompt);
+        let model = embedder.name();
+
+        let mut swarm = VectorIndex::load(
+            self.get_swarm_index_path(),
+            self.vector_index.clone(),
+            model,
+        );
+        swarm.insert(id.clone(), agent_id, vector.clone());
+        swarm.save()?;
+
+        let mut agent = VectorIndex::load(
+            self.get_agent_index_path(agent_id),
+            self.vector_index.clone(),
+            model,
+        );
+        agent.insert(id, agent_id, vector.clone());
+        agent.save()?;
+
+        Ok(agent
+            .search(
+                &vector,
+                self.semantic_loop_threshold,
+                self.semantic_similarity_threshold as f32,
+            )
+            .len())
+    }
+
+    /// Prompts any agent sent that are at least `min_similarity` similar to
+    /// `prompt`, most similar first; labelled with the agent that sent them.
+    ///
+    /// Empty without an embedder or with the index turned off.
+    pub fn similar_prompts(
+        &self,
+        prompt: &str,
+        k: usize,
+        min_similarity: f32,
+    ) -> Result<Vec<Neighbor>> {
+        let Some(embedder) = &self.embedder else {
+            return Ok(Vec::new());
+        };
+        if !self.vector_index.enabled {
+            return Ok(Vec::new());
         }
+        let index = VectorIndex::load(
+            self.get_swarm_index_path(),
+            self.vector_index.clone(),
+            embedder.name(),
+        );
+        if index.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(index.search(&embedder.embed(prompt)?, k, min_similarity))
     }
 
     /// Fallback Jaccard similarity for when embeddings are not available
@@ -212,15 +444,22 @@ impl LoopDetector {
         prompt: &str,
     ) -> Result<Option<LoopDetection>> {
         let history = self.load_prompt_history(agent_id)?;
+        let recent: Vec<&str> = history
+            .iter()
+            .rev()
+            .take(self.semantic_loop_threshold)
+            .map(String::as_str)
+            .collect();
 
-        let mut similarity_count = 0;
         let threshold = self.semantic_similarity_threshold;
-
-        for hist_prompt in history.iter().rev().take(self.semantic_loop_threshold) {
-            let similarity = self.semantic_similarity(prompt, hist_prompt);
-            if similarity > threshold {
-                similarity_count += 1;
-            }
+        let (similarities, prompt_vector) =
+            self.similarities(agent_id, prompt, &recent, &history)?;
+        let mut similarity_count = similarities
+            .into_iter()
+            .filter(|&similarity| similarity > threshold)
+            .count();
+        if let Some(vector) = prompt_vector {
+            similarity_count = similarity_count.max(self.index_prompt(agent_id, prompt, vector)?);
         }
 
         if similarity_count >= self.semantic_loop_threshold {
@@ -230,54 +469,170 @@ impl LoopDetector {
                 loop_count: similarity_count,
                 prompt_hash: self.hash_prompt(prompt),
                 timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                cycle: None,
             }))
         } else {
             Ok(None)
         }
     }
 
+    /// Records `state` and reports a cycle of distinct states, such as
+    /// analyzing, writing, analyzing, writing, repeated
+    /// `loop_state_oscillation_threshold` times in a row.
     pub fn check_state_oscillation(
         &mut self,
         agent_id: &str,
         state: &str,
     ) -> Result<Option<LoopDetection>> {
-        let mut history = self.load_state_history(agent_id)?;
+        let history = self.record_state(agent_id, state)?;
+        Ok(self.state_oscillation(agent_id, &history))
+    }
+
+    /// Records `action` and reports a sequence of actions, such as read,
+    /// edit, failing test, repeated `loop_cycle_repeats` times in a row.
+    ///
+    /// Up to `loop_cycle_tolerance` of the steps may differ from the cycle,
+    /// so a single noisy step does not hide the loop.
+    pub fn check_action_cycle(
+        &mut self,
+        agent_id: &str,
+        action: &str,
+    ) -> Result<Option<LoopDetection>> {
+        let mut history = self.load_action_history(agent_id)?;
+        history.push(action.to_string());
+        if history.len() > ACTION_HISTORY_LEN {
+            history.remove(0);
+        }
+        self.save_action_history(agent_id, &history)?;
 
+        let cycle = detect_cycle(
+            &history,
+            self.cycle_max_period,
+            self.cycle_repeats,
+            self.cycle_tolerance,
+        );
+        Ok(cycle.map(|cycle| self.cycle_detection(LoopType::ActionCycle, agent_id, cycle)))
+    }
+
+    fn record_state(&self, agent_id: &str, state: &str) -> Result<Vec<String>> {
+        let mut history = self.load_state_history(agent_id)?;
         history.push(state.to_string());
-        if history.len() > 20 {
+        if history.len() > STATE_HISTORY_LEN {
             history.remove(0);
         }
         self.save_state_history(agent_id, &history)?;
+        Ok(history)
+    }
 
-        if history.len() >= self.state_oscillation_threshold * 2 {
-            let recent = &history[history.len() - self.state_oscillation_threshold * 2..];
+    fn state_oscillation(&self, agent_id: &str, history: &[String]) -> Option<LoopDetection> {
+        let cycle = detect_cycle(
+            history,
+            self.cycle_max_period,
+            self.state_oscillation_threshold.max(2),
+            0.0,
+        )?;
+        Some(self.cycle_detection(LoopType::StateOscillation, agent_id, cycle))
+    }
 
-            let odd_states: Vec<String> = recent.iter().step_by(2).cloned().collect();
-            let even_states: Vec<String> = recent.iter().skip(1).step_by(2).cloned().collect();
+    fn cycle_detection(
+        &self,
+        detection_type: LoopType,
+        agent_id: &str,
+        cycle: LoopCycle,
+    ) -> LoopDetection {
+        LoopDetection {
+            detection_type,
+            agent_id: agent_id.to_string(),
+            loop_count: cycle.repeats,
+            prompt_hash: self.hash_prompt(&cycle.body.join("\n")),
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            cycle: Some(cycle),
+        }
+    }
 
-            let odd_set: std::collections::HashSet<String> = odd_states.iter().cloned().collect();
-            let even_set: std::collections::HashSet<String> = even_states.iter().cloned().collect();
+    /// Records a tool call and reports it once the same call, by
+    /// [`normalize_tool_call`], has been made the tool's threshold number of
+    /// times within `tool_loops.window_secs`.
+    pub fn check_tool_call(
+        &mut self,
+        agent_id: &str,
+        tool_name: &str,
+        args: &Value,
+    ) -> Result<Option<LoopDetection>> {
+        self.check_tool_call_at(agent_id, tool_name, args, chrono::Utc::now().timestamp())
+    }
 
-            if odd_set.len() == 1 && even_set.len() == 1 {
-                let odd_state = odd_states.first().unwrap();
-                let even_state = even_states.first().unwrap();
+    fn check_tool_call_at(
+        &mut self,
+        agent_id: &str,
+        tool_name: &str,
+        args: &Value,
+        now: i64,
+    ) -> Result<Option<LoopDetection>> {
+        if !self.tool_loops.enabled {
+            return Ok(None);
+        }
+        let signature = self.hash_prompt(&normalize_tool_call(tool_name, args, &self.tool_loops));
 
-                if odd_state != even_state {
-                    return Ok(Some(LoopDetection {
-                        detection_type: LoopType::StateOscillation,
-                        agent_id: agent_id.to_string(),
-                        loop_count: self.state_oscillation_threshold,
-                        prompt_hash: String::new(),
-                        timestamp: chrono::Utc::now()
-                            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
-                    }));
-                }
-            }
+        let since = now.saturating_sub(self.tool_loops.window_secs as i64);
+        let mut calls = self.load_tool_calls(agent_id)?;
+        calls.retain(|call| call.at > since);
+        calls.push(ToolCallRecord {
+            signature: signature.clone(),
+            tool: tool_name.to_string(),
+            at: now,
+        });
+        if calls.len() > TOOL_CALL_HISTORY_LEN {
+            calls.drain(..calls.len() - TOOL_CALL_HISTORY_LEN);
+        }
+        self.save_tool_calls(agent_id, &calls)?;
+
+        let count = calls
+            .iter()
+            .filter(|call| call.signature == signature)
+            .count();
+        if count >= self.tool_loops.threshold_for(tool_name) {
+            Ok(Some(LoopDetection {
+                detection_type: LoopType::ToolCallLoop,
+                agent_id: agent_id.to_string(),
+                loop_count: count,
+                prompt_hash: signature,
+                timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                cycle: None,
+            }))
+        } else {
+            Ok(None)
         }
+    }
 
-        Ok(None)
+    /// Records a finished tool call and its state, then checks for the same
+    /// call repeating or a cycle of calls, reporting the first found.
+    ///
+    /// Outcomes alone are not checked for oscillation: editing and re-running
+    /// a failing test alternates `succeeded` and `failed` on every round of
+    /// ordinary debugging. Each step of a cycle pairs the call with its
+    /// outcome instead.
+    pub fn check_tool_loops(
+        &mut self,
+        agent_id: &str,
+        tool_name: &str,
+        args: &Value,
+        state: &str,
+    ) -> Result<Option<LoopDetection>> {
+        let repeated = self.check_tool_call(agent_id, tool_name, args)?;
+        let step = format!(
+            "{} ({})",
+            normalize_tool_call(tool_name, args, &self.tool_loops),
+            state
+        );
+        let action_cycle = self.check_action_cycle(agent_id, &step)?;
+
+        Ok(repeated.or(action_cycle))
     }
 
+    /// Records the prompt and state, then checks for exact and semantic
+    /// repeats of the prompt, a cycle of prompt and state steps, and a cycle
+    /// of states, reporting the first found.
     pub fn check_all_loops(
         &mut self,
         agent_id: &str,
@@ -291,12 +646,8 @@ impl LoopDetector {
         }
         self.save_prompt_history(agent_id, &history)?;
 
-        let mut state_history = self.load_state_history(agent_id)?;
-        state_history.push(state.to_string());
-        if state_history.len() > 20 {
-            state_history.remove(0);
-        }
-        self.save_state_history(agent_id, &state_history)?;
+        let state_history = self.record_state(agent_id, state)?;
+        let action_cycle = self.check_action_cycle(agent_id, &format!("{} ({})", prompt, state))?;
 
         if let Some(detection) = self.check_exact_loop(agent_id, prompt)? {
             return Ok(Some(detection));
@@ -306,11 +657,11 @@ impl LoopDetector {
             return Ok(Some(detection));
         }
 
-        if let Some(detection) = self.check_state_oscillation(agent_id, state)? {
-            return Ok(Some(detection));
+        if action_cycle.is_some() {
+            return Ok(action_cycle);
         }
 
-        Ok(None)
+        Ok(self.state_oscillation(agent_id, &state_history))
     }
 
     pub fn get_intervention_stats(&self) -> Result<InterventionStats> {
@@ -357,12 +708,137 @@ impl LoopDetector {
     }
 
     pub fn is_using_semantic(&self) -> bool {
-        self.use_semantic
+        self.embedder.is_some()
+    }
+
+    pub fn embedder(&self) -> Option<&Arc<dyn Embedder>> {
+        self.embedder.as_ref()
     }
+}
+
+/// Canonical form of a tool call for loop detection: the tool name and its
+/// arguments with object keys sorted, `config.volatile_fields` removed at any
+/// depth, the tool's own `config.tool_volatile_fields` removed from the top
+/// level and surrounding whitespace trimmed from strings.
+pub fn normalize_tool_call(tool_name: &str, args: &Value, config: &ToolLoopConfig) -> String {
+    let mut args = canonicalize(args, &config.volatile_fields);
+    if let (Value::Object(map), Some(fields)) =
+        (&mut args, config.tool_volatile_fields.get(tool_name))
+    {
+        map.retain(|key, _| !fields.iter().any(|field| field.eq_ignore_ascii_case(key)));
+    }
+    match args {
+        Value::Null => tool_name.to_string(),
+        args => format!("{} {}", tool_name, args),
+    }
+}
+
+fn canonicalize(value: &Value, volatile_fields: &[String]) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map
+                .iter()
+                .filter(|(key, _)| {
+                    !volatile_fields
+                        .iter()
+                        .any(|field| field.eq_ignore_ascii_case(key))
+                })
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonicalize(value, volatile_fields)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| canonicalize(item, volatile_fields))
+                .collect(),
+        ),
+        Value::String(text) => Value::String(text.trim().to_string()),
+        other => other.clone(),
+    }
+}
+
+/// Finds the shortest cycle of 2 to `max_period` steps that `stream` ends
+/// with, repeated at least `min_repeats` times back to back.
+///
+/// Each step of the cycle is the one seen most often at its position. Up to
+/// `tolerance` of the steps in the repetitions may differ from it; a cycle
+/// of a single distinct step is a plain repeat and is not reported.
+pub fn detect_cycle(
+    stream: &[String],
+    max_period: usize,
+    min_repeats: usize,
+    tolerance: f64,
+) -> Option<LoopCycle> {
+    let min_repeats = min_repeats.max(2);
+    for period in 2..=max_period {
+        let span = period * min_repeats;
+        if span > stream.len() {
+            break;
+        }
+        let window = &stream[stream.len() - span..];
+        let body: Vec<String> = (0..period)
+            .map(|phase| most_common(window.iter().skip(phase).step_by(period)))
+            .collect();
+        if body.iter().all(|step| step == &body[0]) {
+            continue;
+        }
 
-    pub fn get_semantic_engine(&self) -> &Arc<SemanticEngine> {
-        &self.semantic_engine
+        let mismatches = count_mismatches(window, &body);
+        if mismatches as f64 > tolerance * span as f64 {
+            continue;
+        }
+
+        // Count earlier repetitions that fit the same cycle.
+        let mut repeats = min_repeats;
+        let mut total_mismatches = mismatches;
+        let mut end = stream.len() - span;
+        while end >= period {
+            let block = count_mismatches(&stream[end - period..end], &body);
+            if block as f64 > tolerance * period as f64 {
+                break;
+            }
+            repeats += 1;
+            total_mismatches += block;
+            end -= period;
+        }
+        return Some(LoopCycle {
+            period,
+            body,
+            repeats,
+            mismatches: total_mismatches,
+        });
+    }
+    None
+}
+
+/// The most frequent step; ties go to the latest.
+fn most_common<'a>(steps: impl Iterator<Item = &'a String>) -> String {
+    let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (i, step) in steps.enumerate() {
+        let entry = counts.entry(step.as_str()).or_insert((0, i));
+        entry.0 += 1;
+        entry.1 = i;
     }
+    counts
+        .into_iter()
+        .max_by_key(|&(_, count_and_last)| count_and_last)
+        .map(|(step, _)| step.to_string())
+        .unwrap_or_default()
+}
+
+/// Steps of `window`, a whole number of repetitions, that differ from `body`.
+fn count_mismatches(window: &[String], body: &[String]) -> usize {
+    window
+        .iter()
+        .zip(body.iter().cycle())
+        .filter(|(step, expected)| step != expected)
+        .count()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -385,8 +861,266 @@ mod tests {
         // Should use fallback when model is not loaded
         assert!(!detector.is_using_semantic());
 
-        let similarity = detector.semantic_similarity("Analyze the code", "Code analysis");
-        assert!(similarity > 0.0);
+        let (similarity, vector) = detector
+            .similarities("agent1", "Analyze the code", &["Code analysis"], &[])
+            .unwrap();
+        assert!(similarity[0] > 0.0);
+        assert!(vector.is_none());
+    }
+
+    #[test]
+    fn test_semantic_similarity_with_embedder() {
+        let config = crate::types::SwarmConfig::default();
+        let stub = crate::embedder::StubEmbedder::new(4)
+            .with_embedding("Analyze the code", vec![1.0, 0.0, 0.0, 0.0])
+            .with_embedding("Study the source", vec![1.0, 0.0, 0.0, 0.0]);
+        let detector = LoopDetector::with_embedder(&config, Arc::new(stub)).with_disk_cache(false);
+
+        assert!(detector.is_using_semantic());
+        let (similarity, vector) = detector
+            .similarities("agent1", "Analyze the code", &["Study the source"], &[])
+            .unwrap();
+        assert!((similarity[0] - 1.0).abs() < 1e-6);
+        assert_eq!(vector.unwrap(), vec![1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_history_embeddings_stored_next_to_history() {
+        let base_dir = std::env::temp_dir().join(format!(
+            "swarm_tools_loop_embeddings_{}",
+            std::process::id()
+        ));
+        let config = crate::types::SwarmConfig::default();
+        let stub = Arc::new(crate::embedder::StubEmbedder::new(16));
+        let mut detector = LoopDetector::with_embedder(&config, stub).with_base_dir(&base_dir);
+
+        for prompt in ["first prompt", "second prompt", "first prompt"] {
+            detector.check_all_loops("agent1", prompt, "state").unwrap();
+        }
+
+        let path = base_dir.join("loop-detector/agent1_embeddings.json");
+        assert_eq!(EmbeddingFile::load(&path).len(), 2);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_vector_index_finds_long_cycles() {
+        let base_dir =
+            std::env::temp_dir().join(format!("swarm_tools_loop_index_{}", std::process::id()));
+        let config = crate::types::SwarmConfig::default();
+        // Six tasks retried in turn, each worded differently every round: no
+        // two similar prompts ever fall in the same window of five.
+        let mut stub = crate::embedder::StubEmbedder::new(8);
+        for round in 0..5 {
+            for task in 0..6 {
+                let mut embedding = vec![0.0; 8];
+                embedding[task] = 1.0;
+                stub = stub.with_embedding(&format!("task {} attempt {}", task, round), embedding);
+            }
+        }
+        let stub = Arc::new(stub);
+
+        let run = |index: VectorIndexConfig, dir: &str| {
+            let mut detector = LoopDetector::with_embedder(&config, stub.clone())
+                .with_base_dir(base_dir.join(dir))
+                .with_vector_index(index);
+            let mut detections = Vec::new();
+            for round in 0..5 {
+                for task in 0..6 {
+                    let prompt = format!("task {} attempt {}", task, round);
+                    if let Some(detection) = detector
+                        .check_all_loops("agent1", &prompt, &prompt)
+                        .unwrap()
+                    {
+                        detections.push(detection);
+                    }
+                }
+            }
+            (detector, detections)
+        };
+
+        let disabled = VectorIndexConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(run(disabled, "window").1.is_empty());
+
+        let (detector, detections) = run(VectorIndexConfig::default(), "index");
+        assert_eq!(detections.len(), 6);
+        assert!(detections
+            .iter()
+            .all(|d| d.detection_type == LoopType::SemanticLoop && d.loop_count == 5));
+
+        let similar = detector
+            .similar_prompts("task 2 attempt 0", 10, 0.9)
+            .unwrap();
+        assert_eq!(similar.len(), 5);
+        assert!(similar.iter().all(|n| n.label == "agent1"));
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    fn steps(text: &str) -> Vec<String> {
+        text.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_detect_cycle_finds_shortest_period() {
+        let cycle = detect_cycle(&steps("x read edit test read edit test"), 6, 2, 0.0).unwrap();
+        assert_eq!(cycle.period, 3);
+        assert_eq!(cycle.body, steps("read edit test"));
+        assert_eq!(cycle.repeats, 2);
+        assert_eq!(cycle.mismatches, 0);
+
+        let cycle = detect_cycle(&steps("a b a b a b a b"), 6, 2, 0.0).unwrap();
+        assert_eq!(cycle.period, 2);
+        assert_eq!(cycle.repeats, 4);
+
+        // Repeating one step is an exact loop, not a cycle.
+        assert!(detect_cycle(&steps("a a a a a a"), 6, 2, 0.0).is_none());
+        assert!(detect_cycle(&steps("a b c a b d"), 6, 2, 0.0).is_none());
+        assert!(detect_cycle(&steps("a b c a b c"), 2, 2, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_detect_cycle_tolerates_noisy_steps() {
+        let noisy = steps("read edit test lint read edit test lint read edit fix lint");
+        assert!(detect_cycle(&noisy, 6, 3, 0.0).is_none());
+
+        let cycle = detect_cycle(&noisy, 6, 3, 0.1).unwrap();
+        assert_eq!(cycle.period, 4);
+        assert_eq!(cycle.body, steps("read edit test lint"));
+        assert_eq!(cycle.mismatches, 1);
+    }
+
+    #[test]
+    fn test_check_all_loops_reports_action_cycle() {
+        let base_dir =
+            std::env::temp_dir().join(format!("swarm_tools_cycle_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut detector =
+            LoopDetector::new(&crate::types::SwarmConfig::default()).with_base_dir(&base_dir);
+
+        let calls = [
+            ("read src/lib.rs", "succeeded"),
+            ("edit src/lib.rs", "succeeded"),
+            ("cargo test", "failed"),
+        ];
+        let mut detections = Vec::new();
+        for (prompt, state) in calls.iter().chain(calls.iter()) {
+            detections.push(detector.check_all_loops("agent1", prompt, state).unwrap());
+        }
+
+        assert!(detections[..5].iter().all(Option::is_none));
+        let detection = detections[5].clone().unwrap();
+        assert_eq!(detection.detection_type, LoopType::ActionCycle);
+        assert_eq!(detection.loop_count, 2);
+        let cycle = detection.cycle.unwrap();
+        assert_eq!(cycle.period, 3);
+        assert_eq!(cycle.body[2], "cargo test (failed)");
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_normalize_tool_call() {
+        let config = ToolLoopConfig {
+            volatile_fields: vec!["timestamp".to_string()],
+            ..Default::default()
+        };
+        let a = normalize_tool_call(
+            "Grep",
+            &serde_json::json!({"pattern": "fn main ", "path": "src", "meta": {"timestamp": 1, "n": 2}}),
+            &config,
+        );
+        let b = normalize_tool_call(
+            "Grep",
+            &serde_json::json!({"meta": {"n": 2, "Timestamp": 9}, "path": "src ", "pattern": "fn main"}),
+            &config,
+        );
+        assert_eq!(a, b);
+        assert_eq!(
+            a,
+            r#"Grep {"meta":{"n":2},"path":"src","pattern":"fn main"}"#
+        );
+        assert_eq!(normalize_tool_call("Stop", &Value::Null, &config), "Stop");
+    }
+
+    #[test]
+    fn test_description_is_only_volatile_for_bash() {
+        let config = ToolLoopConfig::default();
+        let bash = |description: &str| {
+            normalize_tool_call(
+                "Bash",
+                &serde_json::json!({"command": "cargo test", "description": description}),
+                &config,
+            )
+        };
+        assert_eq!(bash("Run the tests"), bash("Retry the tests"));
+
+        let task = |description: &str| {
+            normalize_tool_call(
+                "Task",
+                &serde_json::json!({"description": description, "prompt": "go"}),
+                &config,
+            )
+        };
+        assert_ne!(task("Audit auth"), task("Audit billing"));
+        let todo = |description: &str| {
+            normalize_tool_call(
+                "TodoWrite",
+                &serde_json::json!({"todos": [{"content": "x", "description": description}]}),
+                &config,
+            )
+        };
+        assert_ne!(todo("first"), todo("second"));
+    }
+
+    #[test]
+    fn test_tool_call_loop_uses_per_tool_threshold_and_window() {
+        let base_dir =
+            std::env::temp_dir().join(format!("swarm_tools_tool_calls_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut config = ToolLoopConfig {
+            window_secs: 60,
+            ..ToolLoopConfig::default()
+        };
+        config.tool_thresholds.insert("Read".to_string(), 2);
+        let mut detector = LoopDetector::new(&crate::types::SwarmConfig::default())
+            .with_base_dir(&base_dir)
+            .with_tool_loops(config);
+        let read = serde_json::json!({"file_path": "src/lib.rs"});
+        let test = serde_json::json!({"command": "cargo test"});
+
+        // Reads a minute apart never share the window.
+        assert!(detector
+            .check_tool_call_at("agent1", "Read", &read, 0)
+            .unwrap()
+            .is_none());
+        assert!(detector
+            .check_tool_call_at("agent1", "Read", &read, 61)
+            .unwrap()
+            .is_none());
+        let detection = detector
+            .check_tool_call_at("agent1", "Read", &read, 90)
+            .unwrap()
+            .unwrap();
+        assert_eq!(detection.detection_type, LoopType::ToolCallLoop);
+        assert_eq!(detection.loop_count, 2);
+
+        for at in 100..102 {
+            assert!(detector
+                .check_tool_call_at("agent1", "Bash", &test, at)
+                .unwrap()
+                .is_none());
+        }
+        assert!(detector
+            .check_tool_call_at("agent1", "Bash", &test, 102)
+            .unwrap()
+            .is_some());
+        assert!(detector
+            .check_tool_call_at("agent2", "Bash", &test, 102)
+            .unwrap()
+            .is_none());
+        let _ = fs::remove_dir_all(&base_dir);
     }
 
     #[test]
@@ -0,0 +1,290 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use swarm_tools::cli::{self, CliError, Workspace, EXIT_FAILURE, EXIT_SUCCESS};
+use swarm_tools::doctor;
+use swarm_tools::role_feedback::write_labelled;
+use swarm_tools::types::AgentRole;
+
+/// Loop detection, tool routing and trajectory tools for Claude Code agents.
+#[derive(Debug, Parser)]
+#[command(name = "swarm-tools", version)]
+struct Args {
+    /// Config profile to apply on top of the user and project config.
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Project directory whose config and state are used (hooks use the
+    /// event's `cwd` instead).
+    #[arg(long, global = true, default_value = ".")]
+    project_dir: PathBuf,
+
+    /// Print machine-readable JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Handle a Claude Code hook event read as JSON from stdin.
+    Hook {
+        event: HookEvent,
+        /// Agent role whose tool filter applies; defaults to
+        /// SWARM_TOOLS_AGENT_ROLE or the role recorded for the session.
+        #[arg(long, value_parser = AgentRole::from_name)]
+        role: Option<AgentRole>,
+    },
+    /// Summarize session activity and tool-routing savings.
+    Stats,
+    /// Suggest a role, team and model for a task.
+    Route { task: String },
+    /// Codify a task into prioritized plan steps.
+    Plan {
+        task: String,
+        /// Role to plan for; defaults to the role the task routes to.
+        #[arg(long, value_parser = AgentRole::from_name)]
+        role: Option<AgentRole>,
+    },
+    /// Compress a trajectory file.
+    Compress {
+        path: PathBuf,
+        /// Write the compressed trajectory here instead of stdout.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Diagnose compiled features, model files, config, state and hooks.
+    Doctor {
+        /// hooks.json to check; defaults to $CLAUDE_PLUGIN_ROOT/hooks/hooks.json.
+        #[arg(long)]
+        hooks_file: Option<PathBuf>,
+    },
+    /// Provision or check the embedding model files.
+    Models {
+        #[command(subcommand)]
+        command: ModelsCommand,
+    },
+    /// Record, export and evaluate role-routing corrections.
+    Roles {
+        #[command(subcommand)]
+        command: RolesCommand,
+    },
+    /// Delete hook state: every session, or only one with --session.
+    Reset {
+        #[arg(long)]
+        session: Option<String>,
+        /// Delete without asking for confirmation.
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ModelsCommand {
+    /// Download missing or mismatched model files, verifying each before it
+    /// is moved into place.
+    Fetch {
+        /// Download every file again.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Hash the model files and compare them with their expected digests.
+    Verify,
+}
+
+#[derive(Debug, Subcommand)]
+enum RolesCommand {
+    /// Record that a task should be routed to ROLE.
+    Label {
+        #[arg(value_parser = AgentRole::from_name)]
+        role: AgentRole,
+        task: String,
+    },
+    /// Print the corrections routing learns from, one JSON object per line.
+    Export {
+        /// Write them here instead of stdout.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Report routing accuracy and a confusion matrix on a labelled JSONL
+    /// file in the format `roles export` writes.
+    Eval {
+        path: PathBuf,
+        /// Route with the role descriptions alone, ignoring recorded
+        /// corrections.
+        #[arg(long)]
+        no_feedback: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum HookEvent {
+    SessionStart,
+    PreToolUse,
+    PostToolUse,
+    UserPromptSubmit,
+    Notification,
+    PreCompact,
+    SubagentStop,
+    Stop,
+    SessionEnd,
+}
+
+impl HookEvent {
+    /// The `hook_event_name` Claude Code sends for this event.
+    fn event_name(self) -> &'static str {
+        match self {
+            HookEvent::SessionStart => "SessionStart",
+            HookEvent::PreToolUse => "PreToolUse",
+            HookEvent::PostToolUse => "PostToolUse",
+            HookEvent::UserPromptSubmit => "UserPromptSubmit",
+            HookEvent::Notification => "Notification",
+            HookEvent::PreCompact => "PreCompact",
+            HookEvent::SubagentStop => "SubagentStop",
+            HookEvent::Stop => "Stop",
+            HookEvent::SessionEnd => "SessionEnd",
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    // clap exits with status 2 on bad arguments, which Claude Code treats as
+    // a blocking hook error; report them as ordinary failures instead.
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            let _ = e.print();
+            return ExitCode::from(if e.use_stderr() {
+                EXIT_FAILURE as u8
+            } else {
+                EXIT_SUCCESS as u8
+            });
+        }
+    };
+    match run(args) {
+        Ok(code) => ExitCode::from(code as u8),
+        Err(e) => {
+            eprintln!("swarm-tools: {}", e);
+            ExitCode::from(EXIT_FAILURE as u8)
+        }
+    }
+}
+
+fn run(args: Args) -> Result<i32, CliError> {
+    let workspace = || Workspace::load(&args.project_dir, args.profile.clone());
+
+    match args.command {
+        Command::Hook { event, role } => {
+            cli::run_hook(
+                event.event_name(),
+                args.profile.clone(),
+                role,
+                io::stdin().lock(),
+                io::stdout().lock(),
+            )?;
+        }
+        Command::Stats => {
+            let report = cli::stats(&workspace()?);
+            print(args.json, &report, || report.to_string())?;
+        }
+        Command::Route { ref task } => {
+            let routing = cli::route(&workspace()?, task);
+            print(args.json, &routing, || cli::format_route(&routing))?;
+        }
+        Command::Plan { ref task, ref role } => {
+            let plan = cli::plan(&workspace()?, task, role.clone())?;
+            print(args.json, &plan, || cli::format_plan(&plan))?;
+        }
+        Command::Compress {
+            ref path,
+            ref output,
+        } => {
+            let compressed = cli::compress(&workspace()?, path)?;
+            let json = serde_json::to_string_pretty(&compressed)
+                .map_err(|e| CliError::Invalid(e.to_string()))?;
+            match output {
+                Some(output) => std::fs::write(output, json)?,
+                None => println!("{}", json),
+            }
+        }
+        Command::Doctor { ref hooks_file } => {
+            let report = doctor::run(
+                &args.project_dir,
+                args.profile.clone(),
+                hooks_file.as_deref(),
+            );
+            print(args.json, &report, || report.to_string())?;
+            if !report.passed() {
+                return Ok(EXIT_FAILURE);
+            }
+        }
+        Command::Models { ref command } => {
+            let (fetch, force) = match *command {
+                ModelsCommand::Fetch { force } => (true, force),
+                ModelsCommand::Verify => (false, false),
+            };
+            let report = cli::models(&workspace()?, fetch, force)?;
+            print(args.json, &report, || report.to_string())?;
+            if !report.is_complete() {
+                return Ok(EXIT_FAILURE);
+            }
+        }
+        Command::Roles { ref command } => match command {
+            RolesCommand::Label { role, task } => {
+                let path = cli::label_role(&workspace()?, task, role.clone())?;
+                println!("Recorded {} in {}", role.as_str(), path.display());
+            }
+            RolesCommand::Export { output } => {
+                let examples = cli::export_roles(&workspace()?);
+                match output {
+                    Some(output) => write_labelled(&examples, fs::File::create(output)?)?,
+                    None => write_labelled(&examples, io::stdout().lock())?,
+                }
+            }
+            RolesCommand::Eval { path, no_feedback } => {
+                let evaluation = cli::eval_roles(&workspace()?, path, !no_feedback)?;
+                print(args.json, &evaluation, || evaluation.to_string())?;
+            }
+        },
+        Command::Reset { ref session, yes } => {
+            let workspace = workspace()?;
+            let targets = cli::reset_targets(&workspace, session.as_deref());
+            if targets.is_empty() {
+                println!("Nothing to reset.");
+                return Ok(EXIT_SUCCESS);
+            }
+            for target in &targets {
+                println!("{}", target.display());
+            }
+            if !yes && !confirm("Delete the paths above?")? {
+                println!("Nothing deleted; pass --yes to skip this prompt.");
+                return Ok(EXIT_SUCCESS);
+            }
+            cli::reset(&targets)?;
+            println!("Removed {} path(s).", targets.len());
+        }
+    }
+    Ok(EXIT_SUCCESS)
+}
+
+fn print<T: Serialize>(json: bool, value: &T, text: impl FnOnce() -> String) -> io::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else {
+        println!("{}", text());
+    }
+    Ok(())
+}
+
+fn confirm(question: &str) -> io::Result<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
@@ -1,7 +1,7 @@
-use crate::feature_config::McpRoutingConfig;
+use crate::feature_config::{CustomRoleConfig, McpRoutingConfig};
 use crate::types::AgentRole;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum McpRoutingDecision {
@@ -40,6 +40,18 @@ impl McpRouter {
         }
     }
 
+    /// Restricts each custom role with a tool list to those tools, on top of
+    /// any `role_tool_filters` entry for it.
+    pub fn with_custom_roles(mut self, roles: &BTreeMap<String, CustomRoleConfig>) -> Self {
+        for (name, custom) in roles.iter().filter(|(_, custom)| !custom.tools.is_empty()) {
+            self.role_tool_filters
+                .entry(AgentRole::Custom(name.clone()))
+                .or_default()
+                .extend(custom.tools.iter().cloned());
+        }
+        self
+    }
+
     pub fn route_tool_call(
         &self,
         role: AgentRole,
@@ -58,8 +70,8 @@ impl McpRouter {
         let allowed_tools = self.role_tool_filters.get(&role);
 
         if let Some(tools) = allowed_tools {
-            if tools.iter().any(|t| tool_name.contains(t)) {
-                let modified = self.modify_args_if_needed(tool_name, args, role);
+            if tool_listed(tools, tool_name) {
+                let modified = self.modify_args_if_needed(tool_name, args, &role);
                 return McpRoutingResult {
                     decision: modified,
                     tool_name: tool_name.to_string(),
@@ -69,8 +81,8 @@ impl McpRouter {
             }
         }
 
-        if self.default_tools.iter().any(|t| tool_name.contains(t)) {
-            let modified = self.modify_args_if_needed(tool_name, args, role);
+        if tool_listed(&self.default_tools, tool_name) {
+            let modified = self.modify_args_if_needed(tool_name, args, &role);
             return McpRoutingResult {
                 decision: modified,
                 tool_name: tool_name.to_string(),
@@ -97,14 +109,15 @@ impl McpRouter {
         &self,
         tool_name: &str,
         args: &serde_json::Value,
-        _role: AgentRole,
+        _role: &AgentRole,
     ) -> McpRoutingDecision {
         let args_str = args.to_string();
         let original_len = args_str.len();
 
         let mut modified_args = args.clone();
+        let name = mcp_tool_name(tool_name).to_ascii_lowercase();
 
-        if tool_name.contains("read_file") || tool_name.contains("browse_file") {
+        if matches!(name.as_str(), "read" | "read_file" | "browse_file") {
             if let Some(obj) = modified_args.as_object_mut() {
                 if let Some(context) = obj.get("context") {
                     if context.as_str().map(|s| s.len()).unwrap_or(0) > 5000 {
@@ -118,12 +131,13 @@ impl McpRouter {
             }
         }
 
-        if tool_name.contains("search") || tool_name.contains("grep") {
+        if name.contains("search") || name == "grep" {
             if let Some(obj) = modified_args.as_object_mut() {
                 if let Some(query) = obj.get("query") {
                     if let Some(query_str) = query.as_str() {
-                        if query_str.len() > 500 {
-                            let trimmed = &query_str[..500];
+                        // Cut after 500 characters, never inside one.
+                        if let Some((cut, _)) = query_str.char_indices().nth(500) {
+                            let trimmed = &query_str[..cut];
                             obj["query"] = serde_json::Value::String(trimmed.to_string());
                             return McpRoutingDecision::ModifyArgs {
                                 new_args: modified_args,
@@ -153,6 +167,22 @@ impl McpRouter {
     }
 }
 
+/// Whether `tool_name` is listed in `tools`. Names compare case-insensitively,
+/// and an MCP tool (`mcp__<server>__<tool>`) also matches its bare `<tool>`.
+fn tool_listed(tools: &[String], tool_name: &str) -> bool {
+    let bare = mcp_tool_name(tool_name);
+    tools
+        .iter()
+        .any(|t| t.eq_ignore_ascii_case(tool_name) || t.eq_ignore_ascii_case(bare))
+}
+
+fn mcp_tool_name(tool_name: &str) -> &str {
+    tool_name
+        .strip_prefix("mcp__")
+        .and_then(|rest| rest.split_once("__"))
+        .map_or(tool_name, |(_, tool)| tool)
+}
+
 fn convert_role_filters(
     filters: Option<HashMap<String, Vec<String>>>,
 ) -> HashMap<AgentRole, Vec<String>> {
@@ -160,7 +190,7 @@ fn convert_role_filters(
 
     if let Some(filters) = filters {
         for (role_str, tools) in filters {
-            if let Ok(role) = role_str.parse::<AgentRole>() {
+            if let Ok(role) = AgentRole::from_name(&role_str) {
                 result.insert(role, tools);
             }
         }
@@ -184,7 +214,7 @@ mod tests {
     fn test_mcp_router_allow_relevant_tool() {
         let router = McpRouter::new();
         let args = serde_json::json!({"path": "/test/file.rs"});
-        let result = router.route_tool_call(AgentRole::Extractor, "read_file", &args);
+        let result = router.route_tool_call(AgentRole::Extractor, "Read", &args);
         assert_eq!(result.decision, McpRoutingDecision::Allow);
     }
 
@@ -192,7 +222,7 @@ mod tests {
     fn test_mcp_router_deny_irrelevant_tool() {
         let router = McpRouter::new();
         let args = serde_json::json!({"query": "test"});
-        let result = router.route_tool_call(AgentRole::Extractor, "web_search", &args);
+        let result = router.route_tool_call(AgentRole::Extractor, "WebSearch", &args);
         match &result.decision {
             McpRoutingDecision::Deny { reason } => {
                 assert!(reason.contains("not in allowed list"));
@@ -209,7 +239,7 @@ mod tests {
             "context": large_context
         });
         let router = McpRouter::new();
-        let result = router.route_tool_call(AgentRole::Extractor, "read_file", &args);
+        let result = router.route_tool_call(AgentRole::Extractor, "Read", &args);
 
         match &result.decision {
             McpRoutingDecision::ModifyArgs { new_args } => {
@@ -234,15 +264,15 @@ mod tests {
     fn test_mcp_router_analyzer_tools() {
         let router = McpRouter::new();
         let args = serde_json::json!({"pattern": "fn test", "path": "/src"});
-        let result = router.route_tool_call(AgentRole::Analyzer, "search_code", &args);
+        let result = router.route_tool_call(AgentRole::Analyzer, "Grep", &args);
         assert_eq!(result.decision, McpRoutingDecision::Allow);
     }
 
     #[test]
     fn test_mcp_router_default_tools() {
         let router = McpRouter::new();
-        let args = serde_json::json!({"message": "hello"});
-        let result = router.route_tool_call(AgentRole::General, "send_message", &args);
+        let args = serde_json::json!({"todos": []});
+        let result = router.route_tool_call(AgentRole::General, "TodoWrite", &args);
         assert_eq!(result.decision, McpRoutingDecision::Allow);
     }
 
@@ -250,8 +280,83 @@ mod tests {
     fn test_token_savings_estimate() {
         let router = McpRouter::new();
         let args = serde_json::json!({"message": "x".repeat(1000)});
-        let result = router.route_tool_call(AgentRole::General, "message", &args);
+        let result = router.route_tool_call(AgentRole::General, "TodoWrite", &args);
         assert!(result.token_savings_estimate.is_some());
         assert!((result.token_savings_estimate.unwrap() > 0));
     }
+
+    #[test]
+    fn test_mcp_router_matches_claude_code_tool_names() {
+        let router = McpRouter::new();
+        let args = serde_json::json!({});
+        let decide =
+            |role: AgentRole, tool: &str| router.route_tool_call(role, tool, &args).decision;
+
+        for tool in ["Read", "Grep", "Glob", "Bash"] {
+            assert_eq!(
+                decide(AgentRole::Extractor, tool),
+                McpRoutingDecision::Allow,
+                "{}",
+                tool
+            );
+        }
+        for tool in ["Read", "Edit", "Write", "Bash"] {
+            assert_eq!(
+                decide(AgentRole::Writer, tool),
+                McpRoutingDecision::Allow,
+                "{}",
+                tool
+            );
+        }
+        assert_eq!(decide(AgentRole::Tester, "bash"), McpRoutingDecision::Allow);
+        assert!(matches!(
+            decide(AgentRole::Extractor, "Edit"),
+            McpRoutingDecision::Deny { .. }
+        ));
+        // Exact names only: a listed name inside a longer one does not count.
+        assert!(matches!(
+            decide(AgentRole::Reviewer, "ReadWrite"),
+            McpRoutingDecision::Deny { .. }
+        ));
+    }
+
+    #[test]
+    fn test_mcp_router_matches_bare_mcp_tool_name() {
+        let config = McpRoutingConfig {
+            role_tool_filters: Some(HashMap::from([(
+                "extractor".to_string(),
+                vec!["git_diff".to_string()],
+            )])),
+            ..Default::default()
+        };
+        let router = McpRouter::with_config(config);
+        let args = serde_json::json!({});
+
+        let result = router.route_tool_call(AgentRole::Extractor, "mcp__git__git_diff", &args);
+        assert_eq!(result.decision, McpRoutingDecision::Allow);
+        let result = router.route_tool_call(AgentRole::Extractor, "mcp__git__git_diff_stat", &args);
+        assert!(matches!(result.decision, McpRoutingDecision::Deny { .. }));
+    }
+
+    #[test]
+    fn test_mcp_router_trims_non_ascii_query_on_char_boundary() {
+        let router = McpRouter::new();
+        // Byte 500 falls inside the 167th three-byte character.
+        let query = "検索".repeat(300);
+        let args = serde_json::json!({ "query": query });
+        let result = router.route_tool_call(AgentRole::Analyzer, "WebSearch", &args);
+
+        match &result.decision {
+            McpRoutingDecision::ModifyArgs { new_args } => {
+                let trimmed = new_args["query"].as_str().unwrap();
+                assert_eq!(trimmed.chars().count(), 500);
+                assert!(query.starts_with(trimmed));
+            }
+            _ => panic!("Expected ModifyArgs decision"),
+        }
+
+        let args = serde_json::json!({ "query": "検索".repeat(250) });
+        let result = router.route_tool_call(AgentRole::Analyzer, "WebSearch", &args);
+        assert_eq!(result.decision, McpRoutingDecision::Allow);
+    }
 }
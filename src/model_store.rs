@@ -0,0 +1,773 @@
+//! Model files for the semantic engine: where they live, what they should hash
+//! to, and fetching them ahead of time.
+//!
+//! [`crate::semantic_engine::SemanticEngine`] only loads files already on
+//! disk. `swarm-tools models fetch` downloads missing ones and checks each
+//! against its pinned SHA-256 before renaming it into place; a file nothing
+//! pins is never downloaded. `swarm-tools models verify` re-checks them later.
+//! Nothing else in the crate touches the network.
+//!
+//! Which model is used comes from a registry: [`builtin_models`] overlaid with
+//! `model_store.models`, selected by `model_store.model`.
+
+use crate::feature_config::{EmbeddingModelSpec, ModelStoreConfig, Pooling};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Environment variable naming the model directory; overrides the config.
+pub const MODEL_DIR_ENV_VAR: &str = "SWARM_TOOLS_MODEL_DIR";
+
+/// Environment variable that turns on offline mode when set to `1` or `true`.
+pub const OFFLINE_ENV_VAR: &str = "SWARM_TOOLS_OFFLINE";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Registry id of all-MiniLM-L6-v2, the model used unless configured
+/// otherwise.
+pub const DEFAULT_MODEL: &str = "all-minilm-l6-v2";
+
+/// A file the semantic engine loads from the model directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelFile {
+    pub name: String,
+    pub url: String,
+}
+
+/// A built-in model's files at one commit of its Hugging Face repository,
+/// with the SHA-256 of each file there.
+struct HubPin {
+    repo: &'static str,
+    /// Commit id the download URLs resolve at, so they keep serving the
+    /// bytes the digests were taken from.
+    revision: &'static str,
+    /// Path of the ONNX export fetched as `model.onnx`.
+    onnx: &'static str,
+    tokenizer_sha256: &'static str,
+    config_sha256: &'static str,
+    onnx_sha256: &'static str,
+}
+
+// The commit ids and digests below have not been recorded yet. An empty
+// digest pins nothing, so `models fetch` refuses that file unless
+// `model_store.sha256` pins it; fill each one in from the repository at the
+// commit named in `revision`.
+const MINILM: HubPin = HubPin {
+    repo: "sentence-transformers/all-MiniLM-L6-v2",
+    revision: "main",
+    onnx: "onnx/model.onnx",
+    tokenizer_sha256: "",
+    config_sha256: "",
+    onnx_sha256: "",
+};
+
+const MINILM_INT8: HubPin = HubPin {
+    onnx: "onnx/model_quint8_avx2.onnx",
+    onnx_sha256: "",
+    ..MINILM
+};
+
+const BGE_SMALL: HubPin = HubPin {
+    repo: "BAAI/bge-small-en-v1.5",
+    revision: "main",
+    onnx: "onnx/model.onnx",
+    tokenizer_sha256: "",
+    config_sha256: "",
+    onnx_sha256: "",
+};
+
+const E5_SMALL: HubPin = HubPin {
+    repo: "intfloat/e5-small-v2",
+    revision: "main",
+    onnx: "onnx/model.onnx",
+    tokenizer_sha256: "",
+    config_sha256: "",
+    onnx_sha256: "",
+};
+
+impl HubPin {
+    /// A registry entry fetching the pinned files, with default pooling and
+    /// no prefixes.
+    fn spec(&self) -> EmbeddingModelSpec {
+        let url = |path: &str| {
+            format!(
+                "https://huggingface.co/{}/resolve/{}/{}",
+                self.repo, self.revision, path
+            )
+        };
+        let files = [
+            (
+                "tokenizer.json",
+                url("tokenizer.json"),
+                self.tokenizer_sha256,
+            ),
+            ("config.json", url("config.json"), self.config_sha256),
+            ("model.onnx", url(self.onnx), self.onnx_sha256),
+        ];
+        EmbeddingModelSpec {
+            files: files
+                .iter()
+                .map(|(name, url, _)| (name.to_string(), url.clone()))
+                .collect(),
+            sha256: files
+                .iter()
+                .filter(|(_, _, digest)| !digest.is_empty())
+                .map(|(name, _, digest)| (name.to_string(), digest.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Models known without configuration, by registry id.
+///
+/// - `all-minilm-l6-v2`: the default, 384 dimensions.
+/// - `all-minilm-l6-v2-int8`: the same model quantized to 8 bits, for slow
+///   CPUs.
+/// - `bge-small-en-v1.5` and `e5-small-v2`: 384 dimensions, more accurate,
+///   trained with query and passage prefixes.
+pub fn builtin_models() -> BTreeMap<String, EmbeddingModelSpec> {
+    BTreeMap::from([
+        (DEFAULT_MODEL.to_string(), MINILM.spec()),
+        ("all-minilm-l6-v2-int8".to_string(), MINILM_INT8.spec()),
+        (
+            "bge-small-en-v1.5".to_string(),
+            EmbeddingModelSpec {
+                pooling: Pooling::Cls,
+                query_prefix: "Represent this sentence for searching relevant passages: "
+                    .to_string(),
+                ..BGE_SMALL.spec()
+            },
+        ),
+        (
+            "e5-small-v2".to_string(),
+            EmbeddingModelSpec {
+                query_prefix: "query: ".to_string(),
+                passage_prefix: "passage: ".to_string(),
+                ..E5_SMALL.spec()
+            },
+        ),
+    ])
+}
+
+/// The built-in models with `config.models` added or replacing them.
+pub fn registry(config: &ModelStoreConfig) -> BTreeMap<String, EmbeddingModelSpec> {
+    let mut models = builtin_models();
+    models.extend(config.models.clone());
+    models
+}
+
+/// Files `models fetch` provisions for `spec` on this platform.
+fn files_for(spec: &EmbeddingModelSpec) -> Vec<ModelFile> {
+    #[allow(unused_mut)]
+    let mut files: Vec<ModelFile> = spec
+        .files
+        .iter()
+        .map(|(name, url)| ModelFile {
+            name: name.clone(),
+            url: url.clone(),
+        })
+        .collect();
+    // ONNX Runtime is loaded dynamically on Windows.
+    #[cfg(windows)]
+    files.push(ModelFile {
+        name: "onnxruntime.dll".to_string(),
+        url: "https://cdn.pyke.io/onnxruntime-win-x64-1.20.0/onnxruntime.dll".to_string(),
+    });
+    files
+}
+
+#[derive(Debug, Error)]
+pub enum ModelError {
+    #[error("Model store I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("Offline mode is on; not downloading {0}")]
+    Offline(String),
+    #[error("Could not download {name}: {message}")]
+    Download { name: String, message: String },
+    #[error("{name} has SHA-256 {actual}, expected {expected}")]
+    ChecksumMismatch {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("{0} has no pinned SHA-256; set it in model_store.sha256 to download it")]
+    Unpinned(String),
+}
+
+/// Where the model directory came from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelDirSource {
+    /// [`MODEL_DIR_ENV_VAR`].
+    Env,
+    /// `model_store.model_dir`.
+    Config,
+    /// Provisioned by the build script into `OUT_DIR`.
+    Build,
+    /// The user data directory, where `models fetch` puts files by default.
+    DataDir,
+}
+
+impl fmt::Display for ModelDirSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            ModelDirSource::Env => MODEL_DIR_ENV_VAR,
+            ModelDirSource::Config => "model_store.model_dir",
+            ModelDirSource::Build => "build output",
+            ModelDirSource::DataDir => "user data directory",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileState {
+    /// Matches its expected digest.
+    Verified,
+    /// Present, but nothing says what it should hash to.
+    Unpinned,
+    Mismatch,
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileReport {
+    pub name: String,
+    pub path: PathBuf,
+    pub state: FileState,
+    pub sha256: Option<String>,
+    pub expected: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelStore {
+    dir: PathBuf,
+    source: ModelDirSource,
+    model: String,
+    spec: EmbeddingModelSpec,
+    offline: bool,
+    mirror_url: Option<String>,
+    pinned: BTreeMap<String, String>,
+}
+
+impl ModelStore {
+    /// Resolves the model directory from [`MODEL_DIR_ENV_VAR`], then
+    /// `config.model_dir`, then build-provisioned models, then the user data
+    /// directory. Models other than [`DEFAULT_MODEL`] live in a subdirectory
+    /// named after them.
+    ///
+    /// An id missing from the registry falls back to [`DEFAULT_MODEL`] with a
+    /// warning; config validation reports it too.
+    pub fn resolve(config: &ModelStoreConfig) -> Self {
+        Self::resolve_with(config, |key| std::env::var_os(key))
+    }
+
+    fn resolve_with(config: &ModelStoreConfig, env: impl Fn(&str) -> Option<OsString>) -> Self {
+        let mut models = registry(config);
+        let (model, spec) = match models.remove_entry(&config.model) {
+            Some(entry) => entry,
+            None => {
+                eprintln!(
+                    "Warning: unknown embedding model {:?}; using {}",
+                    config.model, DEFAULT_MODEL
+                );
+                models
+                    .remove_entry(DEFAULT_MODEL)
+                    .expect("the default model is built in")
+            }
+        };
+
+        // The build only provisions the default model.
+        let build_dir = Path::new(env!("OUT_DIR")).join("models");
+        let (root, source) = if let Some(dir) = env(MODEL_DIR_ENV_VAR).filter(|d| !d.is_empty()) {
+            (PathBuf::from(dir), ModelDirSource::Env)
+        } else if let Some(dir) = &config.model_dir {
+            (dir.clone(), ModelDirSource::Config)
+        } else if model == DEFAULT_MODEL
+            && files_for(&spec)
+                .iter()
+                .all(|file| build_dir.join(&file.name).is_file())
+        {
+            (build_dir, ModelDirSource::Build)
+        } else {
+            (data_dir(&env), ModelDirSource::DataDir)
+        };
+        let dir = if model == DEFAULT_MODEL {
+            root
+        } else {
+            root.join(&model)
+        };
+
+        let offline_env = env(OFFLINE_ENV_VAR)
+            .map(|v| matches!(v.to_str(), Some("1" | "true")))
+            .unwrap_or(false);
+        Self {
+            dir,
+            source,
+            model,
+            spec,
+            offline: config.offline || offline_env,
+            mirror_url: config.mirror_url.clone(),
+            pinned: config
+                .sha256
+                .iter()
+                .map(|(name, digest)| (name.clone(), digest.to_ascii_lowercase()))
+                .collect(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn source(&self) -> ModelDirSource {
+        self.source
+    }
+
+    /// Registry id of the model.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn spec(&self) -> &EmbeddingModelSpec {
+        &self.spec
+    }
+
+    /// Files `models fetch` provisions on this platform.
+    pub fn files(&self) -> Vec<ModelFile> {
+        files_for(&self.spec)
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// The digest `name` must have: pinned in config, else in the registry
+    /// entry. Nothing learned from a download is trusted later.
+    fn expected_sha256(&self, name: &str) -> Option<String> {
+        self.pinned
+            .get(name)
+            .or_else(|| self.spec.sha256.get(name))
+            .map(|digest| digest.to_ascii_lowercase())
+    }
+
+    /// Hashes every model file and compares it with its expected digest.
+    pub fn verify(&self) -> Result<Vec<FileReport>, ModelError> {
+        self.files()
+            .into_iter()
+            .map(|file| {
+                let path = self.dir.join(&file.name);
+                let expected = self.expected_sha256(&file.name);
+                let sha256 = if path.is_file() {
+                    Some(sha256_file(&path)?)
+                } else {
+                    None
+                };
+                let state = match (&sha256, &expected) {
+                    (None, _) => FileState::Missing,
+                    (Some(_), None) => FileState::Unpinned,
+                    (Some(actual), Some(expected)) if actual == expected => FileState::Verified,
+                    (Some(_), Some(_)) => FileState::Mismatch,
+                };
+                Ok(FileReport {
+                    name: file.name,
+                    path,
+                    state,
+                    sha256,
+                    expected,
+                })
+            })
+            .collect()
+    }
+
+    /// Downloads model files that are missing or do not match their expected
+    /// digest, or every file with `force`.
+    ///
+    /// Each download is written to a temporary file, checked and only then
+    /// renamed into place, so an interrupted fetch never leaves a partial
+    /// model behind. A file without an expected digest is never downloaded,
+    /// since whatever the server sent would have to be taken on trust.
+    pub fn fetch(&self, force: bool) -> Result<Vec<FileReport>, ModelError> {
+        let mut reports = Vec::new();
+
+        for report in self.verify()? {
+            let needed = force || matches!(report.state, FileState::Missing | FileState::Mismatch);
+            if !needed {
+                reports.push(report);
+                continue;
+            }
+            if self.offline {
+                return Err(ModelError::Offline(report.name));
+            }
+            let Some(expected) = report.expected.clone() else {
+                return Err(ModelError::Unpinned(report.name));
+            };
+
+            let file = self
+                .files()
+                .into_iter()
+                .find(|file| file.name == report.name)
+                .expect("reports come from files");
+            fs::create_dir_all(&self.dir)?;
+            let actual = self.download(&file, &report.path, &expected)?;
+            reports.push(FileReport {
+                state: FileState::Verified,
+                sha256: Some(actual),
+                ..report
+            });
+        }
+        Ok(reports)
+    }
+
+    fn url_for(&self, file: &ModelFile) -> String {
+        match &self.mirror_url {
+            Some(mirror) if self.model == DEFAULT_MODEL => {
+                format!("{}/{}", mirror.trim_end_matches('/'), file.name)
+            }
+            Some(mirror) => format!(
+                "{}/{}/{}",
+                mirror.trim_end_matches('/'),
+                self.model,
+                file.name
+            ),
+            None => file.url.clone(),
+        }
+    }
+
+    /// Streams `file` to `path` through a temporary file and returns its
+    /// digest, which must be `expected`.
+    fn download(
+        &self,
+        file: &ModelFile,
+        path: &Path,
+        expected: &str,
+    ) -> Result<String, ModelError> {
+        let download_error = |message: String| ModelError::Download {
+            name: file.name.clone(),
+            message,
+        };
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout_read(READ_TIMEOUT)
+            .build();
+        let response = agent
+            .get(&self.url_for(file))
+            .call()
+            .map_err(|e| download_error(e.to_string()))?;
+
+        let temp = temp_path(path);
+        let result = (|| {
+            let mut reader = response.into_reader();
+            let mut out = File::create(&temp)?;
+            let mut hasher = Sha256::new();
+            let mut buffer = [0u8; 64 * 1024];
+            loop {
+                match reader.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        hasher.update(&buffer[..n]);
+                        out.write_all(&buffer[..n])?;
+                    }
+                    Err(e) => return Err(download_error(e.to_string())),
+                }
+            }
+            out.sync_all()?;
+            let actual = hex::encode(hasher.finalize());
+            if actual != expected {
+                return Err(ModelError::ChecksumMismatch {
+                    name: file.name.clone(),
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+            fs::rename(&temp, path)?;
+            Ok(actual)
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        result
+    }
+}
+
+/// `$XDG_DATA_HOME/swarm-tools/models`, falling back to `~/.local/share` and
+/// then `%LOCALAPPDATA%`.
+fn data_dir(env: &impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    let base = env("XDG_DATA_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").map(|h| PathBuf::from(h).join(".local").join("share")))
+        .or_else(|| env("LOCALAPPDATA").map(PathBuf::from))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("swarm-tools").join("models")
+}
+
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Temporary sibling of `path`, so the final rename stays on one filesystem.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.part", name, std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unique_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "swarm_tools_{}_{}",
+            test_name,
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_micros()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn env_of(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<OsString> {
+        let vars: Vec<(String, String)> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| OsString::from(v))
+        }
+    }
+
+    #[test]
+    fn test_model_dir_resolution_order() {
+        let config = ModelStoreConfig {
+            model_dir: Some(PathBuf::from("/srv/models")),
+            ..Default::default()
+        };
+
+        let store = ModelStore::resolve_with(&config, env_of(&[(MODEL_DIR_ENV_VAR, "/env")]));
+        assert_eq!(store.dir(), Path::new("/env"));
+        assert_eq!(store.source(), ModelDirSource::Env);
+
+        let store = ModelStore::resolve_with(&config, env_of(&[]));
+        assert_eq!(store.dir(), Path::new("/srv/models"));
+        assert_eq!(store.source(), ModelDirSource::Config);
+
+        let store = ModelStore::resolve_with(
+            &ModelStoreConfig::default(),
+            env_of(&[("XDG_DATA_HOME", "/data"), (OFFLINE_ENV_VAR, "1")]),
+        );
+        if store.source() == ModelDirSource::DataDir {
+            assert_eq!(store.dir(), Path::new("/data/swarm-tools/models"));
+        }
+        assert!(store.is_offline());
+    }
+
+    #[test]
+    fn test_verify_prefers_pinned_digests() {
+        let dir = unique_dir("model_store_verify");
+        fs::write(dir.join("tokenizer.json"), "{}").unwrap();
+        let digest = sha256_file(&dir.join("tokenizer.json")).unwrap();
+        let config = ModelStoreConfig {
+            model_dir: Some(dir.clone()),
+            ..Default::default()
+        };
+        let mut spec = registry(&config).remove(DEFAULT_MODEL).unwrap();
+        spec.sha256 = BTreeMap::from([("tokenizer.json".to_string(), "0".repeat(64))]);
+        let config = ModelStoreConfig {
+            models: BTreeMap::from([(DEFAULT_MODEL.to_string(), spec)]),
+            ..config
+        };
+
+        let report = |reports: Vec<FileReport>, name: &str| {
+            reports.into_iter().find(|r| r.name == name).unwrap()
+        };
+        let reports = ModelStore::resolve_with(&config, env_of(&[]))
+            .verify()
+            .unwrap();
+        assert_eq!(
+            report(reports.clone(), "tokenizer.json").state,
+            FileState::Mismatch
+        );
+        assert_eq!(report(reports, "model.onnx").state, FileState::Missing);
+
+        let pinned = ModelStoreConfig {
+            sha256: BTreeMap::from([("tokenizer.json".to_string(), digest.to_uppercase())]),
+            ..config
+        };
+        let tokenizer = report(
+            ModelStore::resolve_with(&pinned, env_of(&[]))
+                .verify()
+                .unwrap(),
+            "tokenizer.json",
+        );
+        assert_eq!(tokenizer.state, FileState::Verified);
+        assert_eq!(tokenizer.sha256.as_deref(), Some(digest.as_str()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_registry_selects_model_and_directory() {
+        let mut config = ModelStoreConfig {
+            model_dir: Some(PathBuf::from("/srv/models")),
+            mirror_url: Some("https://mirror.example/models/".to_string()),
+            model: "e5-small-v2".to_string(),
+            ..Default::default()
+        };
+        let store = ModelStore::resolve_with(&config, env_of(&[]));
+        assert_eq!(store.model(), "e5-small-v2");
+        assert_eq!(store.dir(), Path::new("/srv/models/e5-small-v2"));
+        assert_eq!(store.spec().query_prefix, "query: ");
+        let onnx = store
+            .files()
+            .into_iter()
+            .find(|file| file.name == "model.onnx")
+            .unwrap();
+        assert_eq!(
+            store.url_for(&onnx),
+            "https://mirror.example/models/e5-small-v2/model.onnx"
+        );
+
+        config.models.insert(
+            "custom".to_string(),
+            EmbeddingModelSpec {
+                dimension: 768,
+                pooling: Pooling::Cls,
+                ..Default::default()
+            },
+        );
+        config.model = "custom".to_string();
+        let store = ModelStore::resolve_with(&config, env_of(&[]));
+        assert_eq!(store.spec().dimension, 768);
+        assert_eq!(store.dir(), Path::new("/srv/models/custom"));
+
+        config.model = "no-such-model".to_string();
+        let store = ModelStore::resolve_with(&config, env_of(&[]));
+        assert_eq!(store.model(), DEFAULT_MODEL);
+        assert_eq!(store.dir(), Path::new("/srv/models"));
+    }
+
+    #[test]
+    fn test_offline_fetch_never_downloads() {
+        let dir = unique_dir("model_store_offline");
+        let config = ModelStoreConfig {
+            model_dir: Some(dir.clone()),
+            offline: true,
+            mirror_url: Some("http://127.0.0.1:9".to_string()),
+            ..Default::default()
+        };
+
+        let store = ModelStore::resolve_with(&config, env_of(&[]));
+        assert!(matches!(store.fetch(false), Err(ModelError::Offline(_))));
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Serves `body` to the next `requests` HTTP requests.
+    fn serve(body: &'static [u8], requests: usize) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let _ = stream.read(&mut [0u8; 4096]);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(body);
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_fetch_checks_digest_before_rename() {
+        let dir = unique_dir("model_store_fetch");
+        let body: &[u8] = b"model bytes";
+        let digest = hex::encode(Sha256::digest(body));
+        let files = ModelStore::resolve_with(&ModelStoreConfig::default(), env_of(&[])).files();
+        let names: Vec<&str> = files.iter().map(|file| file.name.as_str()).collect();
+        let pin = |digest: &str| {
+            names
+                .iter()
+                .map(|name| (name.to_string(), digest.to_string()))
+                .collect()
+        };
+
+        let wrong = ModelStoreConfig {
+            model_dir: Some(dir.clone()),
+            mirror_url: Some(serve(body, 1)),
+            sha256: pin(&"0".repeat(64)),
+            ..Default::default()
+        };
+        let store = ModelStore::resolve_with(&wrong, env_of(&[]));
+        assert!(matches!(
+            store.fetch(false),
+            Err(ModelError::ChecksumMismatch { .. })
+        ));
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+
+        let right = ModelStoreConfig {
+            mirror_url: Some(serve(body, names.len())),
+            sha256: pin(&digest),
+            ..wrong
+        };
+        let store = ModelStore::resolve_with(&right, env_of(&[]));
+        let reports = store.fetch(false).unwrap();
+        assert!(reports.iter().all(|r| r.state == FileState::Verified));
+        assert_eq!(fs::read(dir.join("model.onnx")).unwrap(), body);
+
+        // Already verified: nothing is requested again.
+        assert!(store.fetch(false).is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fetch_refuses_unpinned_files() {
+        let dir = unique_dir("model_store_unpinned");
+        let config = ModelStoreConfig {
+            model_dir: Some(dir.clone()),
+            mirror_url: Some(serve(b"anything", 1)),
+            models: BTreeMap::from([(
+                "custom".to_string(),
+                EmbeddingModelSpec {
+                    files: BTreeMap::from([(
+                        "model.onnx".to_string(),
+                        "https://example.invalid/model.onnx".to_string(),
+                    )]),
+                    ..Default::default()
+                },
+            )]),
+            model: "custom".to_string(),
+            ..Default::default()
+        };
+
+        let store = ModelStore::resolve_with(&config, env_of(&[]));
+        match store.fetch(false) {
+            Err(ModelError::Unpinned(name)) => assert_eq!(name, "model.onnx"),
+            other => panic!("expected an unpinned file, got {:?}", other),
+        }
+        assert!(!dir.join("custom").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
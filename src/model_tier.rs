@@ -1,6 +1,7 @@
-use crate::feature_config::ModelTieringConfig;
-use crate::types::TaskComplexity;
+use crate::feature_config::{CustomRoleConfig, ModelTieringConfig, TierPreference};
+use crate::types::{AgentRole, TaskComplexity};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ModelTier {
@@ -10,6 +11,16 @@ pub enum ModelTier {
     Custom(String),
 }
 
+impl From<TierPreference> for ModelTier {
+    fn from(preference: TierPreference) -> Self {
+        match preference {
+            TierPreference::Haiku => ModelTier::Haiku,
+            TierPreference::Sonnet => ModelTier::Sonnet,
+            TierPreference::Opus => ModelTier::Opus,
+        }
+    }
+}
+
 impl std::fmt::Display for ModelTier {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -31,6 +42,8 @@ pub struct ModelSelection {
 
 pub struct ModelTierer {
     config: ModelTieringConfig,
+    /// Tiers roles asked for, overriding the token estimate.
+    role_tiers: HashMap<AgentRole, ModelTier>,
 }
 
 impl ModelTierer {
@@ -39,7 +52,44 @@ impl ModelTierer {
     }
 
     pub fn with_config(config: ModelTieringConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            role_tiers: HashMap::new(),
+        }
+    }
+
+    /// Sends each custom role with a `model_tier` to that tier.
+    pub fn with_custom_roles(mut self, roles: &BTreeMap<String, CustomRoleConfig>) -> Self {
+        for (name, custom) in roles {
+            if let Some(tier) = custom.model_tier {
+                self.role_tiers
+                    .insert(AgentRole::Custom(name.clone()), tier.into());
+            }
+        }
+        self
+    }
+
+    /// Like [`ModelTierer::select_model`], but a role with a preferred tier
+    /// gets that tier while tiering is enabled.
+    pub fn select_model_for_role(
+        &self,
+        role: &AgentRole,
+        estimated_tokens: u32,
+        complexity: TaskComplexity,
+        impact_score: f64,
+    ) -> ModelSelection {
+        match self.role_tiers.get(role).filter(|_| self.config.enabled) {
+            Some(tier) => {
+                let mut selection = self.create_selection(tier.clone(), impact_score);
+                selection.reasoning = format!(
+                    "Selected {}, the tier preferred by role {}",
+                    selection.model_name,
+                    role.as_str()
+                );
+                selection
+            }
+            None => self.select_model(estimated_tokens, complexity, impact_score),
+        }
     }
 
     pub fn select_model(
@@ -185,6 +235,27 @@ mod tests {
         assert_eq!(result.tier, ModelTier::Opus);
     }
 
+    #[test]
+    fn test_custom_role_tier_preference() {
+        let roles = [(
+            "migration-author".to_string(),
+            CustomRoleConfig {
+                model_tier: Some(TierPreference::Opus),
+                ..Default::default()
+            },
+        )]
+        .into_iter()
+        .collect();
+        let tierer = ModelTierer::new().with_custom_roles(&roles);
+        let role = AgentRole::Custom("migration-author".to_string());
+
+        let result = tierer.select_model_for_role(&role, 500, TaskComplexity::Simple, 0.3);
+        assert_eq!(result.tier, ModelTier::Opus);
+        let result =
+            tierer.select_model_for_role(&AgentRole::Writer, 500, TaskComplexity::Simple, 0.3);
+        assert_eq!(result.tier, ModelTier::Haiku);
+    }
+
     #[test]
     fn test_high_impact_boost() {
         let tierer = ModelTierer::new();
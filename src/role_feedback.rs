@@ -0,0 +1,298 @@
+//! Labelled prompts that teach role routing a team's own wording.
+//!
+//! Corrections ("this prompt should have gone to the tester") are appended to
+//! a JSONL file in the state directory, one [`LabelledPrompt`] per line. The
+//! same format is what `swarm-tools roles export` writes and `roles eval`
+//! reads, so an exported set can be checked into a repo and evaluated
+//! against.
+
+use crate::role_router::RoleRouter;
+use crate::types::AgentRole;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Feedback file name inside the state directory.
+pub const FEEDBACK_FILE: &str = "role_feedback.jsonl";
+
+/// A prompt and the role it should be routed to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LabelledPrompt {
+    pub prompt: String,
+    #[serde(serialize_with = "role_name", deserialize_with = "parse_role")]
+    pub role: AgentRole,
+}
+
+fn role_name<S: Serializer>(role: &AgentRole, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(role.as_str())
+}
+
+fn parse_role<'de, D: Deserializer<'de>>(deserializer: D) -> Result<AgentRole, D::Error> {
+    AgentRole::from_name(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// Reads labelled prompts, one JSON object per line; blank lines are skipped.
+pub fn read_labelled(path: &Path) -> io::Result<Vec<LabelledPrompt>> {
+    let content = fs::read_to_string(path)?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", path.display(), i + 1, e),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Writes labelled prompts in the format [`read_labelled`] reads.
+pub fn write_labelled(examples: &[LabelledPrompt], mut output: impl Write) -> io::Result<()> {
+    for example in examples {
+        serde_json::to_writer(&mut output, example)?;
+        output.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Append-only record of routing corrections.
+pub struct RoleFeedback {
+    path: PathBuf,
+}
+
+impl RoleFeedback {
+    /// Opens the feedback kept in `state_dir`.
+    pub fn new(state_dir: &Path) -> Self {
+        Self {
+            path: state_dir.join(FEEDBACK_FILE),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, example: &LabelledPrompt) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(example)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())
+    }
+
+    /// Every correction in the order recorded, skipping lines that do not
+    /// parse.
+    pub fn entries(&self) -> Vec<LabelledPrompt> {
+        fs::read_to_string(&self.path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    /// The newest label for each distinct prompt, keeping at most
+    /// `max_per_role` of the most recent prompts per role.
+    pub fn exemplars(&self, max_per_role: usize) -> Vec<LabelledPrompt> {
+        let entries = self.entries();
+        let mut seen = HashSet::new();
+        let mut per_role: BTreeMap<AgentRole, usize> = BTreeMap::new();
+        let mut kept: Vec<LabelledPrompt> = entries
+            .into_iter()
+            .rev()
+            .filter(|example| seen.insert(example.prompt.trim().to_string()))
+            .filter(|example| {
+                let count = per_role.entry(example.role.clone()).or_default();
+                *count += 1;
+                *count <= max_per_role
+            })
+            .collect();
+        kept.reverse();
+        kept
+    }
+}
+
+/// How a router did on a labelled set.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct RoutingEvaluation {
+    pub total: usize,
+    pub correct: usize,
+    /// Fraction routed to their labelled role; 0 for an empty set.
+    pub accuracy: f64,
+    /// Prompts routed to `General` because no role stood out.
+    pub ambiguous: usize,
+    /// Counts by expected role, then by the role actually routed to.
+    pub confusion: BTreeMap<AgentRole, BTreeMap<AgentRole, usize>>,
+}
+
+impl RoutingEvaluation {
+    /// Routes every example and tallies the results.
+    pub fn run(router: &RoleRouter, examples: &[LabelledPrompt]) -> Self {
+        let mut evaluation = Self::default();
+        for example in examples {
+            let decision = router.route(&example.prompt);
+            evaluation.total += 1;
+            evaluation.correct += usize::from(decision.role == example.role);
+            evaluation.ambiguous += usize::from(decision.ambiguous);
+            *evaluation
+                .confusion
+                .entry(example.role.clone())
+                .or_default()
+                .entry(decision.role)
+                .or_default() += 1;
+        }
+        if evaluation.total > 0 {
+            evaluation.accuracy = evaluation.correct as f64 / evaluation.total as f64;
+        }
+        evaluation
+    }
+}
+
+impl fmt::Display for RoutingEvaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Accuracy: {:.1}% ({}/{}, {} ambiguous)",
+            self.accuracy * 100.0,
+            self.correct,
+            self.total,
+            self.ambiguous
+        )?;
+        if self.confusion.is_empty() {
+            return Ok(());
+        }
+
+        // Columns for every role that was expected or predicted.
+        let mut columns: Vec<AgentRole> = self
+            .confusion
+            .iter()
+            .flat_map(|(expected, row)| std::iter::once(expected).chain(row.keys()))
+            .cloned()
+            .collect();
+        columns.sort();
+        columns.dedup();
+
+        let width = columns
+            .iter()
+            .map(|role| role.as_str().len())
+            .max()
+            .unwrap_or(0);
+        write!(
+            f,
+            "\n\n{:width$}",
+            "expected \\ routed",
+            width = width.max(17)
+        )?;
+        for column in &columns {
+            write!(f, " {:>width$}", column.as_str(), width = width)?;
+        }
+        for (expected, row) in &self.confusion {
+            write!(f, "\n{:width$}", expected.as_str(), width = width.max(17))?;
+            for column in &columns {
+                let count = row.get(column).copied().unwrap_or(0);
+                write!(f, " {:>width$}", count, width = width)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labelled(prompt: &str, role: AgentRole) -> LabelledPrompt {
+        LabelledPrompt {
+            prompt: prompt.to_string(),
+            role,
+        }
+    }
+
+    #[test]
+    fn test_feedback_keeps_newest_label_per_prompt() {
+        let dir = std::env::temp_dir().join(format!("swarm_tools_feedback_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let feedback = RoleFeedback::new(&dir);
+
+        feedback
+            .record(&labelled("plan the terraform apply", AgentRole::Writer))
+            .unwrap();
+        feedback
+            .record(&labelled("plan the terraform apply", AgentRole::Optimizer))
+            .unwrap();
+        feedback
+            .record(&labelled("write the k8s manifests", AgentRole::Writer))
+            .unwrap();
+        feedback
+            .record(&labelled("draft the migration", AgentRole::Writer))
+            .unwrap();
+
+        assert_eq!(feedback.entries().len(), 4);
+        assert_eq!(
+            feedback.exemplars(1),
+            vec![
+                labelled("plan the terraform apply", AgentRole::Optimizer),
+                labelled("draft the migration", AgentRole::Writer),
+            ]
+        );
+
+        let mut exported = Vec::new();
+        write_labelled(&feedback.exemplars(10), &mut exported).unwrap();
+        let exported = String::from_utf8(exported).unwrap();
+        assert!(exported.starts_with(r#"{"prompt":"plan the terraform apply","role":"optimizer"}"#));
+
+        let path = dir.join("eval.jsonl");
+        fs::write(&path, format!("{}\n\n", exported)).unwrap();
+        assert_eq!(read_labelled(&path).unwrap(), feedback.exemplars(10));
+
+        fs::write(&path, "{\"prompt\": \"x\", \"role\": \"Pilot 2\"}\n").unwrap();
+        let err = read_labelled(&path).unwrap_err().to_string();
+        assert!(err.contains("eval.jsonl:1"), "{}", err);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_evaluation_tallies_confusion() {
+        let examples = [
+            labelled(
+                "Show me the git diff for the recent changes",
+                AgentRole::Extractor,
+            ),
+            labelled(
+                "Review this code for security vulnerabilities",
+                AgentRole::Reviewer,
+            ),
+            labelled("zzz qqq", AgentRole::Tester),
+        ];
+        let evaluation = RoutingEvaluation::run(&RoleRouter::new(), &examples);
+
+        assert_eq!(evaluation.total, 3);
+        assert_eq!(evaluation.correct, 2);
+        assert_eq!(evaluation.ambiguous, 1);
+        assert_eq!(
+            evaluation.confusion[&AgentRole::Tester][&AgentRole::General],
+            1
+        );
+        assert!((evaluation.accuracy - 2.0 / 3.0).abs() < 1e-9);
+
+        let report = evaluation.to_string();
+        assert!(
+            report.starts_with("Accuracy: 66.7% (2/3, 1 ambiguous)"),
+            "{}",
+            report
+        );
+        assert!(report.contains("expected \\ routed"), "{}", report);
+    }
+}
@@ -1,8 +1,13 @@
 use crate::config::RoleRouterKeywordsConfig;
-use crate::semantic_engine::{RoleEmbeddingStore, SemanticEngine};
+use crate::embedder::Embedder;
+use crate::feature_config::{CustomRoleConfig, PrototypeStrategy, RerankerKind, RoleRoutingConfig};
+use crate::role_feedback::LabelledPrompt;
+use crate::semantic_engine::RoleEmbeddingStore;
+use crate::team_optimizer::TaskAnalyzer;
 use crate::types::AgentRole;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::sync::Arc;
 
 /// Configuration for role-based context filtering.
@@ -66,6 +71,70 @@ pub struct FilteredContent {
     pub impact_score: f64,
 }
 
+/// Which stage of [`RoleRouter::route`] settled on the role.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingStage {
+    /// Similarity between the task and the role descriptions.
+    Embedding,
+    /// The roles' keyword tables, when there are no embeddings.
+    Keyword,
+    /// The tie-breaker run on a close first stage.
+    Reranker,
+}
+
+/// A routed role and how sure the router is of it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoutingDecision {
+    pub role: AgentRole,
+    /// Softmax probability of the best-scoring role over all roles, from 0
+    /// to 1; see `role_routing.temperature`.
+    pub confidence: f64,
+    /// Lead of the best first-stage score over the runner-up's.
+    pub margin: f64,
+    pub runner_up: Option<AgentRole>,
+    pub stage: RoutingStage,
+    /// Neither stage could separate the top roles, so `role` is
+    /// [`AgentRole::General`] rather than a guess.
+    pub ambiguous: bool,
+}
+
+/// The role one clause of a compound task was routed to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClauseRouting {
+    pub clause: String,
+    pub decision: RoutingDecision,
+}
+
+/// Every role a compound task calls for, from [`RoleRouter::route_multi`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MultiRouting {
+    /// Roles in the order their clauses appear, without repeats. Ambiguous
+    /// clauses add none; if no clause has a clear role this holds the whole
+    /// task's role.
+    pub roles: Vec<AgentRole>,
+    /// Each clause of the task and where it was routed.
+    pub clauses: Vec<ClauseRouting>,
+}
+
+impl MultiRouting {
+    /// The clauses clearly routed to `role`.
+    pub fn clauses_for(&self, role: &AgentRole) -> Vec<String> {
+        self.clauses
+            .iter()
+            .filter(|c| !c.decision.ambiguous && &c.decision.role == role)
+            .map(|c| c.clause.clone())
+            .collect()
+    }
+}
+
+/// Scores a task against the roles a close first stage could not separate,
+/// e.g. with a cross-encoder.
+pub trait Reranker: fmt::Debug + Send + Sync {
+    /// One score per candidate, higher meaning a better fit.
+    fn rerank(&self, task: &str, candidates: &[AgentRole]) -> Vec<f64>;
+}
+
 /// Router for filtering context based on agent roles.
 ///
 /// Uses embedding-based semantic matching when available, with keyword matching as fallback.
@@ -78,12 +147,16 @@ pub struct RoleRouter {
     custom_configs: HashMap<String, RoleConfig>,
     /// Default filters for each role.
     default_filters: HashMap<AgentRole, Vec<String>>,
-    /// Semantic engine for embedding-based routing.
-    semantic_engine: Option<Arc<SemanticEngine>>,
+    /// Embedder for embedding-based routing.
+    embedder: Option<Arc<dyn Embedder>>,
     /// Pre-computed role embeddings.
     role_embeddings: Option<Arc<RoleEmbeddingStore>>,
     /// Whether to use semantic routing.
     use_semantic: bool,
+    /// Margin, calibration and tie-breaking for [`RoleRouter::route`].
+    routing: RoleRoutingConfig,
+    /// Replaces the keyword tie-breaker when set.
+    reranker: Option<Arc<dyn Reranker>>,
 }
 
 impl RoleRouter {
@@ -105,9 +178,9 @@ impl RoleRouter {
         let mut role_configs = HashMap::new();
         for (role, filters) in &default_filters {
             role_configs.insert(
-                *role,
+                role.clone(),
                 RoleConfig {
-                    role: *role,
+                    role: role.clone(),
                     filters: filters.clone(),
                     keywords: filters.clone(),
                     recency_multiplier_max: config.recency_multiplier_max,
@@ -119,69 +192,259 @@ impl RoleRouter {
             role_configs,
             custom_configs: HashMap::new(),
             default_filters,
-            semantic_engine: None,
+            embedder: None,
             role_embeddings: None,
             use_semantic: false,
+            routing: RoleRoutingConfig::default(),
+            reranker: None,
+        }
+    }
+
+    /// Uses `routing` to decide when a role is too close to call.
+    pub fn with_routing(mut self, routing: RoleRoutingConfig) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Replaces the routing settings, e.g. after a config reload.
+    pub fn set_routing(&mut self, routing: RoleRoutingConfig) {
+        self.routing = routing;
+    }
+
+    /// Breaks close calls with `reranker` instead of keywords.
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
+    /// Replaces the keyword tables while keeping custom roles and the embedder.
+    pub fn update_config(&mut self, config: RoleRouterKeywordsConfig) {
+        let updated = Self::with_config(config);
+        let custom: Vec<(AgentRole, Vec<String>)> = self
+            .default_filters
+            .drain()
+            .filter(|(role, _)| role.is_custom())
+            .collect();
+        self.role_configs = updated.role_configs;
+        self.default_filters = updated.default_filters;
+        self.default_filters.extend(custom);
+    }
+
+    /// Routes to the roles declared under `custom_roles` as well, by their
+    /// keywords and, with embeddings, their descriptions.
+    ///
+    /// Apply before [`RoleRouter::with_exemplars`], which only learns
+    /// prototypes for roles the router already knows.
+    pub fn with_custom_roles(mut self, roles: &BTreeMap<String, CustomRoleConfig>) -> Self {
+        self.set_custom_roles(roles);
+        self
+    }
+
+    /// Replaces the custom roles, e.g. after a config reload.
+    pub fn set_custom_roles(&mut self, roles: &BTreeMap<String, CustomRoleConfig>) {
+        self.custom_configs
+            .retain(|_, config| !config.role.is_custom());
+        self.default_filters.retain(|role, _| !role.is_custom());
+        let recency_multiplier_max = self
+            .role_configs
+            .get(&AgentRole::General)
+            .map_or(2.0, |config| config.recency_multiplier_max);
+        for (name, custom) in roles {
+            let role = AgentRole::Custom(name.clone());
+            self.default_filters
+                .insert(role.clone(), custom.keywords.clone());
+            self.custom_configs.insert(
+                name.clone(),
+                RoleConfig {
+                    role,
+                    filters: custom.keywords.clone(),
+                    keywords: custom.keywords.clone(),
+                    recency_multiplier_max,
+                },
+            );
+        }
+
+        if let Some(store) = &self.role_embeddings {
+            let descriptions: Vec<(AgentRole, String)> = roles
+                .iter()
+                .map(|(name, custom)| {
+                    (
+                        AgentRole::Custom(name.clone()),
+                        custom.routing_description(name),
+                    )
+                })
+                .collect();
+            self.role_embeddings = Some(Arc::new(store.with_custom_roles(&descriptions)));
         }
     }
 
-    /// Creates a RoleRouter with semantic embedding support.
-    pub fn with_semantic_engine(semantic_engine: Arc<SemanticEngine>) -> Self {
-        let mut router = Self::with_config(RoleRouterKeywordsConfig::default());
-        router.semantic_engine = Some(semantic_engine.clone());
-        router.role_embeddings = Some(Arc::new(RoleEmbeddingStore::new(semantic_engine)));
+    /// Creates a RoleRouter with custom keywords that routes by embedding
+    /// prompts and role descriptions with `embedder`.
+    pub fn with_embedder(config: RoleRouterKeywordsConfig, embedder: Arc<dyn Embedder>) -> Self {
+        let mut router = Self::with_config(config);
+        router.role_embeddings = Some(Arc::new(RoleEmbeddingStore::new(Arc::clone(&embedder))));
+        router.embedder = Some(embedder);
         router.use_semantic = true;
         router
     }
 
-    /// Routes a task to the most appropriate agent role based on semantic similarity.
-    ///
-    /// Uses embedding cosine similarity between the task description and role definitions.
-    /// Falls back to keyword matching if embeddings are not available.
-    ///
-    /// # Arguments
-    /// * `task_description` - The task/user prompt to route
-    ///
-    /// # Returns
-    /// The most appropriate AgentRole for this task.
-    pub fn route_task(&self, task_description: &str) -> AgentRole {
-        if self.use_semantic {
-            if let Some(store) = &self.role_embeddings {
-                return store.route_task(task_description);
-            }
+    /// Reshapes the role prototypes with prompts labelled with the role they
+    /// should have gone to; see [`RoleEmbeddingStore::with_exemplars`].
+    /// Keyword routing is unaffected.
+    pub fn with_exemplars(
+        mut self,
+        exemplars: &[LabelledPrompt],
+        strategy: PrototypeStrategy,
+    ) -> Self {
+        if let Some(store) = self
+            .role_embeddings
+            .as_ref()
+            .filter(|_| !exemplars.is_empty())
+        {
+            self.role_embeddings = Some(Arc::new(store.with_exemplars(exemplars, strategy)));
         }
+        self
+    }
 
-        // Fallback to keyword-based routing
-        self.route_task_keyword(task_description)
+    /// The embedder routing uses, if any.
+    pub fn embedder(&self) -> Option<&Arc<dyn Embedder>> {
+        self.embedder.as_ref()
     }
 
-    /// Fallback keyword-based task routing.
-    fn route_task_keyword(&self, task: &str) -> AgentRole {
-        let task_lower = task.to_lowercase();
+    /// Creates a RoleRouter with custom keywords that routes with role
+    /// embeddings computed earlier, e.g. kept warm by the daemon.
+    pub fn with_role_embeddings(
+        config: RoleRouterKeywordsConfig,
+        role_embeddings: Arc<RoleEmbeddingStore>,
+    ) -> Self {
+        let mut router = Self::with_config(config);
+        router.role_embeddings = Some(role_embeddings);
+        router.use_semantic = true;
+        router
+    }
 
-        // Score each role based on keyword matching
-        let mut scores: Vec<(AgentRole, f64)> = self
-            .default_filters
+    /// Routes a task to the most appropriate agent role; see
+    /// [`RoleRouter::route`].
+    pub fn route_task(&self, task_description: &str) -> AgentRole {
+        self.route(task_description).role
+    }
+
+    /// Routes a task by embedding similarity to the role descriptions, or by
+    /// keywords without embeddings, and says how sure it is.
+    ///
+    /// When the best role leads the runner-up by less than
+    /// `role_routing.min_margin`, the roles within that margin go to the
+    /// reranker. If it cannot separate them either, the task is routed to
+    /// [`AgentRole::General`] and marked ambiguous.
+    pub fn route(&self, task: &str) -> RoutingDecision {
+        let stage = if self.use_semantic && self.role_embeddings.is_some() {
+            RoutingStage::Embedding
+        } else {
+            RoutingStage::Keyword
+        };
+        let scores = self.get_all_routing_scores(task);
+        let Some((best, top)) = scores.first().cloned() else {
+            return RoutingDecision {
+                role: AgentRole::General,
+                confidence: 0.0,
+                margin: 0.0,
+                runner_up: None,
+                stage,
+                ambiguous: true,
+            };
+        };
+        let runner_up = scores.get(1).map(|(role, _)| role.clone());
+        let margin = top - scores.get(1).map_or(0.0, |&(_, score)| score);
+
+        let temperature = self.routing.temperature.max(f64::EPSILON);
+        let total: f64 = scores
             .iter()
-            .map(|(role, keywords)| {
-                let score = self.task_keyword_score(&task_lower, keywords);
-                (*role, score)
+            .map(|(_, score)| ((score - top) / temperature).exp())
+            .sum();
+        let decision = |role, stage, ambiguous| RoutingDecision {
+            role,
+            confidence: 1.0 / total,
+            margin,
+            runner_up: runner_up.clone(),
+            stage,
+            ambiguous,
+        };
+
+        if margin >= self.routing.min_margin {
+            return decision(best, stage, false);
+        }
+        let candidates: Vec<AgentRole> = scores
+            .iter()
+            .take_while(|(_, score)| top - score < self.routing.min_margin)
+            .map(|(role, _)| role.clone())
+            .collect();
+        match self.rerank(task, &candidates, stage) {
+            Some(role) => decision(role, RoutingStage::Reranker, false),
+            None => decision(AgentRole::General, stage, true),
+        }
+    }
+
+    /// Routes each clause of a compound task on its own, e.g. "review this
+    /// PR and then write docs for the API" to the reviewer and the
+    /// documenter.
+    ///
+    /// Clauses come from [`TaskAnalyzer::extract_subtasks`]; a task with a
+    /// single clause is routed as [`RoleRouter::route`] would.
+    pub fn route_multi(&self, task: &str) -> MultiRouting {
+        let clauses: Vec<ClauseRouting> = TaskAnalyzer::extract_subtasks(task)
+            .into_iter()
+            .map(|clause| ClauseRouting {
+                decision: self.route(&clause),
+                clause,
             })
             .collect();
 
-        // Sort by score descending
-        scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        let mut roles: Vec<AgentRole> = Vec::new();
+        for clause in clauses.iter().filter(|c| !c.decision.ambiguous) {
+            if !roles.contains(&clause.decision.role) {
+                roles.push(clause.decision.role.clone());
+            }
+        }
+        if roles.is_empty() {
+            roles.push(match clauses.as_slice() {
+                [only] => only.decision.role.clone(),
+                _ => self.route(task).role,
+            });
+        }
+        MultiRouting { roles, clauses }
+    }
 
-        eprintln!(
-            "DEBUG get_all_routing_scores: task='{}', scores={:?}",
-            task, scores
-        );
+    /// The one candidate the reranker clearly prefers, if any.
+    fn rerank(
+        &self,
+        task: &str,
+        candidates: &[AgentRole],
+        stage: RoutingStage,
+    ) -> Option<AgentRole> {
+        let scores = match (&self.reranker, self.routing.reranker) {
+            (Some(reranker), _) => reranker.rerank(task, candidates),
+            // Keyword routing already ranked by keywords.
+            (None, RerankerKind::Keyword) if stage == RoutingStage::Embedding => {
+                let task_lower = task.to_lowercase();
+                candidates
+                    .iter()
+                    .map(|role| {
+                        self.default_filters.get(role).map_or(0.0, |keywords| {
+                            self.task_keyword_score(&task_lower, keywords)
+                        })
+                    })
+                    .collect()
+            }
+            _ => return None,
+        };
 
-        // Return the highest scoring role
-        scores
-            .first()
-            .map(|(r, _)| *r)
-            .unwrap_or(AgentRole::General)
+        let mut ranked: Vec<(AgentRole, f64)> = candidates.iter().cloned().zip(scores).collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        match ranked.as_slice() {
+            [(role, _)] => Some(role.clone()),
+            [(role, best), (_, next), ..] if best - next > f64::EPSILON => Some(role.clone()),
+            _ => None,
+        }
     }
 
     fn task_keyword_score(&self, task: &str, keywords: &[String]) -> f64 {
@@ -261,7 +524,7 @@ impl RoleRouter {
         total_messages: usize,
         impact_score: f64,
     ) -> f64 {
-        let keywords = self.get_role_keywords(role);
+        let keywords = self.get_role_keywords(&role);
 
         let keyword_score = self.keyword_matching(content, &keywords);
 
@@ -295,13 +558,13 @@ impl RoleRouter {
         keyword_score * position_score * impact_boost
     }
 
-    fn get_role_keywords(&self, role: AgentRole) -> Vec<String> {
+    fn get_role_keywords(&self, role: &AgentRole) -> Vec<String> {
         self.custom_configs
             .values()
-            .find(|c| c.role == role)
+            .find(|c| c.role == *role)
             .map(|c| c.keywords.clone())
-            .or(self.role_configs.get(&role).map(|c| c.keywords.clone()))
-            .or(self.default_filters.get(&role).cloned())
+            .or(self.role_configs.get(role).map(|c| c.keywords.clone()))
+            .or(self.default_filters.get(role).cloned())
             .unwrap_or_else(|| vec!["all".to_string()])
     }
 
@@ -334,7 +597,7 @@ impl RoleRouter {
 
     /// Filters and scores a sequence of messages for a specific agent role.
     pub fn filter_context(&self, messages: &[(&str, usize, f64)], role: AgentRole) -> RoleContext {
-        let keywords = self.get_role_keywords(role);
+        let keywords = self.get_role_keywords(&role);
         let recency_multiplier_max = self
             .role_configs
             .get(&role)
@@ -431,7 +694,7 @@ impl RoleRouter {
             .iter()
             .map(|(role, keywords)| {
                 let score = self.task_keyword_score(&task_lower, keywords);
-                (*role, score)
+                (role.clone(), score)
             })
             .collect();
         scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
@@ -529,6 +792,88 @@ mod tests {
         assert_eq!(role, AgentRole::Reviewer);
     }
 
+    #[test]
+    fn test_route_reports_confidence_and_margin() {
+        let router = RoleRouter::new();
+        let decision = router.route("Show me the git diff for the recent changes");
+        assert_eq!(decision.role, AgentRole::Extractor);
+        assert_eq!(decision.stage, RoutingStage::Keyword);
+        assert!(!decision.ambiguous);
+        assert!(decision.margin >= 0.02);
+        assert!(decision.confidence > 0.5 && decision.confidence <= 1.0);
+    }
+
+    #[test]
+    fn test_route_without_a_winner_is_ambiguous() {
+        let router = RoleRouter::new();
+        let decision = router.route("zzz qqq");
+        assert_eq!(decision.role, AgentRole::General);
+        assert!(decision.ambiguous);
+        assert_eq!(decision.margin, 0.0);
+    }
+
+    /// Prefers whichever candidate comes last.
+    #[derive(Debug)]
+    struct LastWins;
+
+    impl Reranker for LastWins {
+        fn rerank(&self, _task: &str, candidates: &[AgentRole]) -> Vec<f64> {
+            (0..candidates.len()).map(|i| i as f64).collect()
+        }
+    }
+
+    #[test]
+    fn test_close_embedding_scores_go_to_reranker() {
+        // Every role description embeds to the same vector: a dead heat.
+        let embedder = Arc::new(crate::embedder::StubEmbedder::new(1));
+        let router = RoleRouter::with_embedder(RoleRouterKeywordsConfig::default(), embedder);
+
+        let decision = router.route("Review the security_issues");
+        assert_eq!(decision.stage, RoutingStage::Reranker);
+        assert_eq!(decision.role, AgentRole::Reviewer);
+        assert!(decision.margin < 0.02);
+
+        let decision = router.route("zzz qqq");
+        assert!(decision.ambiguous);
+        assert_eq!(decision.role, AgentRole::General);
+
+        let router = router.with_reranker(Arc::new(LastWins));
+        let decision = router.route("zzz qqq");
+        assert!(!decision.ambiguous);
+        assert_eq!(decision.stage, RoutingStage::Reranker);
+
+        let mut router = router;
+        router.set_routing(RoleRoutingConfig {
+            reranker: RerankerKind::None,
+            ..Default::default()
+        });
+        assert_eq!(router.route("zzz qqq").stage, RoutingStage::Reranker);
+    }
+
+    #[test]
+    fn test_route_multi_splits_compound_tasks() {
+        let router = RoleRouter::new();
+        let routing = router.route_multi(
+            "Review this code for security vulnerabilities, and then analyze the performance metrics",
+        );
+        assert_eq!(
+            routing.roles,
+            vec![AgentRole::Reviewer, AgentRole::Analyzer]
+        );
+        assert_eq!(routing.clauses.len(), 2);
+        assert_eq!(
+            routing.clauses_for(&AgentRole::Analyzer),
+            vec!["analyze the performance metrics".to_string()]
+        );
+
+        let routing = router.route_multi("Show me the git diff for the recent changes");
+        assert_eq!(routing.roles, vec![AgentRole::Extractor]);
+        assert_eq!(routing.clauses.len(), 1);
+
+        let routing = router.route_multi("zzz qqq");
+        assert_eq!(routing.roles, vec![AgentRole::General]);
+    }
+
     #[test]
     fn test_get_all_routing_scores() {
         let router = RoleRouter::new();
@@ -0,0 +1,207 @@
+//! Append-only record of tool-routing decisions and the tokens they saved.
+//!
+//! Each line of the ledger file is one JSON [`LedgerEntry`], so concurrent
+//! hook processes can append without coordinating and a truncated last line
+//! only loses that entry. Past [`DEFAULT_MAX_LEDGER_BYTES`] the file is rotated
+//! to [`ROTATED_LEDGER_FILE`], replacing the previous one, so the ledger keeps
+//! the most recent decisions in at most twice that space.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Ledger file name inside the state directory.
+pub const LEDGER_FILE: &str = "mcp_savings.jsonl";
+
+/// Older entries, moved aside when [`LEDGER_FILE`] grew too large.
+pub const ROTATED_LEDGER_FILE: &str = "mcp_savings.1.jsonl";
+
+/// Size at which [`LEDGER_FILE`] is rotated.
+pub const DEFAULT_MAX_LEDGER_BYTES: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LedgerEntry {
+    pub timestamp: String,
+    pub session_id: String,
+    pub agent_id: String,
+    pub tool_name: String,
+    pub role: String,
+    /// `allow`, `deny` or `modify_args`.
+    pub decision: String,
+    pub token_savings_estimate: Option<usize>,
+}
+
+/// Totals over every entry in a ledger.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SavingsSummary {
+    pub decisions: usize,
+    pub denied: usize,
+    pub modified: usize,
+    pub tokens_saved: usize,
+    pub tokens_saved_by_tool: BTreeMap<String, usize>,
+}
+
+pub struct SavingsLedger {
+    path: PathBuf,
+    rotated_path: PathBuf,
+    max_bytes: u64,
+}
+
+impl SavingsLedger {
+    /// Opens the ledger kept in `state_dir`.
+    pub fn new(state_dir: &Path) -> Self {
+        Self {
+            path: state_dir.join(LEDGER_FILE),
+            rotated_path: state_dir.join(ROTATED_LEDGER_FILE),
+            max_bytes: DEFAULT_MAX_LEDGER_BYTES,
+        }
+    }
+
+    /// Rotates the ledger once it reaches `max_bytes`.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes.max(1);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, entry: &LedgerEntry) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Another hook may rotate at the same moment; whichever rename lands
+        // second just finds the file gone.
+        let full = fs::metadata(&self.path).is_ok_and(|m| m.len() >= self.max_bytes);
+        if full {
+            match fs::rename(&self.path, &self.rotated_path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())
+    }
+
+    /// Reads every entry still kept, oldest first, skipping lines that do
+    /// not parse.
+    pub fn entries(&self) -> Vec<LedgerEntry> {
+        [&self.rotated_path, &self.path]
+            .into_iter()
+            .flat_map(|path| {
+                fs::read_to_string(path)
+                    .unwrap_or_default()
+                    .lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn summary(&self) -> SavingsSummary {
+        let mut summary = SavingsSummary::default();
+        for entry in self.entries() {
+            summary.decisions += 1;
+            match entry.decision.as_str() {
+                "deny" => summary.denied += 1,
+                "modify_args" => summary.modified += 1,
+                _ => {}
+            }
+            if let Some(tokens) = entry.token_savings_estimate {
+                summary.tokens_saved += tokens;
+                *summary
+                    .tokens_saved_by_tool
+                    .entry(entry.tool_name)
+                    .or_insert(0) += tokens;
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(tool_name: &str, decision: &str, tokens: Option<usize>) -> LedgerEntry {
+        LedgerEntry {
+            timestamp: "2025-01-01T00:00:00Z".to_string(),
+            session_id: "s".to_string(),
+            agent_id: "a".to_string(),
+            tool_name: tool_name.to_string(),
+            role: "extractor".to_string(),
+            decision: decision.to_string(),
+            token_savings_estimate: tokens,
+        }
+    }
+
+    #[test]
+    fn test_record_and_summarize() {
+        let dir = std::env::temp_dir().join(format!(
+            "swarm_tools_ledger_{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_micros()
+        ));
+        let ledger = SavingsLedger::new(&dir);
+        ledger
+            .record(&entry("read_file", "modify_args", Some(1500)))
+            .unwrap();
+        ledger
+            .record(&entry("read_file", "allow", Some(200)))
+            .unwrap();
+        ledger.record(&entry("web_search", "deny", None)).unwrap();
+
+        let summary = ledger.summary();
+        assert_eq!(summary.decisions, 3);
+        assert_eq!(summary.denied, 1);
+        assert_eq!(summary.modified, 1);
+        assert_eq!(summary.tokens_saved, 1700);
+        assert_eq!(summary.tokens_saved_by_tool["read_file"], 1700);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rotation_bounds_the_ledger() {
+        let dir = std::env::temp_dir().join(format!(
+            "swarm_tools_ledger_rotation_{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_micros()
+        ));
+        let line_len = serde_json::to_string(&entry("read_file", "allow", Some(1)))
+            .unwrap()
+            .len() as u64
+            + 1;
+        let ledger = SavingsLedger::new(&dir).with_max_bytes(3 * line_len);
+        for i in 0..10 {
+            ledger
+                .record(&entry("read_file", "allow", Some(i)))
+                .unwrap();
+        }
+
+        // Rotated before the 4th, 7th and 10th entries; each rotation replaced
+        // the one before it.
+        let kept: Vec<usize> = ledger
+            .entries()
+            .iter()
+            .map(|e| e.token_savings_estimate.unwrap())
+            .collect();
+        assert_eq!(kept, [6, 7, 8, 9]);
+        assert!(fs::metadata(ledger.path()).unwrap().len() <= 3 * line_len);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
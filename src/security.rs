@@ -3,6 +3,9 @@ use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
+/// Largest state, trajectory or plan file swarm-tools will read.
+pub const MAX_FILE_SIZE: usize = 10 * 1024 * 1024; // 10MB
+
 #[derive(Debug, Error)]
 pub enum SecurityError {
     #[error("Path traversal attempt detected: {0}")]
@@ -63,6 +63,12 @@ impl SelfHealingManager {
         }
     }
 
+    /// Applies a new configuration while keeping tracked contributions.
+    pub fn update_config(&mut self, config: SelfHealingConfig) {
+        self.state.enabled = config.enabled;
+        self.config = config;
+    }
+
     pub fn check_pruning_candidate(
         &self,
         agent_id: &str,
@@ -273,4 +279,21 @@ mod tests {
         let recent = state.recent_contributions.get("agent1").unwrap();
         assert_eq!(recent.len(), 5);
     }
+
+    #[test]
+    fn test_update_config_keeps_history() {
+        let mut manager = SelfHealingManager::new();
+        manager.record_contribution("agent1", 0.5);
+
+        manager.update_config(SelfHealingConfig {
+            auto_prune_enabled: true,
+            prune_threshold: 0.6,
+            prune_over_turns: 1,
+            ..Default::default()
+        });
+
+        let decision = manager.check_pruning_candidate("agent1", AgentRole::General, 0.5);
+        assert!(matches!(decision, PruneDecision::Prune { .. }));
+        assert_eq!(manager.get_state().recent_contributions["agent1"].len(), 1);
+    }
 }
@@ -1,31 +1,59 @@
+use crate::embedder::{cosine_similarity, normalize, Embedder, WordHashEmbedder};
+use crate::feature_config::{EmbeddingModelSpec, ModelStoreConfig, PrototypeStrategy};
+use crate::model_store::{builtin_models, ModelStore, DEFAULT_MODEL};
+use crate::role_feedback::LabelledPrompt;
 use crate::types::AgentRole;
 use crate::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[cfg(all(feature = "semantic", feature = "ort"))]
-use ort::session::Session;
-
+use crate::embedder::OnnxEmbedder;
+#[cfg(feature = "semantic")]
+use crate::embedder::TokenizerHashEmbedder;
 #[cfg(feature = "semantic")]
 use tokenizers::Tokenizer;
 
 pub const DEFAULT_EMBEDDING_DIM: usize = 384;
 
+/// Tokens per text sentence-transformers feeds all-MiniLM-L6-v2.
+pub const DEFAULT_MAX_SEQ_LENGTH: usize = 256;
+
+/// Hugging Face model configuration in the model directory.
+pub const MODEL_CONFIG_FILE: &str = "config.json";
+
+/// Loads the model files and embeds with the best backend that loaded.
 #[derive(Debug, Clone)]
 pub struct SemanticEngine {
-    #[cfg(all(feature = "semantic", feature = "ort"))]
-    session: Option<Arc<Mutex<Session>>>,
-    #[cfg(feature = "semantic")]
-    tokenizer: Option<Tokenizer>,
     config: ModelConfig,
     model_path: PathBuf,
-    use_fallback: bool,
-    fallback_enabled: bool,
+    /// Registry id and entry of the model in `model_path`.
+    model: String,
+    spec: EmbeddingModelSpec,
+    max_seq_length: usize,
+    embedder: Arc<dyn Embedder>,
+    backend: EmbeddingBackend,
+    load_warnings: Vec<String>,
+}
+
+/// How [`SemanticEngine::embed`] produces embeddings.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingBackend {
+    /// BERT embeddings from the ONNX model.
+    Onnx,
+    /// Tokenizer ids without the model.
+    Tokenizer,
+    /// Hashed words; no model files needed.
+    Fallback,
 }
 
+/// The parts of a BERT `config.json` embedding depends on. Fields missing
+/// from the file keep their all-MiniLM-L6-v2 values.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ModelConfig {
     pub hidden_size: usize,
     pub vocab_size: usize,
@@ -37,6 +65,8 @@ pub struct ModelConfig {
     pub pad_token_id: u32,
     pub bos_token_id: u32,
     pub eos_token_id: u32,
+    /// Longest input the position embeddings cover.
+    pub max_position_embeddings: usize,
 }
 
 impl Default for ModelConfig {
@@ -52,453 +82,220 @@ impl Default for ModelConfig {
             pad_token_id: 0,
             bos_token_id: 101,
             eos_token_id: 102,
+            max_position_embeddings: 512,
         }
     }
 }
 
+impl ModelConfig {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid model config {:?}: {}", path, e).into())
+    }
+}
+
 impl SemanticEngine {
+    /// An engine for the model directory [`ModelStore::resolve`] picks with
+    /// the default configuration.
     pub fn new() -> Self {
-        Self::with_path(PathBuf::from("models"))
+        Self::for_store(&ModelStore::resolve(&ModelStoreConfig::default()))
+    }
+
+    /// An engine for the model `store` resolved.
+    pub fn for_store(store: &ModelStore) -> Self {
+        Self::with_path(store.dir().to_path_buf()).with_model(store.model(), store.spec().clone())
     }
 
+    /// An engine for the default model's files in `model_path`.
     pub fn with_path(model_path: PathBuf) -> Self {
+        let config = ModelConfig::default();
+        let spec = builtin_models()
+            .remove(DEFAULT_MODEL)
+            .expect("the default model is built in");
         Self {
-            #[cfg(all(feature = "semantic", feature = "ort"))]
-            session: None,
-            #[cfg(feature = "semantic")]
-            tokenizer: None,
-            config: ModelConfig::default(),
+            embedder: Arc::new(WordHashEmbedder::new(config.hidden_size)),
+            backend: EmbeddingBackend::Fallback,
+            config,
             model_path,
-            use_fallback: false,
-            fallback_enabled: true,
+            model: DEFAULT_MODEL.to_string(),
+            spec,
+            max_seq_length: DEFAULT_MAX_SEQ_LENGTH,
+            load_warnings: Vec::new(),
         }
     }
 
-    pub fn initialize(&mut self) -> Result<()> {
-        #[cfg(feature = "semantic")]
-        {
-            // First, ensure model files exist (download if needed)
-            self.ensure_models_exist()?;
-
-            // Try to load tokenizer
-            let tokenizer_path = self.model_path.join("tokenizer.json");
-            if tokenizer_path.exists() {
-                match Tokenizer::from_file(&tokenizer_path) {
-                    Ok(tokenizer) => {
-                        self.tokenizer = Some(tokenizer);
-                    }
-                    Err(e) => {
-                        eprintln!("Warning: Could not load tokenizer: {}", e);
-                    }
-                }
-            }
-
-            // Try to load ONNX model (only when ort feature is enabled)
-            #[cfg(feature = "ort")]
-            {
-                let onnx_path = self.model_path.join("model.onnx");
-                if onnx_path.exists() {
-                    #[cfg(windows)]
-                    {
-                        // On Windows, use load-dynamic to load ONNX Runtime DLL at runtime
-                        let ort_dll_path = self.model_path.join("onnxruntime.dll");
-                        if ort_dll_path.exists() {
-                            if ort::init_from(ort_dll_path.to_string_lossy().as_ref())
-                                .commit()
-                                .is_ok()
-                            {
-                                match Session::builder() {
-                                    Ok(session_builder) => {
-                                        match session_builder.commit_from_file(&onnx_path) {
-                                            Ok(inference_session) => {
-                                                self.session =
-                                                    Some(Arc::new(Mutex::new(inference_session)));
-                                                self.fallback_enabled = false;
-                                                self.use_fallback = false;
-                                                eprintln!("[SEMANTIC] Loaded ONNX embedding model (Windows runtime DLL)");
-                                            }
-                                            Err(e) => {
-                                                eprintln!(
-                                                    "Warning: Could not load ONNX model: {}",
-                                                    e
-                                                );
-                                                self.enable_fallback();
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        eprintln!(
-                                            "Warning: Could not create session builder: {}",
-                                            e
-                                        );
-                                        self.enable_fallback();
-                                    }
-                                }
-                            } else {
-                                eprintln!("Warning: Could not initialize ONNX Runtime");
-                                self.enable_fallback();
-                            }
-                        } else {
-                            eprintln!("Warning: onnxruntime.dll not found at {:?}", ort_dll_path);
-                            self.enable_fallback();
-                        }
-                    }
-
-                    #[cfg(not(windows))]
-                    {
-                        match Session::builder() {
-                            Ok(session_builder) => match session_builder
-                                .commit_from_file(&onnx_path)
-                            {
-                                Ok(inference_session) => {
-                                    self.session = Some(Arc::new(Mutex::new(inference_session)));
-                                    self.fallback_enabled = false;
-                                    self.use_fallback = false;
-                                    eprintln!("[SEMANTIC] Loaded ONNX embedding model");
-                                }
-                                Err(e) => {
-                                    eprintln!("Warning: Could not load ONNX model: {}", e);
-                                    self.enable_fallback();
-                                }
-                            },
-                            Err(e) => {
-                                eprintln!("Warning: Could not create session builder: {}", e);
-                                self.enable_fallback();
-                            }
-                        }
-                    }
-                } else {
-                    eprintln!("Warning: model.onnx not found at {:?}", onnx_path);
-                    self.enable_fallback();
-                }
-            }
-
-            // When ort is not available, use tokenizer fallback
-            #[cfg(all(feature = "semantic", not(feature = "ort")))]
-            {
-                if self.tokenizer.is_some() {
-                    eprintln!(
-                        "[SEMANTIC] Using tokenizer-based embeddings (ONNX Runtime not available)"
-                    );
-                } else {
-                    self.enable_fallback();
-                }
-            }
-        }
-
-        #[cfg(not(feature = "semantic"))]
-        {
-            self.use_fallback = true;
-        }
-
-        Ok(())
+    /// Truncates ONNX inputs to `max_seq_length` tokens, special tokens
+    /// included.
+    pub fn with_max_seq_length(mut self, max_seq_length: usize) -> Self {
+        self.max_seq_length = max_seq_length;
+        self
     }
 
-    #[cfg(feature = "semantic")]
-    fn ensure_models_exist(&self) -> Result<()> {
-        use std::fs;
-        use std::io::Write;
-
-        let model_files = [
-            ("tokenizer.json", "https://huggingface.co/sentence-transformers/all-MiniLM-L6-v2/resolve/main/tokenizer.json"),
-            ("config.json", "https://huggingface.co/sentence-transformers/all-MiniLM-L6-v2/resolve/main/config.json"),
-            ("model.onnx", "https://huggingface.co/sentence-transformers/all-MiniLM-L6-v2/resolve/main/onnx/model.onnx"),
-        ];
-
-        if !self.model_path.exists() {
-            fs::create_dir_all(&self.model_path)?;
-        }
-
-        for (filename, url) in model_files {
-            let filepath = self.model_path.join(filename);
-            if !filepath.exists() {
-                eprintln!("[SEMANTIC] Downloading {}...", filename);
-                match ureq::get(url).call() {
-                    Ok(response) => {
-                        let mut file = fs::File::create(&filepath)?;
-                        let mut reader = response.into_reader();
-                        let mut buffer = [0u8; 8192];
-                        loop {
-                            match reader.read(&mut buffer)? {
-                                0 => break,
-                                n => file.write_all(&buffer[..n])?,
-                            }
-                        }
-                        eprintln!("[SEMANTIC] Downloaded {}", filename);
-                    }
-                    Err(e) => {
-                        eprintln!("[SEMANTIC] Warning: Could not download {}: {}", filename, e);
-                    }
-                }
-            }
-        }
-
-        Ok(())
+    /// Loads the registry entry `spec`, named `model`, from the model path:
+    /// its pooling, prefixes and dimension.
+    pub fn with_model(mut self, model: &str, spec: EmbeddingModelSpec) -> Self {
+        self.model = model.to_string();
+        self.config.hidden_size = spec.dimension;
+        self.embedder = Arc::new(WordHashEmbedder::new(spec.dimension));
+        self.spec = spec;
+        self
     }
 
-    pub fn is_loaded(&self) -> bool {
-        #[cfg(all(feature = "semantic", feature = "ort"))]
-        {
-            self.session.is_some() || self.tokenizer.is_some()
-        }
-        #[cfg(all(feature = "semantic", not(feature = "ort")))]
-        {
-            self.tokenizer.is_some()
-        }
-        #[cfg(not(feature = "semantic"))]
-        {
-            false
-        }
+    pub fn model_path(&self) -> &Path {
+        &self.model_path
     }
 
-    pub fn enable_fallback(&mut self) {
-        self.fallback_enabled = true;
-        self.use_fallback = true;
-        eprintln!("[SEMANTIC] Enabled fallback mode (TF-IDF style embeddings)");
+    /// Registry id of the model.
+    pub fn model(&self) -> &str {
+        &self.model
     }
 
-    pub fn disable_fallback(&mut self) {
-        self.fallback_enabled = false;
-        if self.is_loaded() {
-            self.use_fallback = false;
-        }
+    /// Tokens per text after capping at the model's position embeddings.
+    pub fn max_seq_length(&self) -> usize {
+        self.max_seq_length.min(self.config.max_position_embeddings)
     }
 
-    pub fn embed(&self, text: &str) -> Result<Vec<f32>> {
-        if self.use_fallback || !self.is_loaded() {
-            return self.embed_fallback(text);
-        }
+    /// Problems found by the last [`SemanticEngine::initialize`].
+    pub fn load_warnings(&self) -> &[String] {
+        &self.load_warnings
+    }
 
-        #[cfg(all(feature = "semantic", feature = "ort"))]
-        {
-            self.embed_onnx(text)
-        }
-        #[cfg(any(not(feature = "semantic"), not(feature = "ort")))]
-        {
-            self.embed_tokenized(text)
-        }
+    pub fn backend(&self) -> EmbeddingBackend {
+        self.backend
     }
 
-    #[cfg(all(feature = "semantic", feature = "ort"))]
-    fn embed_onnx(&self, text: &str) -> Result<Vec<f32>> {
-        use ndarray::Array;
+    /// The backend embeddings come from, to share without the engine.
+    pub fn embedder(&self) -> Arc<dyn Embedder> {
+        Arc::clone(&self.embedder)
+    }
 
-        let input_ids = self.tokenize_to_ids(text)?;
-        let attention_mask = vec![1i64; input_ids.len()];
+    fn warn(&mut self, message: String) {
+        eprintln!("Warning: {}", message);
+        self.load_warnings.push(message);
+    }
 
-        let mut session = match &self.session {
-            Some(s) => match s.lock() {
-                Ok(guard) => guard,
-                Err(_) => return self.embed_fallback(text),
-            },
-            None => return self.embed_fallback(text),
+    /// Loads `config.json`, the tokenizer and the ONNX model already in the
+    /// model directory.
+    ///
+    /// Nothing is downloaded; provision the files with `swarm-tools models
+    /// fetch`. Problems switch the engine to fallback embeddings and are kept
+    /// in [`SemanticEngine::load_warnings`].
+    pub fn initialize(&mut self) -> Result<()> {
+        self.load_warnings.clear();
+        self.config = ModelConfig {
+            hidden_size: self.spec.dimension,
+            ..Default::default()
         };
-
-        let input_ids_array: Array<i64, _> = Array::from_shape_vec(
-            (1, input_ids.len()),
-            input_ids.iter().map(|&x| x as i64).collect(),
-        )?;
-        let attention_mask_array: Array<i64, _> =
-            Array::from_shape_vec((1, attention_mask.len()), attention_mask)?;
-
-        let input_ids_tensor = ort::value::Tensor::from_array(input_ids_array.into_dyn())?;
-        let attention_mask_tensor =
-            ort::value::Tensor::from_array(attention_mask_array.into_dyn())?;
-
-        let inputs = ort::inputs![
-            "input_ids" => input_ids_tensor,
-            "attention_mask" => attention_mask_tensor,
-        ];
-
-        let outputs = session.run(inputs)?;
-
-        let output_array = outputs[0].try_extract_array::<f32>()?;
-        let data: Vec<f32> = output_array.iter().copied().collect();
-
-        let seq_len = input_ids.len();
-        let hidden_size = self.config.hidden_size;
-
-        let mut sum = vec![0.0f32; hidden_size];
-        for i in 0..seq_len {
-            for j in 0..hidden_size {
-                sum[j] += data[i * hidden_size + j];
+        let config_path = self.model_path.join(MODEL_CONFIG_FILE);
+        if config_path.exists() {
+            // The model's own config wins over its registry entry.
+            match ModelConfig::from_file(&config_path) {
+                Ok(config) => {
+                    if config.hidden_size != self.spec.dimension {
+                        self.warn(format!(
+                            "{:?} has hidden_size {}, but model {} is registered with dimension {}",
+                            config_path, config.hidden_size, self.model, self.spec.dimension
+                        ));
+                    }
+                    self.config = config;
+                }
+                Err(e) => self.warn(e.to_string()),
             }
         }
+        self.embedder = Arc::new(WordHashEmbedder::new(self.config.hidden_size));
+        self.backend = EmbeddingBackend::Fallback;
 
-        for j in 0..hidden_size {
-            sum[j] /= seq_len as f32;
-        }
-
-        Ok(sum)
-    }
-
-    #[cfg(feature = "semantic")]
-    fn embed_tokenized(&self, text: &str) -> Result<Vec<f32>> {
-        match &self.tokenizer {
-            Some(tokenizer) => {
-                let encoding = tokenizer
-                    .encode(text, false)
-                    .map_err(|e| anyhow::anyhow!(e))?;
-
-                let ids = encoding.get_ids();
-                let attention_mask = encoding.get_attention_mask();
-                let lowercase_text = text.to_lowercase();
-
-                let hidden_size = self.config.hidden_size;
-                let mut embedding = vec![0.0f32; hidden_size];
-
-                // Token-based embedding
-                for (i, &id) in ids.iter().enumerate() {
-                    let mask_weight = if i < attention_mask.len() && attention_mask[i] > 0 {
-                        1.0
-                    } else {
-                        0.0
-                    };
-
-                    let base_idx = id as usize % hidden_size;
-                    embedding[base_idx] += mask_weight;
-
-                    if base_idx + 1 < hidden_size {
-                        embedding[base_idx + 1] += mask_weight * 0.5;
-                    }
+        #[cfg(feature = "semantic")]
+        {
+            // Try to load tokenizer
+            let tokenizer_path = self.model_path.join("tokenizer.json");
+            let mut tokenizer: Option<Tokenizer> = None;
+            if tokenizer_path.exists() {
+                match Tokenizer::from_file(&tokenizer_path) {
+                    Ok(loaded) => tokenizer = Some(loaded),
+                    Err(e) => self.warn(format!("Could not load tokenizer: {}", e)),
                 }
+            }
 
-                // Role-specific keyword boosting
-                let role_keywords: Vec<(AgentRole, Vec<&str>)> = vec![
-                    (
-                        AgentRole::Extractor,
-                        vec!["git", "diff", "extract", "file", "change", "delta"],
-                    ),
-                    (
-                        AgentRole::Analyzer,
-                        vec!["analyze", "metric", "pattern", "statistic", "find"],
-                    ),
-                    (
-                        AgentRole::Reviewer,
-                        vec!["review", "security", "bug", "error", "quality", "check"],
-                    ),
-                    (
-                        AgentRole::Writer,
-                        vec!["write", "doc", "update", "content", "text"],
-                    ),
-                    (
-                        AgentRole::Synthesizer,
-                        vec!["summar", "conclu", "recommend", "consolid"],
-                    ),
-                    (AgentRole::Tester, vec!["test", "verif", "unit", "check"]),
-                    (
-                        AgentRole::Documenter,
-                        vec!["document", "comment", "api", "guide"],
-                    ),
-                    (
-                        AgentRole::Optimizer,
-                        vec!["optim", "perf", "refactor", "efficien"],
-                    ),
-                ];
-
-                for (role, keywords) in &role_keywords {
-                    for keyword in keywords {
-                        if lowercase_text.contains(keyword) {
-                            let boost_idx = (*role as usize) % hidden_size;
-                            embedding[boost_idx] += 0.3;
-                        }
-                    }
+            // Try to load ONNX model (only when ort feature is enabled)
+            #[cfg(feature = "ort")]
+            match OnnxEmbedder::load(
+                &self.model_path,
+                tokenizer.clone(),
+                self.config.clone(),
+                self.max_seq_length(),
+                &self.model,
+                &self.spec,
+            ) {
+                Ok(embedder) => {
+                    self.embedder = Arc::new(embedder);
+                    self.backend = EmbeddingBackend::Onnx;
+                    eprintln!("[SEMANTIC] Loaded ONNX embedding model {}", self.model);
                 }
+                Err(message) => self.warn(message),
+            }
 
-                // Normalize
-                let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-                if norm > 0.0 {
-                    for v in &mut embedding {
-                        *v /= norm;
+            // Without ONNX (no ort feature, or no runtime library), hash
+            // tokenizer ids rather than whole words.
+            if self.backend == EmbeddingBackend::Fallback {
+                match tokenizer {
+                    Some(tokenizer) => {
+                        self.embedder = Arc::new(TokenizerHashEmbedder::new(
+                            tokenizer,
+                            self.config.hidden_size,
+                        ));
+                        self.backend = EmbeddingBackend::Tokenizer;
+                        eprintln!(
+                            "[SEMANTIC] Using tokenizer-based embeddings (ONNX model not loaded)"
+                        );
                     }
+                    None => self.warn(format!("No tokenizer loaded from {:?}", tokenizer_path)),
                 }
-
-                Ok(embedding)
             }
-            None => self.embed_fallback(text),
         }
-    }
 
-    #[cfg(feature = "semantic")]
-    fn tokenize_to_ids(&self, text: &str) -> Result<Vec<u32>> {
-        match &self.tokenizer {
-            Some(tokenizer) => {
-                let encoding = tokenizer
-                    .encode(text, false)
-                    .map_err(|e| anyhow::anyhow!(e))?;
-                Ok(encoding.get_ids().to_vec())
-            }
-            None => self.tokenize_fallback(text),
-        }
+        Ok(())
     }
 
-    fn tokenize_fallback(&self, text: &str) -> Result<Vec<u32>> {
-        let mut tokens = Vec::new();
-        tokens.push(self.config.bos_token_id);
-
-        let clean_text: String = text
-            .to_lowercase()
-            .chars()
-            .filter(|c| c.is_alphanumeric() || c.is_whitespace())
-            .collect();
-
-        let vocab_size_u32 = self.config.vocab_size as u32;
-        for word in clean_text.split_whitespace() {
-            let hash = Self::simple_hash(word);
-            tokens.push(hash % vocab_size_u32);
-        }
-
-        tokens.push(self.config.eos_token_id);
-        Ok(tokens)
+    pub fn is_loaded(&self) -> bool {
+        self.backend != EmbeddingBackend::Fallback
     }
 
-    fn simple_hash(word: &str) -> u32 {
-        let mut hash = 0u32;
-        for byte in word.bytes() {
-            hash = hash.wrapping_mul(31).wrapping_add(byte as u32);
-        }
-        hash
+    pub fn enable_fallback(&mut self) {
+        self.embedder = Arc::new(WordHashEmbedder::new(self.config.hidden_size));
+        self.backend = EmbeddingBackend::Fallback;
+        eprintln!("[SEMANTIC] Enabled fallback mode (TF-IDF style embeddings)");
     }
 
-    fn embed_fallback(&self, text: &str) -> Result<Vec<f32>> {
-        let lowercase_text = text.to_lowercase();
-        let words: Vec<&str> = lowercase_text.split_whitespace().collect();
-        let mut embedding = vec![0.0f32; self.config.hidden_size];
-
-        for (i, word) in words.iter().enumerate() {
-            let hash = Self::simple_hash(word) as usize % self.config.hidden_size;
-            let weight = (i as f32 + 1.0).recip();
-            embedding[hash] += weight;
-        }
-
-        let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm > 0.0 {
-            for v in &mut embedding {
-                *v /= norm;
-            }
-        }
+    pub fn cosine_similarity(&self, a: &[f32], b: &[f32]) -> f32 {
+        cosine_similarity(a, b)
+    }
 
-        Ok(embedding)
+    pub fn embedding_dimension(&self) -> usize {
+        self.config.hidden_size
     }
+}
 
-    pub fn cosine_similarity(&self, a: &[f32], b: &[f32]) -> f32 {
-        if a.len() != b.len() {
-            return 0.0;
-        }
+impl Embedder for SemanticEngine {
+    fn name(&self) -> &str {
+        self.embedder.name()
+    }
 
-        let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-        let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    fn dimension(&self) -> usize {
+        self.embedder.dimension()
+    }
 
-        if norm_a == 0.0 || norm_b == 0.0 {
-            return 0.0;
-        }
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embedder.embed(text)
+    }
 
-        dot_product / (norm_a * norm_b)
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.embedder.embed_batch(texts)
     }
 
-    pub fn embedding_dimension(&self) -> usize {
-        self.config.hidden_size
+    fn embed_queries(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.embedder.embed_queries(texts)
     }
 }
 
@@ -510,90 +307,196 @@ impl Default for SemanticEngine {
     }
 }
 
+/// One-line description of each role, embedded as its starting prototype.
+const ROLE_DESCRIPTIONS: [(AgentRole, &str); 10] = [
+    (
+        AgentRole::Extractor,
+        "Extract code changes, file deltas, git diff, modifications, additions",
+    ),
+    (
+        AgentRole::Analyzer,
+        "Analyze code patterns, metrics, statistics, findings, trends",
+    ),
+    (
+        AgentRole::Writer,
+        "Write documentation, content, updates, revisions, text",
+    ),
+    (
+        AgentRole::Reviewer,
+        "Review code, security issues, bugs, errors, violations, quality gate",
+    ),
+    (
+        AgentRole::Synthesizer,
+        "Synthesize summaries, findings, consolidations, conclusions, recommendations",
+    ),
+    (
+        AgentRole::Tester,
+        "Test code, run tests, execute verification, unit tests",
+    ),
+    (
+        AgentRole::Documenter,
+        "Write documentation, comments, API docs, guides",
+    ),
+    (
+        AgentRole::Optimizer,
+        "Optimize performance, refactor code, improve efficiency",
+    ),
+    (
+        AgentRole::Specialist,
+        "Handle specialized tasks, domain-specific requirements",
+    ),
+    (
+        AgentRole::General,
+        "General purpose tasks, communication, messaging",
+    ),
+];
+
+/// Role descriptions embedded once, as passages, to route prompts embedded
+/// as queries.
+///
+/// Each role is scored by its closest prototype: the description alone, or
+/// after [`RoleEmbeddingStore::with_exemplars`] the centroid of the
+/// description and the prompts labelled with the role, or each of them.
 #[derive(Debug, Clone)]
 pub struct RoleEmbeddingStore {
-    engine: Arc<SemanticEngine>,
+    engine: Arc<dyn Embedder>,
+    /// Name of the embedder the role embeddings came from.
+    model: String,
+    /// Embedded role descriptions.
     role_embeddings: HashMap<AgentRole, Vec<f32>>,
+    prototypes: HashMap<AgentRole, Vec<Vec<f32>>>,
 }
 
 impl RoleEmbeddingStore {
-    pub fn new(engine: Arc<SemanticEngine>) -> Self {
+    pub fn new(engine: Arc<dyn Embedder>) -> Self {
         let mut store = Self {
+            model: engine.name().to_string(),
             engine,
             role_embeddings: HashMap::new(),
+            prototypes: HashMap::new(),
         };
 
-        let role_descriptions = [
-            (
-                AgentRole::Extractor,
-                "Extract code changes, file deltas, git diff, modifications, additions".to_string(),
-            ),
-            (
-                AgentRole::Analyzer,
-                "Analyze code patterns, metrics, statistics, findings, trends".to_string(),
-            ),
-            (
-                AgentRole::Writer,
-                "Write documentation, content, updates, revisions, text".to_string(),
-            ),
-            (
-                AgentRole::Reviewer,
-                "Review code, security issues, bugs, errors, violations, quality gate".to_string(),
-            ),
-            (
-                AgentRole::Synthesizer,
-                "Synthesize summaries, findings, consolidations, conclusions, recommendations"
-                    .to_string(),
-            ),
-            (
-                AgentRole::Tester,
-                "Test code, run tests, execute verification, unit tests".to_string(),
-            ),
-            (
-                AgentRole::Documenter,
-                "Write documentation, comments, API docs, guides".to_string(),
-            ),
-            (
-                AgentRole::Optimizer,
-                "Optimize performance, refactor code, improve efficiency".to_string(),
-            ),
-            (
-                AgentRole::Specialist,
-                "Handle specialized tasks, domain-specific requirements".to_string(),
-            ),
-            (
-                AgentRole::General,
-                "General purpose tasks, communication, messaging".to_string(),
-            ),
-        ];
-
-        for (role, description) in role_descriptions {
-            if let Ok(embedding) = store.engine.embed(&description) {
-                store.role_embeddings.insert(role, embedding);
+        let texts: Vec<&str> = ROLE_DESCRIPTIONS
+            .iter()
+            .map(|(_, description)| *description)
+            .collect();
+        if let Ok(embeddings) = store.engine.embed_batch(&texts) {
+            for ((role, _), embedding) in ROLE_DESCRIPTIONS.iter().zip(embeddings) {
+                store
+                    .prototypes
+                    .insert(role.clone(), vec![embedding.clone()]);
+                store.role_embeddings.insert(role.clone(), embedding);
             }
         }
 
         store
     }
 
-    pub fn route_task(&self, user_prompt: &str) -> AgentRole {
-        let prompt_embedding = match self.engine.embed(user_prompt) {
-            Ok(e) => e,
-            Err(_) => {
-                return AgentRole::General;
+    /// Rebuilds each role's prototypes from its description and `exemplars`,
+    /// prompts labelled with the role they should have gone to.
+    ///
+    /// Exemplars are embedded as passages, like the descriptions, so the
+    /// daemon's embedding cache spares re-embedding them on every prompt.
+    /// Roles without exemplars keep their description; exemplars for roles
+    /// the store does not know, such as custom roles since removed, are
+    /// ignored.
+    pub fn with_exemplars(
+        &self,
+        exemplars: &[LabelledPrompt],
+        strategy: PrototypeStrategy,
+    ) -> Self {
+        let mut store = self.clone();
+        if exemplars.is_empty() {
+            return store;
+        }
+        let texts: Vec<&str> = exemplars.iter().map(|e| e.prompt.as_str()).collect();
+        let Ok(embeddings) = self.engine.embed_batch(&texts) else {
+            return store;
+        };
+
+        let mut by_role: HashMap<AgentRole, Vec<Vec<f32>>> = HashMap::new();
+        for (example, embedding) in exemplars.iter().zip(embeddings) {
+            by_role
+                .entry(example.role.clone())
+                .or_default()
+                .push(embedding);
+        }
+        for (role, mut vectors) in by_role {
+            let Some(description) = self.role_embeddings.get(&role) else {
+                continue;
+            };
+            vectors.insert(0, description.clone());
+            let prototypes = match strategy {
+                PrototypeStrategy::Centroid => vec![centroid(&vectors)],
+                PrototypeStrategy::Exemplars => vectors,
+            };
+            store.prototypes.insert(role, prototypes);
+        }
+        store
+    }
+
+    /// Replaces the custom roles with `roles`, each with the description
+    /// embedded as its prototype.
+    pub fn with_custom_roles(&self, roles: &[(AgentRole, String)]) -> Self {
+        let mut store = self.clone();
+        store.role_embeddings.retain(|role, _| !role.is_custom());
+        store.prototypes.retain(|role, _| !role.is_custom());
+        if roles.is_empty() {
+            return store;
+        }
+
+        let texts: Vec<&str> = roles
+            .iter()
+            .map(|(_, description)| description.as_str())
+            .collect();
+        if let Ok(embeddings) = self.engine.embed_batch(&texts) {
+            for ((role, _), embedding) in roles.iter().zip(embeddings) {
+                store
+                    .prototypes
+                    .insert(role.clone(), vec![embedding.clone()]);
+                store.role_embeddings.insert(role.clone(), embedding);
             }
+        }
+        store
+    }
+
+    /// Name of the embedder the role embeddings came from.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Number of prototypes each role is scored against.
+    pub fn prototype_counts(&self) -> HashMap<AgentRole, usize> {
+        self.prototypes
+            .iter()
+            .map(|(role, prototypes)| (role.clone(), prototypes.len()))
+            .collect()
+    }
+
+    fn embed_prompt(&self, user_prompt: &str) -> Option<Vec<f32>> {
+        self.engine.embed_queries(&[user_prompt]).ok()?.pop()
+    }
+
+    fn role_score(prompt_embedding: &[f32], prototypes: &[Vec<f32>]) -> f32 {
+        prototypes
+            .iter()
+            .map(|prototype| cosine_similarity(prompt_embedding, prototype))
+            .fold(f32::MIN, f32::max)
+    }
+
+    pub fn route_task(&self, user_prompt: &str) -> AgentRole {
+        let Some(prompt_embedding) = self.embed_prompt(user_prompt) else {
+            return AgentRole::General;
         };
 
         let mut best_role = AgentRole::General;
         let mut best_score = 0.0f32;
 
-        for (role, role_embedding) in &self.role_embeddings {
-            let score = self
-                .engine
-                .cosine_similarity(&prompt_embedding, role_embedding);
+        for (role, prototypes) in &self.prototypes {
+            let score = Self::role_score(&prompt_embedding, prototypes);
             if score > best_score {
                 best_score = score;
-                best_role = *role;
+                best_role = role.clone();
             }
         }
 
@@ -601,21 +504,18 @@ impl RoleEmbeddingStore {
     }
 
     pub fn get_all_scores(&self, user_prompt: &str) -> Vec<(AgentRole, f32)> {
-        let prompt_embedding = match self.engine.embed(user_prompt) {
-            Ok(e) => e,
-            Err(_) => {
-                return Vec::new();
-            }
+        let Some(prompt_embedding) = self.embed_prompt(user_prompt) else {
+            return Vec::new();
         };
 
         let mut scores: Vec<(AgentRole, f32)> = self
-            .role_embeddings
+            .prototypes
             .iter()
-            .map(|(role, role_embedding)| {
-                let score = self
-                    .engine
-                    .cosine_similarity(&prompt_embedding, role_embedding);
-                (*role, score)
+            .map(|(role, prototypes)| {
+                (
+                    role.clone(),
+                    Self::role_score(&prompt_embedding, prototypes),
+                )
             })
             .collect();
 
@@ -624,6 +524,21 @@ impl RoleEmbeddingStore {
     }
 }
 
+/// Unit-length mean of the unit-length `vectors`.
+fn centroid(vectors: &[Vec<f32>]) -> Vec<f32> {
+    let dimension = vectors.first().map_or(0, Vec::len);
+    let mut sum = vec![0.0f32; dimension];
+    for vector in vectors {
+        let mut vector = vector.clone();
+        normalize(&mut vector);
+        for (total, x) in sum.iter_mut().zip(vector) {
+            *total += x;
+        }
+    }
+    normalize(&mut sum);
+    sum
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -647,6 +562,61 @@ mod tests {
         assert_eq!(engine.embedding_dimension(), 384);
     }
 
+    #[test]
+    fn test_model_config_from_hugging_face_file() {
+        let dir =
+            std::env::temp_dir().join(format!("swarm_tools_model_config_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(MODEL_CONFIG_FILE),
+            r#"{"architectures": ["BertModel"], "hidden_size": 384,
+                "max_position_embeddings": 128, "model_type": "bert",
+                "type_vocab_size": 2, "vocab_size": 30522}"#,
+        )
+        .unwrap();
+
+        let mut engine = SemanticEngine::with_path(dir.clone());
+        engine.initialize().ok();
+        assert_eq!(engine.max_seq_length(), 128);
+        assert_eq!(engine.embedding_dimension(), 384);
+        let engine = engine.with_max_seq_length(64);
+        assert_eq!(engine.max_seq_length(), 64);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "semantic")]
+    #[test]
+    fn test_tokenizer_embeddings_when_onnx_does_not_load() {
+        use tokenizers::models::wordlevel::WordLevel;
+
+        let dir = std::env::temp_dir().join(format!(
+            "swarm_tools_tokenizer_fallback_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let vocab = [("[UNK]", 0), ("review", 1), ("code", 2)]
+            .into_iter()
+            .map(|(token, id)| (token.to_string(), id))
+            .collect();
+        let model = WordLevel::builder()
+            .vocab(vocab)
+            .unk_token("[UNK]".to_string())
+            .build()
+            .unwrap();
+        Tokenizer::new(model)
+            .save(dir.join("tokenizer.json"), false)
+            .unwrap();
+
+        // No model.onnx, so ONNX fails to load wherever ort is enabled.
+        let mut engine = SemanticEngine::with_path(dir.clone());
+        engine.initialize().unwrap();
+        assert_eq!(engine.backend(), EmbeddingBackend::Tokenizer);
+        assert!(engine.is_loaded());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_role_embedding_store() {
         let mut engine = SemanticEngine::new();
@@ -664,6 +634,43 @@ mod tests {
         assert_eq!(role, AgentRole::Extractor);
     }
 
+    #[test]
+    fn test_exemplars_reshape_prototypes() {
+        let store = RoleEmbeddingStore::new(Arc::new(WordHashEmbedder::new(DEFAULT_EMBEDDING_DIM)));
+        let task = "apply the terraform changes to the staging vpc";
+        assert_ne!(store.route_task(task), AgentRole::Optimizer);
+
+        let exemplars = [
+            LabelledPrompt {
+                prompt: "apply terraform to the production vpc".to_string(),
+                role: AgentRole::Optimizer,
+            },
+            LabelledPrompt {
+                prompt: "terraform the staging cluster".to_string(),
+                role: AgentRole::Optimizer,
+            },
+        ];
+        for strategy in [PrototypeStrategy::Centroid, PrototypeStrategy::Exemplars] {
+            let learned = store.with_exemplars(&exemplars, strategy);
+            assert_eq!(
+                learned.route_task(task),
+                AgentRole::Optimizer,
+                "{:?}",
+                strategy
+            );
+        }
+
+        let counts = store
+            .with_exemplars(&exemplars, PrototypeStrategy::Exemplars)
+            .prototype_counts();
+        assert_eq!(counts[&AgentRole::Optimizer], 3);
+        assert_eq!(counts[&AgentRole::Tester], 1);
+        let counts = store
+            .with_exemplars(&exemplars, PrototypeStrategy::Centroid)
+            .prototype_counts();
+        assert_eq!(counts[&AgentRole::Optimizer], 1);
+    }
+
     #[test]
     fn test_all_scores() {
         let mut engine = SemanticEngine::new();
@@ -0,0 +1,340 @@
+//! Session-scoped hook state and its retention.
+//!
+//! Every Claude Code session gets its own directory,
+//! `<config_dir>/sessions/<session_id>/`, holding loop-detector history,
+//! trajectories, saved states and checkpoints. Nothing carries over between
+//! sessions, so a prompt legitimately repeated on different days is never a
+//! loop. Finished sessions are expired, or archived, by
+//! [`apply_retention`].
+
+use crate::feature_config::SessionRetentionConfig;
+use crate::security::sanitize_agent_id;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Directory under the state directory holding one directory per session.
+pub const SESSIONS_DIR: &str = "sessions";
+
+/// Directory under the state directory receiving archived sessions.
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// Empty file dated when its session was archived; a rename keeps the
+/// session's own modification times.
+const ARCHIVED_MARKER: &str = ".archived";
+
+/// Session metadata file inside a session directory.
+pub const SESSION_FILE: &str = "session.json";
+
+/// Per-agent directories written directly under the state directory before
+/// state was session-scoped.
+pub const LEGACY_DIRS: [&str; 3] = ["loop-detector", "states", "checkpoints"];
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Contents of `session.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct SessionRecord {
+    pub session_id: String,
+    pub started_at: String,
+    /// How Claude Code started the session: `startup`, `resume`, `clear` or `compact`.
+    pub source: String,
+    /// Set by the most recent Stop.
+    pub last_stopped_at: Option<String>,
+    pub summary: Option<SessionSummary>,
+}
+
+/// Activity totals for a session, refreshed on every Stop.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct SessionSummary {
+    pub tool_calls: usize,
+    pub failed_tool_calls: usize,
+    pub repeated_tool_calls: usize,
+    pub trajectory_tokens: u32,
+    pub denied_tool_calls: usize,
+    pub tokens_saved: usize,
+}
+
+/// What one [`apply_retention`] pass removed or archived.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionReport {
+    pub expired_sessions: Vec<String>,
+    pub archived_sessions: Vec<String>,
+    pub legacy_files: usize,
+    /// Archived sessions and legacy files deleted from the archive.
+    pub pruned_archive: usize,
+}
+
+pub fn session_dir(state_dir: &Path, session_id: &str) -> PathBuf {
+    state_dir
+        .join(SESSIONS_DIR)
+        .join(sanitize_agent_id(session_id))
+}
+
+/// Reads a session's `session.json`, if it exists and parses.
+pub fn load_session(session_dir: &Path) -> Option<SessionRecord> {
+    let content = fs::read_to_string(session_dir.join(SESSION_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn save_session(session_dir: &Path, record: &SessionRecord) -> std::io::Result<()> {
+    fs::create_dir_all(session_dir)?;
+    fs::write(
+        session_dir.join(SESSION_FILE),
+        serde_json::to_string_pretty(record)?,
+    )
+}
+
+/// Expires sessions and legacy per-agent files that fall outside `policy`.
+///
+/// A session is stale when nothing in its directory changed for
+/// `max_age_days`, or when it is not among the `max_sessions` most recently
+/// active. `current_session` is never touched. Stale sessions are moved to
+/// `<state_dir>/archive/` when `policy.archive` is set and deleted otherwise;
+/// legacy files only expire by age. The archive is held to the same limits,
+/// counted from when each entry was archived.
+pub fn apply_retention(
+    state_dir: &Path,
+    policy: &SessionRetentionConfig,
+    current_session: Option<&str>,
+    now: SystemTime,
+) -> std::io::Result<RetentionReport> {
+    let mut report = RetentionReport::default();
+    if !policy.enabled {
+        return Ok(report);
+    }
+    let max_age = Duration::from_secs(policy.max_age_days * SECONDS_PER_DAY);
+    let is_stale = |modified: SystemTime| {
+        now.duration_since(modified)
+            .map(|age| age > max_age)
+            .unwrap_or(false)
+    };
+
+    let current = current_session.map(sanitize_agent_id);
+    let sessions = sessions_newest_first(&state_dir.join(SESSIONS_DIR), current.as_deref());
+
+    // The current session takes one of the kept slots.
+    let keep = policy
+        .max_sessions
+        .saturating_sub(usize::from(current.is_some()));
+
+    for (index, (name, path, modified)) in sessions.into_iter().enumerate() {
+        if index < keep && !is_stale(modified) {
+            continue;
+        }
+        if policy.archive {
+            let target = state_dir.join(ARCHIVE_DIR).join(SESSIONS_DIR).join(&name);
+            move_dir(&path, &target)?;
+            stamp(&target.join(ARCHIVED_MARKER), now)?;
+            report.archived_sessions.push(name);
+        } else {
+            fs::remove_dir_all(&path)?;
+            report.expired_sessions.push(name);
+        }
+    }
+
+    for dir_name in LEGACY_DIRS {
+        let Ok(entries) = fs::read_dir(state_dir.join(dir_name)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let stale = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(is_stale)
+                .unwrap_or(false);
+            if !path.is_file() || !stale {
+                continue;
+            }
+            if policy.archive {
+                let target = state_dir
+                    .join(ARCHIVE_DIR)
+                    .join(dir_name)
+                    .join(entry.file_name());
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(&path, &target)?;
+                stamp(&target, now)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+            report.legacy_files += 1;
+        }
+    }
+
+    let archive_dir = state_dir.join(ARCHIVE_DIR);
+    let archived = sessions_newest_first(&archive_dir.join(SESSIONS_DIR), None);
+    for (index, (_, path, modified)) in archived.into_iter().enumerate() {
+        if index < policy.max_sessions && !is_stale(modified) {
+            continue;
+        }
+        fs::remove_dir_all(&path)?;
+        report.pruned_archive += 1;
+    }
+    for dir_name in LEGACY_DIRS {
+        let Ok(entries) = fs::read_dir(archive_dir.join(dir_name)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .map(is_stale)
+                .unwrap_or(false);
+            if entry.path().is_file() && stale {
+                fs::remove_file(entry.path())?;
+                report.pruned_archive += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Session directories under `dir` other than `skip`, most recently active
+/// first.
+fn sessions_newest_first(dir: &Path, skip: Option<&str>) -> Vec<(String, PathBuf, SystemTime)> {
+    let mut sessions = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !path.is_dir() || skip == Some(name.as_str()) {
+                continue;
+            }
+            let modified = last_modified(&path).unwrap_or(SystemTime::UNIX_EPOCH);
+            sessions.push((name, path, modified));
+        }
+    }
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.2));
+    sessions
+}
+
+/// Dates `path`, creating it if needed, as modified at `time`.
+fn stamp(path: &Path, time: SystemTime) -> std::io::Result<()> {
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .set_modified(time)
+}
+
+/// Most recent modification time of a directory or anything below it.
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    let mut newest = fs::metadata(path).and_then(|m| m.modified()).ok();
+    if path.is_dir() {
+        for entry in fs::read_dir(path).ok()?.flatten() {
+            if let Some(modified) = last_modified(&entry.path()) {
+                newest = Some(newest.map_or(modified, |n| n.max(modified)));
+            }
+        }
+    }
+    newest
+}
+
+fn move_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    if to.exists() {
+        fs::remove_dir_all(to)?;
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(from, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unique_dir(test_name: &str) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_micros();
+        let dir = std::env::temp_dir().join(format!("swarm_tools_{}_{}", test_name, timestamp));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch_session(state_dir: &Path, session_id: &str) {
+        save_session(
+            &session_dir(state_dir, session_id),
+            &SessionRecord {
+                session_id: session_id.to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_retention_expires_old_sessions_and_legacy_files() {
+        let state_dir = unique_dir("retention_age");
+        touch_session(&state_dir, "old");
+        touch_session(&state_dir, "current");
+        fs::create_dir_all(state_dir.join("loop-detector")).unwrap();
+        fs::write(state_dir.join("loop-detector/agent_hashes.json"), "{}").unwrap();
+
+        let policy = SessionRetentionConfig::default();
+        let later =
+            SystemTime::now() + Duration::from_secs((policy.max_age_days + 1) * SECONDS_PER_DAY);
+        let report = apply_retention(&state_dir, &policy, Some("current"), later).unwrap();
+
+        assert_eq!(report.expired_sessions, vec!["old".to_string()]);
+        assert_eq!(report.legacy_files, 1);
+        assert!(session_dir(&state_dir, "current").exists());
+        assert!(!session_dir(&state_dir, "old").exists());
+    }
+
+    #[test]
+    fn test_retention_archives_beyond_max_sessions() {
+        let state_dir = unique_dir("retention_count");
+        for id in ["a", "b", "c"] {
+            touch_session(&state_dir, id);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        let policy = SessionRetentionConfig {
+            max_sessions: 2,
+            archive: true,
+            ..Default::default()
+        };
+        let report = apply_retention(&state_dir, &policy, None, SystemTime::now()).unwrap();
+
+        assert_eq!(report.archived_sessions, vec!["a".to_string()]);
+        assert!(state_dir.join("archive/sessions/a/session.json").exists());
+        assert!(session_dir(&state_dir, "c").exists());
+    }
+
+    #[test]
+    fn test_retention_prunes_the_archive() {
+        let state_dir = unique_dir("retention_archive");
+        let policy = SessionRetentionConfig {
+            max_sessions: 1,
+            archive: true,
+            ..Default::default()
+        };
+        let day = Duration::from_secs(SECONDS_PER_DAY);
+        let start = SystemTime::now();
+        for (i, id) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            touch_session(&state_dir, id);
+            apply_retention(&state_dir, &policy, Some(id), start + day * i as u32).unwrap();
+        }
+
+        // Each session was archived when the next one stopped; the archive
+        // keeps only the newest.
+        let archived = sessions_newest_first(&state_dir.join("archive/sessions"), None);
+        let names: Vec<&str> = archived.iter().map(|(name, ..)| name.as_str()).collect();
+        assert_eq!(names, ["c"]);
+
+        // Archived sessions expire max_age_days after they were archived.
+        let later = start + day * (policy.max_age_days as u32 + 4);
+        let report = apply_retention(&state_dir, &policy, Some("d"), later).unwrap();
+        assert_eq!(report.pruned_archive, 1);
+        assert!(!state_dir.join("archive/sessions/c").exists());
+        let _ = fs::remove_dir_all(&state_dir);
+    }
+}
@@ -1,9 +1,13 @@
+use crate::feature_config::CustomRoleConfig;
+use crate::role_router::MultiRouting;
 pub use crate::types::*;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub struct TaskAnalyzer {
     complexity_indicators: HashMap<TaskComplexity, Vec<String>>,
+    /// Custom roles and the lowercase keywords that call for them.
+    custom_roles: Vec<(AgentRole, Vec<String>)>,
 }
 
 impl TaskAnalyzer {
@@ -57,13 +61,26 @@ impl TaskAnalyzer {
 
         Self {
             complexity_indicators,
+            custom_roles: Vec::new(),
         }
     }
 
+    /// Adds a custom role to every task that mentions one of its keywords.
+    pub fn with_custom_roles(mut self, roles: &BTreeMap<String, CustomRoleConfig>) -> Self {
+        self.custom_roles = roles
+            .iter()
+            .map(|(name, custom)| {
+                let keywords = custom.keywords.iter().map(|k| k.to_lowercase()).collect();
+                (AgentRole::Custom(name.clone()), keywords)
+            })
+            .collect();
+        self
+    }
+
     pub fn analyze_task(&self, task_description: &str) -> Result<TaskAnalysis> {
         let complexity = self.determine_complexity(task_description);
         let task_type = self.determine_task_type(task_description);
-        let subtasks = self.extract_subtasks(task_description);
+        let subtasks = Self::extract_subtasks(task_description);
         let estimated_effort = self.estimate_effort(complexity, &subtasks);
         let required_roles = self.determine_roles(task_description, &task_type);
         let priority = self.determine_priority(task_description);
@@ -170,7 +187,10 @@ impl TaskAnalyzer {
             .unwrap_or_else(|| "analysis".to_string())
     }
 
-    fn extract_subtasks(&self, task_description: &str) -> Vec<String> {
+    /// Splits a task into its subtasks: the items of a numbered list, or
+    /// else its sentences and the clauses joined by "then", "also" or "and"
+    /// followed by a task verb. A task with one clause is returned whole.
+    pub fn extract_subtasks(task_description: &str) -> Vec<String> {
         let numbered_re = Regex::new(r"\d+\.\s+([^.]+\.?)").unwrap();
         let numbered_items: Vec<&str> = numbered_re
             .find_iter(task_description)
@@ -184,33 +204,29 @@ impl TaskAnalyzer {
                 .collect();
         }
 
-        let task_verbs_re = Regex::new(
-            r"(?:analyze|review|test|write|implement|optimize|refactor|document)\s+([^.]+\.?)",
+        // A verb after "and" starts the next clause rather than ending this one.
+        let break_re = Regex::new(
+            r"(?i)[.;!?](?:\s+|$)|,?\s*\b(?:and\s+then|then|and\s+also|also|additionally|furthermore|moreover|after\s+that)\b|,?\s*\band\s+(?P<verb>analy[sz]e|review|test|write|implement|optimi[sz]e|refactor|document|fix|debug|deploy)\b",
         )
         .unwrap();
-        let task_verbs: Vec<&str> = task_verbs_re
-            .find_iter(task_description)
-            .map(|m| m.as_str())
-            .collect();
-
-        if !task_verbs.is_empty() {
-            return task_verbs
-                .into_iter()
-                .map(|s| s.trim().to_string())
-                .collect();
+        let mut clauses = Vec::new();
+        let mut start = 0;
+        for caps in break_re.captures_iter(task_description) {
+            let brk = caps.get(0).unwrap();
+            clauses.push(&task_description[start..brk.start()]);
+            start = caps.name("verb").map_or(brk.end(), |verb| verb.start());
         }
+        clauses.push(&task_description[start..]);
 
-        let parts: Vec<&str> = Regex::new(r"\b(and|also|additionally|furthermore|moreover)\b")
-            .unwrap()
-            .split(task_description)
+        let clauses: Vec<String> = clauses
+            .into_iter()
+            .map(|clause| clause.trim_matches(|c: char| c.is_whitespace() || c == ','))
+            .filter(|clause| clause.split_whitespace().count() >= 2)
+            .map(str::to_string)
             .collect();
 
-        if parts.len() > 1 {
-            return parts
-                .into_iter()
-                .filter(|s| s.trim().len() > 10)
-                .map(|s| s.trim().to_string())
-                .collect();
+        if clauses.len() > 1 {
+            return clauses;
         }
 
         vec![task_description.trim().to_string()]
@@ -273,6 +289,14 @@ impl TaskAnalyzer {
         if text_lower.contains("optimize") && !roles.contains(&AgentRole::Optimizer) {
             roles.push(AgentRole::Optimizer);
         }
+        for (role, keywords) in &self.custom_roles {
+            if keywords
+                .iter()
+                .any(|keyword| text_lower.contains(keyword.as_str()))
+            {
+                roles.push(role.clone());
+            }
+        }
 
         roles.sort();
         roles.dedup();
@@ -343,6 +367,24 @@ impl TeamOptimizer {
             },
         );
 
+        role_capabilities.insert(
+            AgentRole::Extractor,
+            RoleCapabilities {
+                efficiency: 1.1,
+                cost_per_hour: 3000,
+                max_concurrent_tasks: 4,
+            },
+        );
+
+        role_capabilities.insert(
+            AgentRole::Writer,
+            RoleCapabilities {
+                efficiency: 1.0,
+                cost_per_hour: 4500,
+                max_concurrent_tasks: 3,
+            },
+        );
+
         role_capabilities.insert(
             AgentRole::Reviewer,
             RoleCapabilities {
@@ -395,9 +437,62 @@ impl TeamOptimizer {
         }
     }
 
+    /// Staffs custom roles with their configured capabilities.
+    pub fn with_custom_roles(mut self, roles: &BTreeMap<String, CustomRoleConfig>) -> Self {
+        for (name, custom) in roles {
+            let capabilities = &custom.capabilities;
+            self.role_capabilities.insert(
+                AgentRole::Custom(name.clone()),
+                RoleCapabilities {
+                    efficiency: capabilities.efficiency,
+                    cost_per_hour: capabilities.cost_per_hour,
+                    max_concurrent_tasks: capabilities.max_concurrent_tasks,
+                },
+            );
+        }
+        self
+    }
+
     pub fn optimize_team(&self, task_analysis: &TaskAnalysis) -> Result<TeamComposition> {
-        let team_size = self.determine_team_size(task_analysis);
-        let roles = self.allocate_roles(task_analysis, team_size)?;
+        let staffing = task_analysis
+            .required_roles
+            .iter()
+            .map(|role| (role.clone(), Vec::new()))
+            .collect();
+        self.compose_team(task_analysis, staffing)
+    }
+
+    /// Staffs the roles a task was routed to, in clause order, instead of
+    /// the roles task analysis guessed from keywords. Each agent's primary
+    /// tasks are the clauses routed to its role.
+    ///
+    /// Roles no agent can fill, such as `General`, are skipped; if none is
+    /// left this is [`TeamOptimizer::optimize_team`].
+    pub fn optimize_routed_team(
+        &self,
+        task_analysis: &TaskAnalysis,
+        routing: &MultiRouting,
+    ) -> Result<TeamComposition> {
+        let staffing: Vec<(AgentRole, Vec<String>)> = routing
+            .roles
+            .iter()
+            .filter(|role| self.role_capabilities.contains_key(*role))
+            .map(|role| (role.clone(), routing.clauses_for(role)))
+            .collect();
+        if staffing.is_empty() {
+            return self.optimize_team(task_analysis);
+        }
+        self.compose_team(task_analysis, staffing)
+    }
+
+    /// Builds a team around `staffing`, each role with its primary tasks.
+    fn compose_team(
+        &self,
+        task_analysis: &TaskAnalysis,
+        staffing: Vec<(AgentRole, Vec<String>)>,
+    ) -> Result<TeamComposition> {
+        let team_size = self.determine_team_size(task_analysis, staffing.len());
+        let roles = self.allocate_roles(staffing, team_size)?;
         let workload_distribution = self.distribute_workload(task_analysis, &roles)?;
         let completion_time = self.estimate_completion_time(task_analysis, &roles);
         let cost = self.estimate_cost(task_analysis, &roles, completion_time);
@@ -414,7 +509,7 @@ impl TeamOptimizer {
         })
     }
 
-    fn determine_team_size(&self, task_analysis: &TaskAnalysis) -> usize {
+    fn determine_team_size(&self, task_analysis: &TaskAnalysis, required_count: usize) -> usize {
         let base_sizes: HashMap<TaskComplexity, usize> = vec![
             (TaskComplexity::Simple, 1),
             (TaskComplexity::Moderate, 2),
@@ -425,7 +520,6 @@ impl TeamOptimizer {
         .collect();
 
         let base_size = *base_sizes.get(&task_analysis.complexity).unwrap_or(&1);
-        let required_count = task_analysis.required_roles.len();
         let mut team_size = base_size.max(required_count);
 
         if task_analysis.subtasks.len() > 3 {
@@ -441,22 +535,20 @@ impl TeamOptimizer {
 
     fn allocate_roles(
         &self,
-        task_analysis: &TaskAnalysis,
+        mut staffing: Vec<(AgentRole, Vec<String>)>,
         team_size: usize,
     ) -> Result<Vec<RoleAllocation>> {
-        let mut required_roles = task_analysis.required_roles.clone();
-
-        while required_roles.len() < team_size {
-            required_roles.push(AgentRole::Analyzer);
+        while staffing.len() < team_size {
+            staffing.push((AgentRole::Analyzer, Vec::new()));
         }
 
-        required_roles.truncate(team_size);
+        staffing.truncate(team_size);
 
         let mut allocations = Vec::new();
-        for (i, role) in required_roles.iter().enumerate() {
+        for (i, (role, primary_tasks)) in staffing.into_iter().enumerate() {
             let capabilities = self
                 .role_capabilities
-                .get(role)
+                .get(&role)
                 .ok_or_else(|| format!("Unknown role: {:?}", role))?;
 
             allocations.push(RoleAllocation {
@@ -465,7 +557,7 @@ impl TeamOptimizer {
                 efficiency: capabilities.efficiency,
                 cost_per_hour: capabilities.cost_per_hour,
                 max_concurrent_tasks: capabilities.max_concurrent_tasks,
-                primary_tasks: vec![],
+                primary_tasks,
             });
         }
 
@@ -2,22 +2,26 @@ use crate::enhanced_monitor::TrajectoryCompression;
 use crate::security::compile_regex_with_timeout;
 use crate::types::{CompressedTrajectory, SummaryGroup, TrajectoryEntry, TrajectoryLog};
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::Duration;
 
 /// Configuration for trajectory compression behavior.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct TrajectoryCompressorConfig {
     /// Minimum impact score to preserve entry (0.0 to 1.0).
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub preserve_threshold: f64,
     /// Maximum summaries to keep when grouping repeated actions.
+    #[schemars(range(min = 1))]
     pub max_summaries: usize,
     /// Regex patterns for superseded content detection.
     pub superseded_patterns: Vec<String>,
     /// Whether to auto-filter redundant entries.
     pub filter_redundant: bool,
     /// Token budget for compressed trajectory.
+    #[schemars(range(min = 1))]
     pub max_tokens: usize,
 }
 
@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -19,7 +20,10 @@ impl TaskComplexity {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Longest name a custom role may have.
+pub const MAX_ROLE_NAME_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum AgentRole {
     Extractor,
     Analyzer,
@@ -31,10 +35,26 @@ pub enum AgentRole {
     Optimizer,
     Specialist,
     General,
+    /// A role declared under `custom_roles` in the config, by name.
+    Custom(String),
 }
 
 impl AgentRole {
-    pub fn as_str(&self) -> &'static str {
+    /// Every built-in role.
+    pub const BUILTIN: [AgentRole; 10] = [
+        AgentRole::Extractor,
+        AgentRole::Analyzer,
+        AgentRole::Writer,
+        AgentRole::Reviewer,
+        AgentRole::Synthesizer,
+        AgentRole::Tester,
+        AgentRole::Documenter,
+        AgentRole::Optimizer,
+        AgentRole::Specialist,
+        AgentRole::General,
+    ];
+
+    pub fn as_str(&self) -> &str {
         match self {
             AgentRole::Extractor => "extractor",
             AgentRole::Analyzer => "analyzer",
@@ -46,10 +66,43 @@ impl AgentRole {
             AgentRole::Optimizer => "optimizer",
             AgentRole::Specialist => "specialist",
             AgentRole::General => "general",
+            AgentRole::Custom(name) => name,
+        }
+    }
+
+    /// Parses a built-in role, or takes any other well-formed name as a
+    /// custom role; see [`is_valid_role_name`].
+    ///
+    /// Whether a custom role is declared is up to the config; see
+    /// [`crate::config::SwarmToolsConfig::resolve_role`].
+    pub fn from_name(name: &str) -> std::result::Result<Self, String> {
+        if let Ok(role) = name.parse() {
+            return Ok(role);
+        }
+        let name = name.to_lowercase();
+        if is_valid_role_name(&name) {
+            Ok(AgentRole::Custom(name))
+        } else {
+            Err(format!("Invalid agent role name: {}", name))
         }
     }
+
+    pub fn is_custom(&self) -> bool {
+        matches!(self, AgentRole::Custom(_))
+    }
+}
+
+/// Whether `name` can name a custom role: lowercase ASCII letters, digits,
+/// `-` and `_`, starting with a letter, at most [`MAX_ROLE_NAME_LEN`] long.
+pub fn is_valid_role_name(name: &str) -> bool {
+    name.len() <= MAX_ROLE_NAME_LEN
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
 }
 
+/// Only the built-in roles; custom roles need the config to be recognised.
 impl std::str::FromStr for AgentRole {
     type Err = String;
 
@@ -70,6 +123,44 @@ impl std::str::FromStr for AgentRole {
     }
 }
 
+impl std::fmt::Display for AgentRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Built-in roles keep their variant names (`"Reviewer"`); custom roles are
+/// their own name.
+impl Serialize for AgentRole {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            AgentRole::Extractor => serializer.serialize_str("Extractor"),
+            AgentRole::Analyzer => serializer.serialize_str("Analyzer"),
+            AgentRole::Writer => serializer.serialize_str("Writer"),
+            AgentRole::Reviewer => serializer.serialize_str("Reviewer"),
+            AgentRole::Synthesizer => serializer.serialize_str("Synthesizer"),
+            AgentRole::Tester => serializer.serialize_str("Tester"),
+            AgentRole::Documenter => serializer.serialize_str("Documenter"),
+            AgentRole::Optimizer => serializer.serialize_str("Optimizer"),
+            AgentRole::Specialist => serializer.serialize_str("Specialist"),
+            AgentRole::General => serializer.serialize_str("General"),
+            AgentRole::Custom(name) => serializer.serialize_str(name),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AgentRole {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        AgentRole::from_name(&name).map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum ExecutionMode {
     Sequential,
@@ -82,6 +173,10 @@ pub enum LoopType {
     ExactLoop,
     SemanticLoop,
     StateOscillation,
+    /// The same sequence of actions repeated back to back.
+    ActionCycle,
+    /// One tool called with the same arguments too often in a short time.
+    ToolCallLoop,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -157,6 +252,23 @@ pub struct LoopDetection {
     pub loop_count: usize,
     pub prompt_hash: String,
     pub timestamp: String,
+    /// The repeating steps, for cycle and oscillation detections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycle: Option<LoopCycle>,
+}
+
+/// A run of steps that repeats back to back at the end of a stream.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LoopCycle {
+    /// Steps in one repetition.
+    pub period: usize,
+    /// One repetition, in order and ending with the latest step; each step
+    /// is the one seen most often at its position.
+    pub body: Vec<String>,
+    /// Back-to-back repetitions, the latest included.
+    pub repeats: usize,
+    /// Steps in those repetitions that differ from `body`.
+    pub mismatches: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -201,14 +313,41 @@ pub struct InterventionResult {
     pub action: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Core swarm limits and loop detection thresholds.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct SwarmConfig {
+    /// Maximum number of agents running at the same time.
+    #[schemars(range(min = 1))]
     pub max_parallel_agents: usize,
+    /// Total context window budget in tokens.
+    #[schemars(range(min = 1))]
     pub context_budget: usize,
+    /// Fraction of the context budget at which compression kicks in.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub context_threshold: f64,
+    /// Identical prompts in a row before an exact loop is reported.
+    #[schemars(range(min = 1))]
     pub loop_exact_threshold: usize,
+    /// Similar prompts in recent history before a semantic loop is reported.
+    #[schemars(range(min = 1))]
     pub loop_semantic_threshold: usize,
+    /// Repetitions of a cycle of states, e.g. analyzing, writing,
+    /// analyzing, writing, before a state oscillation is reported.
+    #[schemars(range(min = 1))]
     pub loop_state_oscillation_threshold: usize,
+    /// Back-to-back repetitions of a cycle of actions, e.g. read, edit,
+    /// failing test, before an action cycle is reported.
+    #[schemars(range(min = 2))]
+    pub loop_cycle_repeats: usize,
+    /// Longest cycle, in steps, looked for in actions and states.
+    #[schemars(range(min = 2))]
+    pub loop_cycle_max_period: usize,
+    /// Fraction of the steps in an action cycle's repetitions that may
+    /// differ from it, so one noisy step does not hide a loop.
+    #[schemars(range(min = 0.0, max = 0.5))]
+    pub loop_cycle_tolerance: f64,
+    /// Similarity above which two prompts count as the same.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub semantic_similarity_threshold: f64,
 }
 
@@ -221,6 +360,9 @@ impl Default for SwarmConfig {
             loop_exact_threshold: 3,
             loop_semantic_threshold: 5,
             loop_state_oscillation_threshold: 3,
+            loop_cycle_repeats: 2,
+            loop_cycle_max_period: 6,
+            loop_cycle_tolerance: 0.2,
             semantic_similarity_threshold: 0.95,
         }
     }
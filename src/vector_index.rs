@@ -0,0 +1,530 @@
+//! Nearest-neighbour search over embeddings of everything an agent or the
+//! swarm has done.
+//!
+//! A [`VectorIndex`] scans every vector while it is small. Past
+//! `flat_limit` entries it clusters them with k-means into an inverted file
+//! (IVF) and only scans the `nprobe` clusters nearest the query, so lookups
+//! stay cheap however long the history grows. Indexes are saved in a compact
+//! binary form under `<session_dir>/vector-index/`, tagged with the embedder
+//! that made their vectors; loading one under another model, or a file in
+//! another format, starts it afresh.
+
+use crate::embedder::cosine_similarity;
+use crate::feature_config::VectorIndexConfig;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// k-means rounds when building the inverted file.
+const KMEANS_ITERATIONS: usize = 8;
+
+/// First bytes of a saved index; the digit is the format version.
+const MAGIC: &[u8; 8] = b"SWVIDX01";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexEntry {
+    /// Caller-chosen identifier, e.g. a prompt hash; need not be unique.
+    pub id: String,
+    /// What the vector stands for, e.g. the agent that sent the prompt.
+    pub label: String,
+    pub vector: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Neighbor {
+    pub id: String,
+    pub label: String,
+    pub similarity: f32,
+}
+
+/// Clusters of entry positions around k-means centroids.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct InvertedFile {
+    centroids: Vec<Vec<f32>>,
+    lists: Vec<Vec<usize>>,
+    /// Entries when the clusters were built.
+    built_for: usize,
+    /// Entries added since; the clusters are rebuilt once as many were added
+    /// as they were built for, whether or not old ones were dropped meanwhile.
+    added: usize,
+}
+
+impl InvertedFile {
+    fn build(entries: &[IndexEntry]) -> Self {
+        let clusters = (entries.len() as f64).sqrt().ceil() as usize;
+        // Spread the initial centroids over the history; deterministic, so a
+        // rebuilt index answers the same way.
+        let mut centroids: Vec<Vec<f32>> = (0..clusters)
+            .map(|i| entries[i * entries.len() / clusters].vector.clone())
+            .collect();
+        let mut lists = vec![Vec::new(); clusters];
+
+        for _ in 0..KMEANS_ITERATIONS {
+            lists = vec![Vec::new(); clusters];
+            for (position, entry) in entries.iter().enumerate() {
+                lists[nearest(&centroids, &entry.vector)].push(position);
+            }
+            for (centroid, list) in centroids.iter_mut().zip(&lists) {
+                if list.is_empty() {
+                    continue;
+                }
+                let mut mean = vec![0.0f32; centroid.len()];
+                for &position in list {
+                    for (sum, value) in mean.iter_mut().zip(&entries[position].vector) {
+                        *sum += value;
+                    }
+                }
+                for value in &mut mean {
+                    *value /= list.len() as f32;
+                }
+                *centroid = mean;
+            }
+        }
+
+        Self {
+            centroids,
+            lists,
+            built_for: entries.len(),
+            added: 0,
+        }
+    }
+
+    fn add(&mut self, position: usize, vector: &[f32]) {
+        let cluster = nearest(&self.centroids, vector);
+        self.lists[cluster].push(position);
+        self.added += 1;
+    }
+
+    /// Forgets the first `count` entries and moves the rest down to match.
+    fn drop_front(&mut self, count: usize) {
+        for list in &mut self.lists {
+            list.retain(|&position| position >= count);
+            for position in list.iter_mut() {
+                *position -= count;
+            }
+        }
+    }
+
+    /// Entry positions in the `nprobe` clusters closest to `query`.
+    fn candidates(&self, query: &[f32], nprobe: usize) -> Vec<usize> {
+        let mut clusters: Vec<(usize, f32)> = self
+            .centroids
+            .iter()
+            .enumerate()
+            .map(|(i, centroid)| (i, cosine_similarity(query, centroid)))
+            .collect();
+        clusters.sort_by(|a, b| b.1.total_cmp(&a.1));
+        clusters
+            .iter()
+            .take(nprobe.max(1))
+            .flat_map(|&(i, _)| self.lists[i].iter().copied())
+            .collect()
+    }
+}
+
+fn nearest(centroids: &[Vec<f32>], vector: &[f32]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .map(|(i, centroid)| (i, cosine_similarity(vector, centroid)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(i, _)| i)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorIndex {
+    /// [`crate::embedder::Embedder::name`] of the vectors' embedder.
+    model: String,
+    entries: Vec<IndexEntry>,
+    ivf: Option<InvertedFile>,
+    config: VectorIndexConfig,
+    path: Option<PathBuf>,
+}
+
+impl VectorIndex {
+    /// An empty index for vectors from the embedder named `model`.
+    pub fn new(config: VectorIndexConfig, model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            entries: Vec::new(),
+            ivf: None,
+            config,
+            path: None,
+        }
+    }
+
+    /// Loads the index saved at `path` for `model`'s vectors. A missing or
+    /// unreadable file, or one built by another model, gives an empty index
+    /// that [`VectorIndex::save`] writes there.
+    pub fn load(path: impl Into<PathBuf>, config: VectorIndexConfig, model: &str) -> Self {
+        let path = path.into();
+        let mut index = fs::read(&path)
+            .ok()
+            .and_then(|bytes| decode(&bytes))
+            .filter(|index| index.model == model)
+            .unwrap_or_else(|| Self::new(config.clone(), model));
+        index.config = config;
+        index.path = Some(path);
+        index
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Name of the embedder the vectors came from.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether searches go through the inverted file rather than a full scan.
+    pub fn is_clustered(&self) -> bool {
+        self.ivf.is_some()
+    }
+
+    /// Adds a vector, dropping the oldest entries past `max_entries`.
+    pub fn insert(&mut self, id: impl Into<String>, label: impl Into<String>, vector: Vec<f32>) {
+        self.entries.push(IndexEntry {
+            id: id.into(),
+            label: label.into(),
+            vector,
+        });
+
+        let max_entries = self.config.max_entries.max(1);
+        if self.entries.len() > max_entries {
+            let excess = self.entries.len() - max_entries;
+            self.entries.drain(..excess);
+            if let Some(ivf) = &mut self.ivf {
+                ivf.drop_front(excess);
+            }
+        }
+
+        let position = self.entries.len() - 1;
+        match &mut self.ivf {
+            Some(ivf) if ivf.added < ivf.built_for => {
+                ivf.add(position, &self.entries[position].vector)
+            }
+            _ if self.entries.len() > self.config.flat_limit => {
+                self.ivf = Some(InvertedFile::build(&self.entries));
+            }
+            _ => self.ivf = None,
+        }
+    }
+
+    /// Up to `k` entries at least `min_similarity` similar to `query`, most
+    /// similar first.
+    pub fn search(&self, query: &[f32], k: usize, min_similarity: f32) -> Vec<Neighbor> {
+        let candidates: Box<dyn Iterator<Item = usize>> = match &self.ivf {
+            Some(ivf) => Box::new(ivf.candidates(query, self.config.nprobe).into_iter()),
+            None => Box::new(0..self.entries.len()),
+        };
+        let mut neighbors: Vec<Neighbor> = candidates
+            .filter_map(|position| {
+                let entry = &self.entries[position];
+                let similarity = cosine_similarity(query, &entry.vector);
+                (similarity >= min_similarity).then(|| Neighbor {
+                    id: entry.id.clone(),
+                    label: entry.label.clone(),
+                    similarity,
+                })
+            })
+            .collect();
+        neighbors.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        neighbors.truncate(k);
+        neighbors
+    }
+
+    /// Writes the index back to the file it was loaded from, through a
+    /// temporary file so a reader never sees half of it.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Err("vector index was not loaded from a file".into());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp = path.with_extension(format!("{}.part", std::process::id()));
+        fs::write(&temp, encode(self))?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
+
+/// Saved form: [`MAGIC`], the model, the entries, then the inverted file if
+/// any. Integers are little-endian `u64`, strings and lists are prefixed with
+/// their length and vectors are raw little-endian `f32`s.
+fn encode(index: &VectorIndex) -> Vec<u8> {
+    let vector_bytes: usize = index.entries.iter().map(|e| e.vector.len() * 4).sum();
+    let mut out = Vec::with_capacity(MAGIC.len() + vector_bytes + index.entries.len() * 96);
+    out.extend_from_slice(MAGIC);
+    put_str(&mut out, &index.model);
+    put_u64(&mut out, index.entries.len());
+    for entry in &index.entries {
+        put_str(&mut out, &entry.id);
+        put_str(&mut out, &entry.label);
+        put_vector(&mut out, &entry.vector);
+    }
+    match &index.ivf {
+        None => out.push(0),
+        Some(ivf) => {
+            out.push(1);
+            put_u64(&mut out, ivf.built_for);
+            put_u64(&mut out, ivf.added);
+            put_u64(&mut out, ivf.centroids.len());
+            for (centroid, list) in ivf.centroids.iter().zip(&ivf.lists) {
+                put_vector(&mut out, centroid);
+                put_u64(&mut out, list.len());
+                for &position in list {
+                    put_u64(&mut out, position);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn put_u64(out: &mut Vec<u8>, value: usize) {
+    out.extend_from_slice(&(value as u64).to_le_bytes());
+}
+
+fn put_str(out: &mut Vec<u8>, value: &str) {
+    put_u64(out, value.len());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn put_vector(out: &mut Vec<u8>, vector: &[f32]) {
+    put_u64(out, vector.len());
+    for value in vector {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// Reads what [`encode`] wrote; `None` for anything else, including a
+/// truncated file or one whose positions point past its entries.
+fn decode(bytes: &[u8]) -> Option<VectorIndex> {
+    let mut reader = Reader(bytes.strip_prefix(MAGIC.as_slice())?);
+    let model = reader.string()?;
+    let count = reader.count()?;
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        entries.push(IndexEntry {
+            id: reader.string()?,
+            label: reader.string()?,
+            vector: reader.vector()?,
+        });
+    }
+    let ivf = match reader.take(1)?[0] {
+        0 => None,
+        1 => {
+            let built_for = reader.u64()?;
+            let added = reader.u64()?;
+            let clusters = reader.count()?;
+            let mut centroids = Vec::with_capacity(clusters);
+            let mut lists = Vec::with_capacity(clusters);
+            for _ in 0..clusters {
+                centroids.push(reader.vector()?);
+                let list = (0..reader.count()?)
+                    .map(|_| reader.u64().filter(|&position| position < count))
+                    .collect::<Option<Vec<usize>>>()?;
+                lists.push(list);
+            }
+            Some(InvertedFile {
+                centroids,
+                lists,
+                built_for,
+                added,
+            })
+        }
+        _ => return None,
+    };
+    reader.0.is_empty().then(|| VectorIndex {
+        model,
+        entries,
+        ivf,
+        config: VectorIndexConfig::default(),
+        path: None,
+    })
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn u64(&mut self) -> Option<usize> {
+        let bytes = self.take(8)?.try_into().ok()?;
+        usize::try_from(u64::from_le_bytes(bytes)).ok()
+    }
+
+    /// A length, checked against what is left so a corrupt one can't make
+    /// the caller reserve unbounded memory.
+    fn count(&mut self) -> Option<usize> {
+        self.u64().filter(|&len| len <= self.0.len())
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.count()?;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn vector(&mut self) -> Option<Vec<f32>> {
+        let len = self.count()?;
+        let bytes = self.take(len.checked_mul(4)?)?;
+        Some(
+            bytes
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit(dimension: usize, hot: usize) -> Vec<f32> {
+        let mut vector = vec![0.0; dimension];
+        vector[hot % dimension] = 1.0;
+        vector
+    }
+
+    #[test]
+    fn test_flat_search_applies_cutoff() {
+        let mut index = VectorIndex::new(VectorIndexConfig::default(), "stub");
+        index.insert("a", "agent1", vec![1.0, 0.0]);
+        index.insert("b", "agent1", vec![0.8, 0.6]);
+        index.insert("c", "agent2", vec![0.0, 1.0]);
+
+        let neighbors = index.search(&[1.0, 0.0], 5, 0.5);
+        let ids: Vec<&str> = neighbors.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert!(!index.is_clustered());
+    }
+
+    #[test]
+    fn test_clusters_past_flat_limit() {
+        let config = VectorIndexConfig {
+            flat_limit: 16,
+            nprobe: 2,
+            ..Default::default()
+        };
+        let mut index = VectorIndex::new(config, "stub");
+        for i in 0..64 {
+            index.insert(i.to_string(), "agent", unit(8, i));
+        }
+        assert!(index.is_clustered());
+
+        let neighbors = index.search(&unit(8, 3), 100, 0.99);
+        assert_eq!(neighbors.len(), 8);
+        assert!(neighbors
+            .iter()
+            .all(|n| n.id.parse::<usize>().unwrap() % 8 == 3));
+    }
+
+    #[test]
+    fn test_keeps_clusters_past_max_entries() {
+        let config = VectorIndexConfig {
+            flat_limit: 16,
+            nprobe: 8,
+            max_entries: 24,
+            ..Default::default()
+        };
+        let mut index = VectorIndex::new(config, "stub");
+        for i in 0..17 {
+            index.insert(i.to_string(), "agent", unit(8, i));
+        }
+        let built = index.ivf.clone().unwrap();
+        for i in 17..30 {
+            index.insert(i.to_string(), "agent", unit(8, i));
+        }
+
+        // Dropping the oldest entries shifts positions without re-clustering.
+        let ivf = index.ivf.as_ref().unwrap();
+        assert_eq!(ivf.centroids, built.centroids);
+        assert_eq!(index.len(), 24);
+        let mut ids: Vec<usize> = index
+            .search(&unit(8, 3), 100, 0.99)
+            .iter()
+            .map(|n| n.id.parse().unwrap())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, [11, 19, 27]);
+
+        // Clusters are rebuilt once as many entries were added as they hold.
+        for i in 30..35 {
+            index.insert(i.to_string(), "agent", unit(8, i));
+        }
+        assert_eq!(index.ivf.as_ref().unwrap().built_for, 24);
+    }
+
+    #[test]
+    fn test_clustered_index_round_trips() {
+        let path = std::env::temp_dir().join(format!(
+            "swarm_tools_vector_index_round_trip_{}/index.bin",
+            std::process::id()
+        ));
+        let config = VectorIndexConfig {
+            flat_limit: 4,
+            ..Default::default()
+        };
+        let mut index = VectorIndex::load(&path, config.clone(), "stub");
+        for i in 0..10 {
+            index.insert(i.to_string(), format!("agent{}", i % 2), unit(4, i));
+        }
+        index.save().unwrap();
+
+        let reloaded = VectorIndex::load(&path, config.clone(), "stub");
+        assert!(reloaded.is_clustered());
+        assert_eq!(reloaded, index);
+
+        // A file in any other format starts afresh.
+        fs::write(&path, "{\"model\":\"stub\",\"entries\":[]}").unwrap();
+        assert!(VectorIndex::load(&path, config, "stub").is_empty());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_persists_and_drops_oldest() {
+        let path = std::env::temp_dir().join(format!(
+            "swarm_tools_vector_index_{}/index.bin",
+            std::process::id()
+        ));
+        let config = VectorIndexConfig {
+            max_entries: 2,
+            ..Default::default()
+        };
+        let mut index = VectorIndex::load(&path, config.clone(), "stub");
+        for i in 0..3 {
+            index.insert(i.to_string(), "agent", unit(4, i));
+        }
+        index.save().unwrap();
+
+        let reloaded = VectorIndex::load(&path, config.clone(), "stub");
+        assert_eq!(reloaded.len(), 2);
+        assert!(reloaded.search(&unit(4, 0), 1, 0.5).is_empty());
+        assert_eq!(reloaded.search(&unit(4, 2), 1, 0.5)[0].id, "2");
+
+        // Another model's vectors are never mixed in.
+        let other = VectorIndex::load(&path, config, "onnx-e5-small-v2");
+        assert!(other.is_empty());
+        assert_eq!(other.model(), "onnx-e5-small-v2");
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
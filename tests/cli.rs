@@ -0,0 +1,278 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+use swarm_tools::cli::{self, Workspace};
+use swarm_tools::model_store::MODEL_DIR_ENV_VAR;
+use swarm_tools::savings_ledger::{LedgerEntry, SavingsLedger};
+use swarm_tools::session_state::{save_session, session_dir, SessionRecord, SessionSummary};
+
+fn unique_dir(test_name: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_micros();
+    let dir = std::env::temp_dir().join(format!("swarm_tools_{}_{}", test_name, timestamp));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs the binary against `dir`, isolated from the user's own config.
+fn swarm_tools(dir: &Path, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_swarm-tools"))
+        .args(args)
+        .current_dir(dir)
+        .env("XDG_CONFIG_HOME", dir)
+        .env(MODEL_DIR_ENV_VAR, dir.join("models"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn record_session(state_dir: &Path, session_id: &str, tool_calls: usize) {
+    save_session(
+        &session_dir(state_dir, session_id),
+        &SessionRecord {
+            session_id: session_id.to_string(),
+            summary: Some(SessionSummary {
+                tool_calls,
+                failed_tool_calls: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_stats_totals_sessions_and_savings() {
+    let dir = unique_dir("cli_stats");
+    let workspace = Workspace::load(&dir, None).unwrap();
+    record_session(&workspace.state_dir, "a", 3);
+    record_session(&workspace.state_dir, "b", 4);
+    SavingsLedger::new(&workspace.state_dir)
+        .record(&LedgerEntry {
+            timestamp: "2025-01-01T00:00:00Z".to_string(),
+            session_id: "a".to_string(),
+            agent_id: "a".to_string(),
+            tool_name: "read_file".to_string(),
+            role: "extractor".to_string(),
+            decision: "modify_args".to_string(),
+            token_savings_estimate: Some(900),
+        })
+        .unwrap();
+
+    let report = cli::stats(&workspace);
+
+    assert_eq!(report.sessions, 2);
+    assert_eq!(report.activity.tool_calls, 7);
+    assert_eq!(report.activity.failed_tool_calls, 2);
+    assert_eq!(report.savings.modified, 1);
+    assert_eq!(report.savings.tokens_saved, 900);
+}
+
+#[test]
+fn test_reset_keeps_project_config() {
+    let dir = unique_dir("cli_reset");
+    let state_dir = dir.join(".claude/swarm-tools");
+    record_session(&state_dir, "a", 1);
+    record_session(&state_dir, "b", 1);
+    fs::write(state_dir.join("mcp_savings.jsonl"), "").unwrap();
+    fs::write(state_dir.join("config_override.json"), "{}").unwrap();
+
+    let output = swarm_tools(&dir, &["reset", "--session", "a", "--yes"], "");
+    assert!(output.status.success());
+    assert!(!state_dir.join("sessions/a").exists());
+    assert!(state_dir.join("sessions/b").exists());
+
+    // Without --yes nothing is deleted unless confirmed
+    let output = swarm_tools(&dir, &["reset"], "n\n");
+    assert!(output.status.success());
+    assert!(state_dir.join("sessions/b").exists());
+
+    let output = swarm_tools(&dir, &["reset", "--yes"], "");
+    assert!(output.status.success());
+    assert!(!state_dir.join("sessions").exists());
+    assert!(!state_dir.join("mcp_savings.jsonl").exists());
+    assert!(state_dir.join("config_override.json").exists());
+}
+
+#[test]
+fn test_route_and_plan_print_json() {
+    let dir = unique_dir("cli_route");
+
+    let output = swarm_tools(
+        &dir,
+        &["route", "--json", "Review this pull request for bugs"],
+        "",
+    );
+    assert!(output.status.success());
+    let routing: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(routing["role"].is_string());
+
+    let output = swarm_tools(
+        &dir,
+        &[
+            "plan",
+            "--json",
+            "--role",
+            "tester",
+            "1. Read the parser\n2. Add a failing test\n3. Fix the bug",
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    let plan: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(!plan["steps"].as_array().unwrap().is_empty());
+
+    let output = swarm_tools(&dir, &["plan", "--role", "wizard", "anything"], "");
+    // Usage errors must not look like a blocking hook error (exit 2)
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown agent role"));
+}
+
+#[test]
+fn test_roles_label_export_and_eval() {
+    let dir = unique_dir("cli_roles");
+
+    for (role, task) in [
+        ("optimizer", "apply the terraform plan"),
+        ("writer", "draft the schema migration"),
+    ] {
+        let output = swarm_tools(&dir, &["roles", "label", role, task], "");
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    let output = swarm_tools(&dir, &["roles", "export"], "");
+    assert!(output.status.success());
+    let exported = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        exported,
+        "{\"prompt\":\"apply the terraform plan\",\"role\":\"optimizer\"}\n\
+         {\"prompt\":\"draft the schema migration\",\"role\":\"writer\"}\n"
+    );
+
+    let labelled = dir.join("labelled.jsonl");
+    fs::write(
+        &labelled,
+        "{\"prompt\": \"Show me the git diff for the recent changes\", \"role\": \"extractor\"}\n\
+         {\"prompt\": \"zzz qqq\", \"role\": \"tester\"}\n",
+    )
+    .unwrap();
+    let output = swarm_tools(
+        &dir,
+        &["roles", "eval", "--json", labelled.to_str().unwrap()],
+        "",
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let evaluation: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(evaluation["total"], json!(2));
+    assert_eq!(evaluation["correct"], json!(1));
+    assert_eq!(evaluation["accuracy"], json!(0.5));
+
+    let output = swarm_tools(&dir, &["roles", "eval", labelled.to_str().unwrap()], "");
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.starts_with("Accuracy: 50.0% (1/2"), "{}", report);
+
+    fs::write(&labelled, "not json\n").unwrap();
+    let output = swarm_tools(&dir, &["roles", "eval", labelled.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("labelled.jsonl:1"));
+}
+
+#[test]
+fn test_hook_rejects_mismatched_event() {
+    let dir = unique_dir("cli_hook_event");
+    let payload = json!({
+        "session_id": "session-1",
+        "cwd": dir,
+        "hook_event_name": "Stop",
+        "stop_hook_active": false,
+    });
+
+    let output = swarm_tools(&dir, &["hook", "pre-tool-use"], &payload.to_string());
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected a PreToolUse event"));
+}
+
+#[test]
+fn test_doctor_reports_checks_as_json() {
+    let dir = unique_dir("cli_doctor");
+    fs::create_dir_all(dir.join("hooks")).unwrap();
+    fs::create_dir_all(dir.join("bin")).unwrap();
+    fs::copy(
+        env!("CARGO_BIN_EXE_swarm-tools"),
+        dir.join("bin/swarm-tools"),
+    )
+    .unwrap();
+    let hooks = json!({"hooks": {"Stop": [{"hooks": [{
+        "type": "command",
+        "command": "${CLAUDE_PLUGIN_ROOT}/bin/swarm-tools hook stop",
+    }]}]}});
+    fs::write(dir.join("hooks/hooks.json"), hooks.to_string()).unwrap();
+
+    let output = swarm_tools(
+        &dir,
+        &["--json", "doctor", "--hooks-file", "hooks/hooks.json"],
+        "",
+    );
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let status = |name: &str| {
+        report["checks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|check| check["name"] == name)
+            .map(|check| check["status"].clone())
+            .unwrap()
+    };
+
+    // No model files in an empty project: the semantic build fails that check.
+    assert_eq!(report["features"]["semantic"], cfg!(feature = "semantic"));
+    assert_eq!(output.status.code() == Some(1), cfg!(feature = "semantic"));
+    assert_eq!(status("config"), "ok");
+    assert_eq!(status("state_dir"), "ok");
+    assert_eq!(status("hooks"), "ok");
+}
+
+#[test]
+fn test_models_offline_never_fetches() {
+    let dir = unique_dir("cli_models");
+    let state_dir = dir.join(".claude/swarm-tools");
+    fs::create_dir_all(&state_dir).unwrap();
+    fs::write(
+        state_dir.join("config_override.json"),
+        json!({"model_store": {"offline": true}}).to_string(),
+    )
+    .unwrap();
+
+    let output = swarm_tools(&dir, &["--json", "models", "verify"], "");
+    assert_eq!(output.status.code(), Some(1));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["source"], "env");
+    assert_eq!(report["offline"], true);
+    assert!(report["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|file| file["state"] == "missing"));
+
+    let output = swarm_tools(&dir, &["models", "fetch"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Offline mode is on"));
+    assert!(!dir.join("models").exists());
+}
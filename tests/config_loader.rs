@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use swarm_tools::config_loader::{ConfigError, ConfigLoader, ConfigSourceKind};
+
+fn unique_dir(test_name: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_micros();
+    let dir = std::env::temp_dir().join(format!("swarm_tools_{}_{}", test_name, timestamp));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &PathBuf, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_user_file_preset_takes_effect() {
+    let dir = unique_dir("user_preset");
+    let user_path = dir.join("config.json");
+    fs::copy("config_examples/large_scale.json", &user_path).unwrap();
+
+    let loaded = ConfigLoader::new()
+        .with_user_path(Some(user_path))
+        .with_project_dir(dir.join("project"))
+        .with_env_vars(Vec::<(String, String)>::new())
+        .load()
+        .unwrap();
+
+    assert_eq!(loaded.config.model_tiering.simple_haiku_threshold, 800);
+    assert!(loaded.config.self_healing.auto_prune_enabled);
+    assert_eq!(loaded.config.self_healing.min_active_agents, 3);
+    // Fields the preset omits keep their defaults
+    assert_eq!(loaded.config.self_healing.prune_safety_margin, 0.1);
+    // Role filters merge key by key
+    let filters = loaded
+        .config
+        .mcp_routing
+        .role_tool_filters
+        .as_ref()
+        .unwrap();
+    assert!(filters["writer"].contains(&"Bash".to_string()));
+    assert!(filters.contains_key("tester"));
+
+    let tierer = loaded.config.model_tierer();
+    assert_eq!(tierer.get_thresholds(), (800, 4000));
+}
+
+#[test]
+fn test_layer_precedence() {
+    let dir = unique_dir("precedence");
+    let user_path = dir.join("user.json");
+    write(
+        &user_path,
+        r#"{"model_tiering": {"simple_haiku_threshold": 700, "moderate_sonnet_threshold": 3000}}"#,
+    );
+    let project_dir = dir.join("project");
+    write(
+        &project_dir.join(".claude/swarm-tools/config_override.json"),
+        r#"{"model_tiering": {"simple_haiku_threshold": 900}, "swarm": {"loop_exact_threshold": 5}}"#,
+    );
+
+    let loaded = ConfigLoader::new()
+        .with_user_path(Some(user_path))
+        .with_project_dir(&project_dir)
+        .with_env_vars(vec![("SWARM_TOOLS_SWARM__LOOP_EXACT_THRESHOLD", "7")])
+        .load()
+        .unwrap();
+
+    assert_eq!(loaded.config.model_tiering.simple_haiku_threshold, 900);
+    assert_eq!(loaded.config.model_tiering.moderate_sonnet_threshold, 3000);
+    assert_eq!(loaded.config.swarm.loop_exact_threshold, 7);
+
+    let kinds: Vec<_> = loaded.sources.iter().map(|s| s.kind.clone()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ConfigSourceKind::Defaults,
+            ConfigSourceKind::UserFile,
+            ConfigSourceKind::ProjectFile,
+            ConfigSourceKind::Environment,
+        ]
+    );
+}
+
+#[test]
+fn test_user_file_relocates_project_override() {
+    let dir = unique_dir("relocate");
+    let user_path = dir.join("user.json");
+    write(
+        &user_path,
+        r#"{"shared_config": {"config_dir": "cfg", "override_file": "local.json"}}"#,
+    );
+    let project_dir = dir.join("project");
+    write(
+        &project_dir.join("cfg/local.json"),
+        r#"{"quality_gate": {"minimum_threshold": 55.0}}"#,
+    );
+
+    let loaded = ConfigLoader::new()
+        .with_user_path(Some(user_path))
+        .with_project_dir(&project_dir)
+        .with_env_vars(Vec::<(String, String)>::new())
+        .load()
+        .unwrap();
+
+    assert_eq!(loaded.config.quality_gate.minimum_threshold, 55.0);
+}
+
+#[test]
+fn test_invalid_json_reports_path() {
+    let dir = unique_dir("invalid_json");
+    let user_path = dir.join("broken.json");
+    write(&user_path, "{ not json");
+
+    let err = ConfigLoader::new()
+        .with_user_path(Some(user_path))
+        .with_project_dir(&dir)
+        .with_env_vars(Vec::<(String, String)>::new())
+        .load()
+        .unwrap_err();
+
+    assert!(err.to_string().contains("broken.json"));
+}
+
+#[test]
+fn test_validation_errors_name_every_path() {
+    let dir = unique_dir("validation");
+    let user_path = dir.join("config.json");
+    write(
+        &user_path,
+        r#"{"model_tiering": {"moderate_sonnet_threshold": 500},
+            "communication_patterns": {"redundancy_patterns": [{"pattern": "(oops", "weight": 0.5}]}}"#,
+    );
+
+    let err = ConfigLoader::new()
+        .with_user_path(Some(user_path))
+        .with_project_dir(&dir)
+        .with_env_vars(Vec::<(String, String)>::new())
+        .load()
+        .unwrap_err();
+
+    let ConfigError::Invalid(issues) = &err else {
+        panic!("expected validation error, got {}", err);
+    };
+    let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "model_tiering.moderate_sonnet_threshold",
+            "communication_patterns.redundancy_patterns[0].pattern",
+        ]
+    );
+    assert!(err.to_string().contains("(oops"));
+}
+
+#[test]
+fn test_strict_mode_rejects_misspelled_keys() {
+    let dir = unique_dir("strict");
+    let user_path = dir.join("config.json");
+    write(
+        &user_path,
+        r#"{"$schema": "schema.json", "self_healing": {"prune_treshold": 0.2}}"#,
+    );
+    let loader = ConfigLoader::new()
+        .with_user_path(Some(user_path))
+        .with_project_dir(&dir)
+        .with_env_vars(Vec::<(String, String)>::new());
+
+    let loaded = loader.clone().load().unwrap();
+    assert_eq!(loaded.warnings.len(), 1);
+    assert_eq!(loaded.warnings[0].path, "self_healing.prune_treshold");
+    assert_eq!(loaded.config.self_healing.prune_threshold, 0.3);
+
+    let err = loader.with_strict(true).load().unwrap_err();
+    let ConfigError::Invalid(issues) = &err else {
+        panic!("expected validation error, got {}", err);
+    };
+    assert_eq!(issues[0].suggestion, "did you mean `prune_threshold`?");
+}
+
+fn profiles_loader(dir: &Path) -> ConfigLoader {
+    let user_path = dir.join("config.json");
+    fs::copy("config_examples/profiles.json", &user_path).unwrap();
+    ConfigLoader::new()
+        .with_user_path(Some(user_path))
+        .with_project_dir(dir.join("project"))
+}
+
+#[test]
+fn test_active_profile_applies() {
+    let dir = unique_dir("profile_active");
+    let loaded = profiles_loader(&dir)
+        .with_env_vars(Vec::<(String, String)>::new())
+        .load()
+        .unwrap();
+    assert_eq!(loaded.profile.as_deref(), Some("coding"));
+    let filters = loaded
+        .config
+        .mcp_routing
+        .role_tool_filters
+        .as_ref()
+        .unwrap();
+    assert!(filters["writer"].contains(&"apply_patch".to_string()));
+}
+
+#[test]
+fn test_agent_pattern_pins_profile() {
+    let dir = unique_dir("profile_agent");
+    let loaded = profiles_loader(&dir)
+        .with_env_vars(vec![("SWARM_TOOLS_PROFILE", "large_scale")])
+        .with_agent_id("research-3")
+        .load()
+        .unwrap();
+    assert_eq!(loaded.profile.as_deref(), Some("research"));
+    assert_eq!(loaded.config.model_tiering.simple_haiku_threshold, 1500);
+}
+
+#[test]
+fn test_profile_extends_and_env_wins() {
+    let dir = unique_dir("profile_extends");
+    let loaded = profiles_loader(&dir)
+        .with_env_vars(vec![
+            ("SWARM_TOOLS_PROFILE", "large_scale"),
+            ("SWARM_TOOLS_SELF_HEALING__MIN_ACTIVE_AGENTS", "2"),
+        ])
+        .with_agent_id("coder-1")
+        .load()
+        .unwrap();
+    assert_eq!(loaded.profile.as_deref(), Some("large_scale"));
+    // From large_scale itself
+    assert_eq!(loaded.config.model_tiering.simple_haiku_threshold, 800);
+    assert!(loaded.config.self_healing.auto_prune_enabled);
+    // Inherited from coding
+    let filters = loaded
+        .config
+        .mcp_routing
+        .role_tool_filters
+        .as_ref()
+        .unwrap();
+    assert!(filters["writer"].contains(&"apply_patch".to_string()));
+    // Environment variables still have the last word
+    assert_eq!(loaded.config.self_healing.min_active_agents, 2);
+
+    let kinds: Vec<ConfigSourceKind> = loaded.sources.iter().map(|s| s.kind.clone()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ConfigSourceKind::Defaults,
+            ConfigSourceKind::UserFile,
+            ConfigSourceKind::Profile,
+            ConfigSourceKind::Environment,
+        ]
+    );
+}
+
+#[test]
+fn test_unknown_profile_is_an_error() {
+    let dir = unique_dir("profile_unknown");
+    let err = profiles_loader(&dir)
+        .with_env_vars(Vec::<(String, String)>::new())
+        .with_profile(Some("reserch".to_string()))
+        .load()
+        .unwrap_err();
+    assert!(matches!(err, ConfigError::Profile(_)));
+    assert!(err.to_string().contains("reserch"));
+}
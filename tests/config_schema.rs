@@ -0,0 +1,39 @@
+use std::fs;
+use swarm_tools::config_loader::ConfigLoader;
+use swarm_tools::config_schema::{config_schema_json, SCHEMA_PATH};
+
+/// Regenerate the checked-in schema with `UPDATE_SCHEMA=1 cargo test --test config_schema`.
+#[test]
+fn test_checked_in_schema_is_current() {
+    let generated = config_schema_json();
+    if std::env::var("UPDATE_SCHEMA").is_ok() {
+        fs::create_dir_all("schema").unwrap();
+        fs::write(SCHEMA_PATH, &generated).unwrap();
+    }
+
+    let checked_in = fs::read_to_string(SCHEMA_PATH).unwrap();
+    assert!(
+        checked_in == generated,
+        "{} is out of date; run UPDATE_SCHEMA=1 cargo test --test config_schema",
+        SCHEMA_PATH
+    );
+}
+
+#[test]
+fn test_examples_load_in_strict_mode() {
+    for entry in fs::read_dir("config_examples").unwrap() {
+        let path = entry.unwrap().path();
+        let loaded = ConfigLoader::new()
+            .with_user_path(Some(path.clone()))
+            .with_project_dir("/nonexistent-swarm-tools-project")
+            .with_env_vars(Vec::<(String, String)>::new())
+            .with_strict(true)
+            .load();
+        assert!(
+            loaded.is_ok(),
+            "{}: {}",
+            path.display(),
+            loaded.unwrap_err()
+        );
+    }
+}
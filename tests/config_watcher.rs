@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use swarm_tools::config_loader::ConfigLoader;
+use swarm_tools::config_watcher::{ConfigWatcher, ReloadOutcome};
+
+fn unique_dir(test_name: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_micros();
+    let dir = std::env::temp_dir().join(format!("swarm_tools_{}_{}", test_name, timestamp));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn watcher_for(dir: &Path, user_path: &Path) -> ConfigWatcher {
+    let loader = ConfigLoader::new()
+        .with_user_path(Some(user_path.to_path_buf()))
+        .with_project_dir(dir.join("project"))
+        .with_env_vars(Vec::<(String, String)>::new());
+    ConfigWatcher::new(loader).unwrap()
+}
+
+#[test]
+fn test_reload_applies_new_thresholds() {
+    let dir = unique_dir("watch_reload");
+    let user_path = dir.join("config.json");
+    fs::write(&user_path, r#"{"swarm": {"loop_exact_threshold": 3}}"#).unwrap();
+
+    let mut watcher = watcher_for(&dir, &user_path);
+    let managers = watcher.managers();
+    managers
+        .write()
+        .unwrap()
+        .self_healing
+        .record_contribution("agent1", 0.5);
+    assert!(matches!(watcher.poll(), ReloadOutcome::Unchanged));
+
+    fs::write(
+        &user_path,
+        r#"{"swarm": {"loop_exact_threshold": 6},
+            "self_healing": {"prune_threshold": 0.45},
+            "trajectory_compressor": {"preserve_threshold": 0.5}}"#,
+    )
+    .unwrap();
+    assert!(matches!(watcher.poll(), ReloadOutcome::Reloaded { .. }));
+
+    let live = managers.read().unwrap();
+    assert_eq!(live.config().swarm.loop_exact_threshold, 6);
+    assert_eq!(live.config().self_healing.prune_threshold, 0.45);
+    assert_eq!(live.config().trajectory_compressor.preserve_threshold, 0.5);
+    // State survives the swap
+    assert_eq!(
+        live.self_healing.get_state().recent_contributions["agent1"].len(),
+        1
+    );
+}
+
+#[test]
+fn test_invalid_reload_keeps_previous_config() {
+    let dir = unique_dir("watch_invalid");
+    let user_path = dir.join("config.json");
+    fs::write(
+        &user_path,
+        r#"{"model_tiering": {"simple_haiku_threshold": 900}}"#,
+    )
+    .unwrap();
+
+    let mut watcher = watcher_for(&dir, &user_path);
+    fs::write(
+        &user_path,
+        r#"{"model_tiering": {"simple_haiku_threshold": 9000}}"#,
+    )
+    .unwrap();
+
+    let ReloadOutcome::Rejected(err) = watcher.poll() else {
+        panic!("expected rejection");
+    };
+    assert!(err
+        .to_string()
+        .contains("model_tiering.moderate_sonnet_threshold"));
+
+    let managers = watcher.managers();
+    let live = managers.read().unwrap();
+    assert_eq!(live.model_tierer.get_thresholds(), (900, 5000));
+    drop(live);
+
+    // A broken file is reported once, not on every poll
+    assert!(matches!(watcher.poll(), ReloadOutcome::Unchanged));
+}
+
+#[test]
+fn test_project_override_is_watched() {
+    let dir = unique_dir("watch_project");
+    let user_path = dir.join("config.json");
+    let watcher = watcher_for(&dir, &user_path);
+
+    let paths = watcher.watched_paths();
+    assert_eq!(paths.len(), 2);
+    assert!(paths[1].ends_with(".claude/swarm-tools/config_override.json"));
+}
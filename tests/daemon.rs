@@ -0,0 +1,182 @@
+#![cfg(unix)]
+
+use serde_json::{json, Value};
+use std::fs::{self, Permissions};
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+use swarm_tools::config::SwarmToolsConfig;
+use swarm_tools::daemon::{Daemon, DaemonClient, DaemonError, SOCKET_ENV_VAR};
+use swarm_tools::hook_handlers::{HookOptions, SharedEngines};
+use swarm_tools::hook_io::HookInput;
+
+fn unique_dir(test_name: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_micros();
+    let dir = std::env::temp_dir().join(format!("swarm_tools_{}_{}", test_name, timestamp));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn start_daemon(socket_path: &Path) -> JoinHandle<()> {
+    let engines = SharedEngines::new(None, &SwarmToolsConfig::default());
+    let daemon = Daemon::new(engines, socket_path);
+    let listener = daemon.bind().unwrap();
+    std::thread::spawn(move || Arc::new(daemon).serve(listener).unwrap())
+}
+
+fn post_tool_use(dir: &Path) -> HookInput {
+    let payload = json!({
+        "session_id": "session-1",
+        "cwd": dir,
+        "hook_event_name": "PostToolUse",
+        "tool_name": "Bash",
+        "tool_input": {"command": "cargo build"},
+        "tool_response": {"stdout": "ok"},
+    });
+    HookInput::from_json(&payload.to_string()).unwrap()
+}
+
+#[test]
+fn test_daemon_serves_hook_events() {
+    let dir = unique_dir("daemon_serve");
+    let socket_path = dir.join("daemon.sock");
+    let server = start_daemon(&socket_path);
+    let client = DaemonClient::new(&socket_path);
+
+    let output = client
+        .hook(&post_tool_use(&dir), &HookOptions::default())
+        .unwrap();
+    assert!(!output.is_blocking());
+    assert!(dir
+        .join(".claude/swarm-tools/sessions/session-1/loop-detector/session-1_trajectory.json")
+        .exists());
+
+    let status = client.status().unwrap();
+    assert_eq!(status.pid, std::process::id());
+    assert_eq!(status.requests_served, 2);
+    assert_eq!(status.tracked_agents, 1);
+    assert!(!status.semantic_loaded);
+    assert_eq!(status.embedder, None);
+
+    client.shutdown().unwrap();
+    server.join().unwrap();
+    assert!(!socket_path.exists());
+    assert!(matches!(client.status(), Err(DaemonError::Io(_))));
+}
+
+#[test]
+fn test_bind_replaces_stale_socket_only() {
+    let dir = unique_dir("daemon_bind");
+    let socket_path = dir.join("daemon.sock");
+    drop(UnixListener::bind(&socket_path).unwrap());
+    assert!(socket_path.exists());
+
+    let server = start_daemon(&socket_path);
+    let second = Daemon::new(
+        SharedEngines::new(None, &SwarmToolsConfig::default()),
+        &socket_path,
+    );
+    assert!(matches!(second.bind(), Err(DaemonError::AlreadyRunning(_))));
+
+    DaemonClient::new(&socket_path).shutdown().unwrap();
+    server.join().unwrap();
+}
+
+#[test]
+fn test_hook_command_uses_daemon_and_falls_back() {
+    let dir = unique_dir("daemon_client");
+    let socket_path = dir.join("daemon.sock");
+    let payload = json!({
+        "session_id": "session-1",
+        "cwd": dir,
+        "hook_event_name": "SubagentStop",
+        "stop_hook_active": false,
+    });
+    let run_hook = || {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_swarm-tools"))
+            .args(["hook", "subagent-stop"])
+            .env("XDG_CONFIG_HOME", &dir)
+            .env(SOCKET_ENV_VAR, &socket_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(payload.to_string().as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice::<Value>(&output.stdout).unwrap()
+    };
+
+    // No daemon: a stale socket file is ignored and the event handled in-process.
+    drop(UnixListener::bind(&socket_path).unwrap());
+    fs::set_permissions(&socket_path, Permissions::from_mode(0o600)).unwrap();
+    assert_eq!(run_hook(), json!({"suppressOutput": true}));
+
+    fs::remove_file(&socket_path).unwrap();
+    let server = start_daemon(&socket_path);
+    // A socket other users can connect to is not trusted with the event.
+    fs::set_permissions(&socket_path, Permissions::from_mode(0o666)).unwrap();
+    assert_eq!(run_hook(), json!({"suppressOutput": true}));
+    fs::set_permissions(&socket_path, Permissions::from_mode(0o600)).unwrap();
+    assert_eq!(run_hook(), json!({"suppressOutput": true}));
+    let client = DaemonClient::new(&socket_path);
+    assert_eq!(client.status().unwrap().requests_served, 2);
+
+    client.shutdown().unwrap();
+    server.join().unwrap();
+    assert!(dir
+        .join(".claude/swarm-tools/sessions/session-1/states/session-1.json")
+        .exists());
+}
+
+#[test]
+fn test_bind_uses_private_directory() {
+    let dir = unique_dir("daemon_private");
+    let socket_path = dir.join("run/swarm-tools.sock");
+    let server = start_daemon(&socket_path);
+    let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&dir.join("run")), 0o700);
+    assert_eq!(mode(&socket_path), 0o600);
+    DaemonClient::new(&socket_path).shutdown().unwrap();
+    server.join().unwrap();
+
+    // Anyone could swap the socket in a directory they can write to.
+    fs::set_permissions(dir.join("run"), Permissions::from_mode(0o777)).unwrap();
+    let daemon = Daemon::new(
+        SharedEngines::new(None, &SwarmToolsConfig::default()),
+        &socket_path,
+    );
+    assert!(matches!(daemon.bind(), Err(DaemonError::Untrusted(..))));
+    assert!(!socket_path.exists());
+}
+
+#[test]
+fn test_client_refuses_socket_open_to_other_users() {
+    let dir = unique_dir("daemon_untrusted");
+    let socket_path = dir.join("daemon.sock");
+    let _listener = UnixListener::bind(&socket_path).unwrap();
+    fs::set_permissions(&socket_path, Permissions::from_mode(0o666)).unwrap();
+
+    let client = DaemonClient::new(&socket_path);
+    match client.status() {
+        Err(DaemonError::Untrusted(path, reason)) => {
+            assert_eq!(path, socket_path);
+            assert!(reason.contains("other users"), "{}", reason);
+        }
+        other => panic!("expected an untrusted socket, got {:?}", other),
+    }
+}
@@ -0,0 +1,696 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+use swarm_tools::config::SwarmToolsConfig;
+use swarm_tools::hook_handlers::{self, HookContext, PromptRouting};
+use swarm_tools::hook_io::{HookInput, PermissionDecision};
+use swarm_tools::savings_ledger::SavingsLedger;
+use swarm_tools::session_state::load_session;
+use swarm_tools::types::{AgentRole, TrajectoryLog};
+
+fn unique_dir(test_name: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_micros();
+    let dir = std::env::temp_dir().join(format!("swarm_tools_{}_{}", test_name, timestamp));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_transcript(dir: &Path, prompt: &str) -> PathBuf {
+    let path = dir.join("transcript.jsonl");
+    let lines = [
+        json!({"type": "user", "message": {"role": "user", "content": "earlier prompt"}}),
+        json!({"type": "assistant", "message": {"role": "assistant", "content": [{"type": "text", "text": "ok"}]}}),
+        json!({"type": "user", "message": {"role": "user", "content": [{"type": "text", "text": prompt}]}}),
+        json!({"type": "user", "message": {"role": "user", "content": [{"type": "tool_result", "content": "ignored"}]}}),
+    ];
+    let content: Vec<String> = lines.iter().map(Value::to_string).collect();
+    fs::write(&path, content.join("\n")).unwrap();
+    path
+}
+
+fn event(dir: &Path, fields: Value) -> HookInput {
+    let mut payload = json!({
+        "session_id": "session-1",
+        "transcript_path": dir.join("transcript.jsonl"),
+        "cwd": dir,
+        "permission_mode": "default",
+    });
+    payload
+        .as_object_mut()
+        .unwrap()
+        .extend(fields.as_object().unwrap().clone());
+    HookInput::from_json(&payload.to_string()).unwrap()
+}
+
+#[test]
+fn test_pre_compact_reports_loop() {
+    let dir = unique_dir("hook_precompact");
+    write_transcript(&dir, "fix the failing build");
+    let input = event(
+        &dir,
+        json!({"hook_event_name": "PreCompact", "trigger": "auto", "custom_instructions": ""}),
+    );
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &input);
+
+    let threshold = ctx.config.swarm.loop_exact_threshold;
+    let mut outputs = Vec::new();
+    for _ in 0..threshold {
+        outputs.push(hook_handlers::dispatch(&ctx, &input));
+    }
+
+    assert_eq!(outputs[0].system_message, None);
+    let message = outputs.last().unwrap().system_message.clone().unwrap();
+    assert!(message.contains("loop detected"), "{}", message);
+    assert!(!outputs.last().unwrap().is_blocking());
+    assert!(dir
+        .join(".claude/swarm-tools/sessions/session-1/loop-detector/session-1_history.json")
+        .exists());
+}
+
+#[test]
+fn test_subagent_stop_saves_state_and_checkpoint() {
+    let dir = unique_dir("hook_subagent_stop");
+    let input = event(
+        &dir,
+        json!({"hook_event_name": "SubagentStop", "stop_hook_active": false, "agent_id": "../worker"}),
+    );
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &input);
+
+    let output = hook_handlers::dispatch(&ctx, &input);
+    assert!(!output.is_blocking());
+
+    let state: Value = serde_json::from_str(
+        &fs::read_to_string(
+            dir.join(".claude/swarm-tools/sessions/session-1/states/__worker.json"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(state["status"], "stopped");
+    assert_eq!(state["session_id"], "session-1");
+    assert!(dir
+        .join(".claude/swarm-tools/sessions/session-1/checkpoints/__worker.json")
+        .exists());
+}
+
+#[test]
+fn test_subagent_stop_counts_the_session_trajectory() {
+    let dir = unique_dir("hook_subagent_trajectory");
+    let read = tool_result(
+        &dir,
+        "Read",
+        json!({"file_path": "src/lib.rs"}),
+        json!({"success": true}),
+    );
+    hook_handlers::dispatch(&HookContext::new(SwarmToolsConfig::default(), &read), &read);
+
+    let stop = event(
+        &dir,
+        json!({"hook_event_name": "SubagentStop", "stop_hook_active": false, "agent_id": "worker"}),
+    );
+    hook_handlers::dispatch(&HookContext::new(SwarmToolsConfig::default(), &stop), &stop);
+
+    let checkpoint: Value = serde_json::from_str(
+        &fs::read_to_string(
+            dir.join(".claude/swarm-tools/sessions/session-1/checkpoints/worker.json"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(checkpoint["metadata"]["trajectory_entries"], 1);
+}
+
+#[test]
+fn test_subagent_stop_skips_when_already_continued() {
+    let dir = unique_dir("hook_subagent_active");
+    let input = event(
+        &dir,
+        json!({"hook_event_name": "SubagentStop", "stop_hook_active": true}),
+    );
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &input);
+
+    hook_handlers::dispatch(&ctx, &input);
+    assert!(!dir
+        .join(".claude/swarm-tools/sessions/session-1/states")
+        .exists());
+}
+
+fn tool_call(dir: &Path, tool_name: &str, tool_input: Value) -> HookInput {
+    event(
+        dir,
+        json!({"hook_event_name": "PreToolUse", "tool_name": tool_name, "tool_input": tool_input}),
+    )
+}
+
+#[test]
+fn test_pre_tool_use_without_role_allows_everything() {
+    let dir = unique_dir("hook_tool_no_role");
+    let input = tool_call(&dir, "WebSearch", json!({"query": "rust"}));
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &input);
+
+    let output = hook_handlers::dispatch(&ctx, &input);
+    assert_eq!(serde_json::to_value(&output).unwrap(), json!({}));
+    assert!(SavingsLedger::new(&ctx.state_dir).entries().is_empty());
+}
+
+#[test]
+fn test_pre_tool_use_denies_tool_outside_role() {
+    let dir = unique_dir("hook_tool_deny");
+    let input = tool_call(&dir, "WebSearch", json!({"query": "rust"}));
+    let ctx =
+        HookContext::new(SwarmToolsConfig::default(), &input).with_role(Some(AgentRole::Extractor));
+
+    let output = hook_handlers::dispatch(&ctx, &input);
+    let specific = output.hook_specific_output.unwrap();
+    assert_eq!(specific.hook_event_name, "PreToolUse");
+    assert_eq!(specific.permission_decision, Some(PermissionDecision::Deny));
+    assert!(specific
+        .permission_decision_reason
+        .unwrap()
+        .contains("not in allowed list"));
+
+    let entries = SavingsLedger::new(&ctx.state_dir).entries();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].decision, "deny");
+}
+
+#[test]
+fn test_pre_tool_use_trims_arguments_from_role_file() {
+    let dir = unique_dir("hook_tool_modify");
+    let input = tool_call(
+        &dir,
+        "Read",
+        json!({"file_path": "src/lib.rs", "context": "x".repeat(6000)}),
+    );
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &input);
+    fs::create_dir_all(ctx.role_path().parent().unwrap()).unwrap();
+    fs::write(ctx.role_path(), "extractor\n").unwrap();
+
+    let output = hook_handlers::dispatch(&ctx, &input);
+    let specific = output.hook_specific_output.unwrap();
+    // Trimming must not approve the call on the user's behalf.
+    assert_eq!(specific.permission_decision, Some(PermissionDecision::Ask));
+    assert_eq!(
+        specific.updated_input,
+        Some(json!({"file_path": "src/lib.rs"}))
+    );
+
+    let summary = SavingsLedger::new(&ctx.state_dir).summary();
+    assert_eq!(summary.modified, 1);
+    assert!(summary.tokens_saved > 1000);
+}
+
+fn tool_result(dir: &Path, tool_name: &str, tool_input: Value, tool_response: Value) -> HookInput {
+    event(
+        dir,
+        json!({
+            "hook_event_name": "PostToolUse",
+            "tool_name": tool_name,
+            "tool_input": tool_input,
+            "tool_response": tool_response,
+        }),
+    )
+}
+
+#[test]
+fn test_post_tool_use_records_trajectory() {
+    let dir = unique_dir("hook_post_tool");
+    let write = tool_result(
+        &dir,
+        "Write",
+        json!({"file_path": "a.rs", "content": "fn main() {}"}),
+        json!({"success": true}),
+    );
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &write);
+    let failed = tool_result(
+        &dir,
+        "Bash",
+        json!({"command": "cargo test"}),
+        json!({"stdout": "", "stderr": "error[E0425]", "is_error": true}),
+    );
+    assert_eq!(
+        serde_json::to_value(hook_handlers::dispatch(&ctx, &write)).unwrap(),
+        json!({})
+    );
+    hook_handlers::dispatch(&ctx, &failed);
+
+    let trajectory: TrajectoryLog = serde_json::from_str(
+        &fs::read_to_string(dir.join(
+            ".claude/swarm-tools/sessions/session-1/loop-detector/session-1_trajectory.json",
+        ))
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(trajectory.entries.len(), 2);
+    assert_eq!(
+        trajectory.entries[0].action,
+        r#"Write {"content":"fn main() {}","file_path":"a.rs"}"#
+    );
+    assert!(trajectory.entries[0].succeeded);
+    assert!(!trajectory.entries[1].succeeded);
+    assert!(trajectory.entries[1].impact_score > trajectory.entries[0].impact_score);
+    assert_eq!(
+        trajectory.tokens_used,
+        trajectory
+            .entries
+            .iter()
+            .map(|e| e.tokens_used)
+            .sum::<u32>()
+    );
+}
+
+#[test]
+fn test_post_tool_use_quotes_a_cycle_of_calls() {
+    let dir = unique_dir("hook_post_tool_cycle");
+    let read = tool_result(
+        &dir,
+        "Read",
+        json!({"file_path": "src/lib.rs"}),
+        json!({"type": "text", "file": {"content": "pub mod a;"}}),
+    );
+    let test = tool_result(
+        &dir,
+        "Bash",
+        json!({"command": "cargo test"}),
+        json!({"is_error": true, "stdout": "test result: FAILED"}),
+    );
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &read);
+
+    let outputs: Vec<_> = [&read, &test, &read, &test]
+        .into_iter()
+        .map(|input| hook_handlers::dispatch(&ctx, input))
+        .collect();
+
+    assert!(outputs[..3].iter().all(|output| !output.is_blocking()));
+    let reason = outputs[3].reason.as_deref().unwrap();
+    assert!(reason.contains("loop detected (ActionCycle)"), "{}", reason);
+    assert!(reason.contains("the same 2 steps 2 times"), "{}", reason);
+    assert!(
+        reason.contains("`Bash {\"command\":\"cargo test\"} (failed)`"),
+        "{}",
+        reason
+    );
+}
+
+#[test]
+fn test_post_tool_use_allows_distinct_edit_and_test_rounds() {
+    let dir = unique_dir("hook_post_tool_debugging");
+    let edit = |round: usize| {
+        tool_result(
+            &dir,
+            "Edit",
+            json!({"file_path": "src/lib.rs", "old_string": format!("v{}", round), "new_string": format!("v{}", round + 1)}),
+            json!({"success": true}),
+        )
+    };
+    let test = |round: usize| {
+        tool_result(
+            &dir,
+            "Bash",
+            json!({"command": format!("cargo test case_{}", round)}),
+            json!({"is_error": true, "stdout": "test result: FAILED"}),
+        )
+    };
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &edit(0));
+
+    // Success and failure alternate every round, but no call repeats.
+    for round in 0..6 {
+        for input in [edit(round), test(round)] {
+            let output = hook_handlers::dispatch(&ctx, &input);
+            assert!(!output.is_blocking(), "{:?}", output.reason);
+        }
+    }
+}
+
+#[test]
+fn test_post_tool_use_blocks_repeated_call() {
+    let dir = unique_dir("hook_post_tool_loop");
+    let input = tool_result(
+        &dir,
+        "Read",
+        json!({"file_path": "src/lib.rs"}),
+        json!({"type": "text", "file": {"content": "pub mod a;"}}),
+    );
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &input);
+
+    let outputs: Vec<_> = (0..ctx.config.tool_loops.threshold_for("Read"))
+        .map(|_| hook_handlers::dispatch(&ctx, &input))
+        .collect();
+
+    let (last, earlier) = outputs.split_last().unwrap();
+    assert!(earlier.iter().all(|output| !output.is_blocking()));
+    assert!(last.is_blocking());
+    let reason = last.reason.as_deref().unwrap();
+    assert!(
+        reason.contains("loop detected (ToolCallLoop)"),
+        "{}",
+        reason
+    );
+    assert!(reason.contains("`Read` has now run 5 times"), "{}", reason);
+
+    let trajectory: TrajectoryLog = serde_json::from_str(
+        &fs::read_to_string(dir.join(
+            ".claude/swarm-tools/sessions/session-1/loop-detector/session-1_trajectory.json",
+        ))
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(!trajectory.entries[0].is_repeat);
+    assert!(trajectory.entries[1].is_repeat);
+}
+
+#[test]
+fn test_post_tool_use_ignores_volatile_arguments() {
+    let dir = unique_dir("hook_post_tool_volatile");
+    let run = |description: &str| {
+        tool_result(
+            &dir,
+            "Bash",
+            json!({"description": description, "command": " cargo test "}),
+            json!({"is_error": true, "stdout": "test result: FAILED"}),
+        )
+    };
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &run("Run the tests"));
+
+    let outputs: Vec<_> = ["Run the tests", "Run tests again", "Retry the tests"]
+        .iter()
+        .map(|description| hook_handlers::dispatch(&ctx, &run(description)))
+        .collect();
+
+    assert!(!outputs[1].is_blocking());
+    let reason = outputs[2].reason.as_deref().unwrap();
+    assert!(
+        reason.contains("loop detected (ToolCallLoop)"),
+        "{}",
+        reason
+    );
+}
+
+fn prompt(dir: &Path, text: &str) -> HookInput {
+    event(
+        dir,
+        json!({"hook_event_name": "UserPromptSubmit", "prompt": text}),
+    )
+}
+
+fn injected_context(output: &swarm_tools::hook_io::HookOutput) -> String {
+    let specific = output.hook_specific_output.as_ref().unwrap();
+    assert_eq!(specific.hook_event_name, "UserPromptSubmit");
+    specific.additional_context.clone().unwrap()
+}
+
+#[test]
+fn test_user_prompt_submit_injects_routing_context() {
+    let dir = unique_dir("hook_prompt");
+    let input = prompt(
+        &dir,
+        "Review the authentication module for security issues and write tests",
+    );
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &input);
+
+    let context = injected_context(&hook_handlers::dispatch(&ctx, &input));
+    assert!(context.contains("- Role: "), "{}", context);
+    assert!(context.contains("confidence "), "{}", context);
+    assert!(context.contains("- Team: "), "{}", context);
+    assert!(context.contains("- Model: "), "{}", context);
+    assert!(!ctx.role_path().exists());
+}
+
+#[test]
+fn test_user_prompt_submit_respects_switches() {
+    let dir = unique_dir("hook_prompt_switches");
+    let input = prompt(
+        &dir,
+        "Analyze the performance metrics of the request handler",
+    );
+
+    let mut config = SwarmToolsConfig::default();
+    config.prompt_routing.suggest_team = false;
+    config.prompt_routing.suggest_model = false;
+    config.prompt_routing.include_plan = false;
+    config.prompt_routing.record_role = true;
+    let ctx = HookContext::new(config, &input);
+
+    let context = injected_context(&hook_handlers::dispatch(&ctx, &input));
+    assert!(context.contains("- Role: "));
+    assert!(!context.contains("- Team: "));
+    assert!(!context.contains("- Model: "));
+    assert!(!context.contains("- Plan"));
+    let recorded = fs::read_to_string(ctx.role_path()).unwrap();
+    assert_eq!(ctx.resolve_role().unwrap().as_str(), recorded);
+
+    let mut config = SwarmToolsConfig::default();
+    config.prompt_routing.enabled = false;
+    let ctx = HookContext::new(config, &input);
+    assert_eq!(
+        serde_json::to_value(hook_handlers::dispatch(&ctx, &input)).unwrap(),
+        json!({})
+    );
+}
+
+#[test]
+fn test_recorded_role_filters_later_tool_calls() {
+    let dir = unique_dir("hook_prompt_role_filter");
+    let input = prompt(&dir, "Review this code for security vulnerabilities");
+    let read = tool_call(&dir, "Read", json!({"file_path": "src/lib.rs"}));
+    let edit = tool_call(&dir, "Edit", json!({"file_path": "src/lib.rs"}));
+    let decision = |config: &SwarmToolsConfig, input: &HookInput| {
+        hook_handlers::dispatch(&HookContext::new(config.clone(), input), input)
+            .hook_specific_output
+            .and_then(|specific| specific.permission_decision)
+    };
+
+    // Off by default: the suggestion stays advice and tools are not filtered.
+    let config = SwarmToolsConfig::default();
+    hook_handlers::dispatch(&HookContext::new(config.clone(), &input), &input);
+    assert!(!HookContext::new(config.clone(), &input)
+        .role_path()
+        .exists());
+    assert_eq!(decision(&config, &edit), None);
+
+    let mut config = SwarmToolsConfig::default();
+    config.prompt_routing.record_role = true;
+    hook_handlers::dispatch(&HookContext::new(config.clone(), &input), &input);
+    assert_eq!(
+        HookContext::new(config.clone(), &edit).resolve_role(),
+        Some(AgentRole::Reviewer)
+    );
+    assert_eq!(decision(&config, &read), None);
+    assert_eq!(decision(&config, &edit), Some(PermissionDecision::Deny));
+}
+
+#[test]
+fn test_compound_prompt_is_routed_and_staffed_per_clause() {
+    let dir = unique_dir("hook_prompt_compound");
+    let input = prompt(
+        &dir,
+        "Review this code for security vulnerabilities, and then analyze the performance metrics",
+    );
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &input);
+
+    let context = injected_context(&hook_handlers::dispatch(&ctx, &input));
+    assert!(
+        context.contains("- Roles: reviewer, analyzer"),
+        "{}",
+        context
+    );
+    assert!(
+        context.contains("  - analyzer: analyze the performance metrics"),
+        "{}",
+        context
+    );
+
+    let config = SwarmToolsConfig::default();
+    let routing = PromptRouting::analyze(
+        &config,
+        &config.role_router(),
+        "Review this code for security vulnerabilities, and then analyze the performance metrics",
+    );
+    let team = routing.team.unwrap();
+    assert_eq!(team.roles[0].role, "reviewer");
+    assert_eq!(
+        team.roles[0].primary_tasks,
+        vec!["Review this code for security vulnerabilities".to_string()]
+    );
+    assert_eq!(team.roles[1].role, "analyzer");
+}
+
+#[test]
+fn test_custom_role_is_routed_staffed_and_filtered() {
+    let dir = unique_dir("hook_custom_role");
+    let mut config: SwarmToolsConfig = serde_json::from_value(json!({
+        "custom_roles": {
+            "migration-author": {
+                "description": "Write database schema migrations",
+                "keywords": ["migration", "schema"],
+                "tools": ["Read", "Write"],
+                "model_tier": "opus",
+                "capabilities": {"efficiency": 1.3, "cost_per_hour": 6000, "max_concurrent_tasks": 1}
+            }
+        }
+    }))
+    .unwrap();
+    config.prompt_routing.record_role = true;
+    assert!(config.validate().is_empty(), "{:?}", config.validate());
+
+    let input = prompt(&dir, "Add a schema migration for the orders table");
+    let ctx = HookContext::new(config.clone(), &input);
+    let context = injected_context(&hook_handlers::dispatch(&ctx, &input));
+    assert!(context.contains("- Role: migration-author"), "{}", context);
+    assert!(context.contains("migration-author)"), "{}", context);
+    assert!(
+        context.contains("preferred by role migration-author"),
+        "{}",
+        context
+    );
+    assert_eq!(
+        ctx.resolve_role(),
+        Some(AgentRole::Custom("migration-author".to_string()))
+    );
+
+    let input = tool_call(&dir, "WebSearch", json!({"query": "alembic"}));
+    let ctx = HookContext::new(config.clone(), &input);
+    let specific = hook_handlers::dispatch(&ctx, &input)
+        .hook_specific_output
+        .unwrap();
+    assert_eq!(specific.permission_decision, Some(PermissionDecision::Deny));
+
+    // Without the declaration the recorded role means nothing.
+    let ctx = HookContext::new(SwarmToolsConfig::default(), &input);
+    assert_eq!(ctx.resolve_role(), None);
+}
+
+fn in_session(dir: &Path, session_id: &str, fields: Value) -> HookInput {
+    let mut input = event(dir, fields);
+    input.session_id = session_id.to_string();
+    input
+}
+
+#[test]
+fn test_loop_history_is_scoped_to_session() {
+    let dir = unique_dir("hook_session_scope");
+    let config = SwarmToolsConfig::default();
+    let call = json!({
+        "hook_event_name": "PostToolUse",
+        "tool_name": "Bash",
+        "tool_input": {"command": "cargo test"},
+        "tool_response": {"success": true},
+    });
+
+    // One identical call per session never adds up to a loop.
+    for day in 0..config.swarm.loop_exact_threshold + 1 {
+        let input = in_session(&dir, &format!("day-{}", day), call.clone());
+        let output = hook_handlers::dispatch(&HookContext::new(config.clone(), &input), &input);
+        assert!(!output.is_blocking());
+    }
+}
+
+#[test]
+fn test_session_lifecycle_summary_and_retention() {
+    let dir = unique_dir("hook_session_lifecycle");
+    let mut config = SwarmToolsConfig::default();
+    config.session_retention.max_sessions = 1;
+
+    let old = in_session(
+        &dir,
+        "old",
+        json!({"hook_event_name": "SessionStart", "source": "startup"}),
+    );
+    hook_handlers::dispatch(&HookContext::new(config.clone(), &old), &old);
+    let old_dir = HookContext::new(config.clone(), &old).session_dir;
+    assert!(old_dir.join("session.json").exists());
+
+    let start = in_session(
+        &dir,
+        "current",
+        json!({"hook_event_name": "SessionStart", "source": "startup"}),
+    );
+    let ctx = HookContext::new(config.clone(), &start);
+    hook_handlers::dispatch(&ctx, &start);
+
+    let call = in_session(
+        &dir,
+        "current",
+        json!({
+            "hook_event_name": "PostToolUse",
+            "tool_name": "Bash",
+            "tool_input": {"command": "false"},
+            "tool_response": {"success": false},
+        }),
+    );
+    hook_handlers::dispatch(&ctx, &call);
+
+    let stop = in_session(
+        &dir,
+        "current",
+        json!({"hook_event_name": "Stop", "stop_hook_active": false}),
+    );
+    let output = hook_handlers::dispatch(&ctx, &stop);
+    assert!(!output.is_blocking());
+
+    let record = load_session(&ctx.session_dir).unwrap();
+    assert_eq!(record.source, "startup");
+    assert!(record.last_stopped_at.is_some());
+    let summary = record.summary.unwrap();
+    assert_eq!(summary.tool_calls, 1);
+    assert_eq!(summary.failed_tool_calls, 1);
+
+    assert!(!old_dir.exists());
+    assert!(ctx.session_dir.exists());
+}
+
+#[test]
+fn test_binary_speaks_hook_protocol() {
+    let dir = unique_dir("hook_binary");
+    let payload = json!({
+        "session_id": "session-2",
+        "cwd": dir,
+        "hook_event_name": "SubagentStop",
+        "stop_hook_active": false,
+    });
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_swarm-tools"))
+        .args(["hook", "subagent-stop"])
+        .env("XDG_CONFIG_HOME", &dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(payload.to_string().as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let decision: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(decision, json!({"suppressOutput": true}));
+    assert!(dir
+        .join(".claude/swarm-tools/sessions/session-2/states/session-2.json")
+        .exists());
+}
+
+#[test]
+fn test_binary_rejects_malformed_input() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_swarm-tools"))
+        .args(["hook", "pre-compact"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"not json").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid hook input"));
+}
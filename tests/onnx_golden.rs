@@ -0,0 +1,94 @@
+//! Parity between the ONNX embedder and sentence-transformers.
+//!
+//! `tests/fixtures/minilm-golden.json` holds reference all-MiniLM-L6-v2
+//! embeddings written by `scripts/golden-embeddings.py`. The test needs the
+//! model files (`swarm-tools models fetch`) and ONNX Runtime, so it is ignored
+//! by default; run it with `cargo test --test onnx_golden -- --ignored`.
+#![cfg(feature = "ort")]
+
+use serde::Deserialize;
+use std::path::Path;
+use swarm_tools::embedder::{cosine_similarity, Embedder};
+use swarm_tools::feature_config::ModelStoreConfig;
+use swarm_tools::model_store::ModelStore;
+use swarm_tools::semantic_engine::{EmbeddingBackend, SemanticEngine};
+
+#[derive(Deserialize)]
+struct Fixture {
+    max_seq_length: usize,
+    cases: Vec<Case>,
+}
+
+#[derive(Deserialize)]
+struct Case {
+    text: String,
+    embedding: Vec<f32>,
+}
+
+#[test]
+#[ignore = "needs the provisioned model and ONNX Runtime"]
+fn test_onnx_matches_sentence_transformers() {
+    let store = ModelStore::resolve(&ModelStoreConfig::default());
+    assert!(
+        store.dir().join("model.onnx").exists(),
+        "no model.onnx in {}; run `swarm-tools models fetch`",
+        store.dir().display()
+    );
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/minilm-golden.json");
+    let fixture: Fixture = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+    let mut engine = SemanticEngine::with_path(store.dir().to_path_buf())
+        .with_max_seq_length(fixture.max_seq_length);
+    engine.initialize().unwrap();
+    assert_eq!(
+        engine.backend(),
+        EmbeddingBackend::Onnx,
+        "ONNX model not loaded from {}: {:?}",
+        store.dir().display(),
+        engine.load_warnings()
+    );
+
+    for case in &fixture.cases {
+        assert!(
+            !case.embedding.is_empty(),
+            "no reference embedding for {:?}; run scripts/golden-embeddings.py",
+            case.text
+        );
+        let embedding = engine.embed(&case.text).unwrap();
+        assert_eq!(embedding.len(), case.embedding.len());
+
+        let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!(
+            (norm - 1.0).abs() < 1e-4,
+            "norm {} for {:?}",
+            norm,
+            case.text
+        );
+        let max_diff = embedding
+            .iter()
+            .zip(&case.embedding)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0f32, f32::max);
+        assert!(
+            max_diff < 1e-4 && cosine_similarity(&embedding, &case.embedding) > 0.9999,
+            "{:?} differs from the reference by up to {}",
+            case.text,
+            max_diff
+        );
+    }
+
+    // Padding a batch to its longest text must not change any embedding.
+    let texts: Vec<&str> = fixture
+        .cases
+        .iter()
+        .map(|case| case.text.as_str())
+        .collect();
+    let batch = engine.embed_batch(&texts).unwrap();
+    for (case, embedding) in fixture.cases.iter().zip(&batch) {
+        assert!(
+            cosine_similarity(embedding, &case.embedding) > 0.9999,
+            "{:?} differs from the reference when batched",
+            case.text
+        );
+    }
+}